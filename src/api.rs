@@ -1,4 +1,15 @@
+pub mod agencies;
+pub mod blocks;
 pub mod config;
+pub mod fares;
+pub mod feeds;
+pub mod graphql;
+pub mod nodes;
+pub mod offices;
+pub mod openapi;
+pub mod routes;
+pub mod service_routes;
+pub mod stations;
 pub mod stop_time_details;
 pub mod stops;
 pub mod trips;
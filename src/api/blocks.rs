@@ -0,0 +1,28 @@
+use rocket::State;
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::api::utils::feeds::Feeds;
+
+use crate::api::utils::queries::DateQuery;
+use crate::app::block::{Block, BlockServiceDb};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Response {
+    item: Block,
+}
+
+#[get("/<key>/blocks/<block_id>?<date>")]
+pub fn index(
+    feeds: State<Feeds>,
+    key: String,
+    block_id: String,
+    date: DateQuery,
+) -> Option<Json<Response>> {
+    // TODO: Remove unwrap
+    let gtfs = feeds.open(&key).unwrap()?;
+    let block = BlockServiceDb::new(gtfs)
+        .fetch_block(date.unwrap(), block_id)
+        .unwrap();
+    block.map(|item| Json(Response { item }))
+}
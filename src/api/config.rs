@@ -1,14 +1,19 @@
+use rocket::State;
 use rocket_contrib::json::Json;
 use serde::{Deserialize, Serialize};
 
+use crate::api::utils::feeds::{FeedVersion, Feeds};
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Response {
     version: String,
+    feeds: Vec<FeedVersion>,
 }
 
 #[get("/")]
-pub fn index() -> Json<Response> {
+pub fn index(feeds: State<Feeds>) -> Json<Response> {
     Json(Response {
         version: clap::crate_version!().into(),
+        feeds: feeds.versions(),
     })
 }
@@ -0,0 +1,39 @@
+use rocket::request::LenientForm;
+use rocket::response::status::BadRequest;
+use rocket::State;
+use rocket_contrib::json::Json;
+
+use crate::api::utils::feeds::Feeds;
+use crate::api::utils::pages::{Page, PageQuery};
+
+use crate::app::fare::FareServiceDb;
+use crate::external::gtfs::extended::leg_fares::LEG_FARE_SORT_COLUMNS;
+
+#[get("/<key>/fares?<from_stop_id>&<to_stop_id>&<route_id>&<page..>")]
+pub fn index(
+    feeds: State<Feeds>,
+    key: String,
+    from_stop_id: String,
+    to_stop_id: String,
+    route_id: Option<String>,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(LEG_FARE_SORT_COLUMNS)?;
+    // TODO: Remove unwrap
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = FareServiceDb::new(gtfs);
+        let fares = service
+            .fetch_leg_fares(
+                from_stop_id.clone(),
+                to_stop_id.clone(),
+                route_id.clone(),
+                &paging,
+            )
+            .unwrap();
+        let total = service
+            .count_leg_fares(from_stop_id, to_stop_id, route_id)
+            .unwrap();
+        Json(page.apply_paged(fares, total).unwrap())
+    }))
+}
@@ -0,0 +1,21 @@
+use std::collections::BTreeMap;
+
+use rocket::State;
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::api::utils::feeds::{FeedVersion, Feeds};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Response {
+    items: Vec<FeedVersion>,
+    aliases: BTreeMap<String, String>,
+}
+
+#[get("/feeds")]
+pub fn index(feeds: State<Feeds>) -> Json<Response> {
+    Json(Response {
+        items: feeds.versions(),
+        aliases: feeds.aliases().clone(),
+    })
+}
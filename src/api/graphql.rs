@@ -0,0 +1,18 @@
+use rocket::State;
+use rocket_contrib::json::Json;
+
+use crate::api::utils::feeds::Feeds;
+
+use crate::app::graphql::{GraphqlLimits, GraphqlRequest, GraphqlResponse, GraphqlServiceDb};
+
+#[post("/<key>/graphql", format = "json", data = "<request>")]
+pub fn index(
+    feeds: State<Feeds>,
+    limits: State<GraphqlLimits>,
+    key: String,
+    request: Json<GraphqlRequest>,
+) -> Option<Json<GraphqlResponse>> {
+    // TODO: Remove unwrap
+    let gtfs = feeds.open(&key).unwrap()?;
+    Some(Json(GraphqlServiceDb::new(gtfs).execute(&request, &limits)))
+}
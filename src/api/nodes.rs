@@ -0,0 +1,80 @@
+use rocket::request::LenientForm;
+use rocket::response::status::BadRequest;
+use rocket::State;
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::api::utils::feeds::Feeds;
+use crate::api::utils::pages::{Page, PageQuery};
+
+use crate::api::utils::queries::DateQuery;
+use crate::app::node::{NodeServiceDb, NodeWithDetails};
+
+use crate::external::gtfs::extended::node_details::{
+    NODE_DEPARTURE_SORT_COLUMNS, NODE_SORT_COLUMNS,
+};
+use crate::external::gtfs::extended::nodes::NodeId;
+use crate::external::gtfs::{GtfsTime, OperatorScope};
+
+#[get("/<key>/nodes?<word>&<page..>")]
+pub fn index(
+    feeds: State<Feeds>,
+    key: String,
+    word: Option<String>,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(NODE_SORT_COLUMNS)?;
+    // TODO: Remove unwrap
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = NodeServiceDb::new(gtfs);
+        let nodes = service.fetch_nodes(word.clone(), &paging).unwrap();
+        let total = service.count_nodes(word).unwrap();
+        Json(page.apply_paged(nodes, total).unwrap())
+    }))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DetailResponse {
+    item: NodeWithDetails,
+}
+
+#[get("/<key>/nodes/<node_id>")]
+pub fn detail(feeds: State<Feeds>, key: String, node_id: NodeId) -> Option<Json<DetailResponse>> {
+    // TODO: Remove unwrap
+    let gtfs = feeds.open(&key).unwrap()?;
+    let node = NodeServiceDb::new(gtfs).fetch_node(node_id).unwrap();
+    node.map(|item| Json(DetailResponse { item }))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[get("/<key>/nodes/<node_id>/departures?<date>&<from>&<agency_id>&<office_id>&<page..>")]
+pub fn departures(
+    feeds: State<Feeds>,
+    key: String,
+    node_id: NodeId,
+    date: Option<DateQuery>,
+    from: Option<GtfsTime>,
+    agency_id: Option<String>,
+    office_id: Option<String>,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(NODE_DEPARTURE_SORT_COLUMNS)?;
+    let date = date.map(|x| x.unwrap());
+    let scope = OperatorScope {
+        agency_id,
+        office_id,
+    };
+    // TODO: Remove unwrap
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = NodeServiceDb::new(gtfs);
+        let departures = service
+            .fetch_departures(node_id, date, from, &scope, &paging)
+            .unwrap();
+        let total = service
+            .count_departures(node_id, date, from, &scope)
+            .unwrap();
+        Json(page.apply_paged(departures, total).unwrap())
+    }))
+}
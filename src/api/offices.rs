@@ -0,0 +1,27 @@
+use rocket::request::LenientForm;
+use rocket::response::status::BadRequest;
+use rocket::State;
+use rocket_contrib::json::Json;
+
+use crate::api::utils::feeds::Feeds;
+use crate::api::utils::pages::{Page, PageQuery};
+
+use crate::app::operator::OperatorServiceDb;
+use crate::external::gtfs::extended::office_details::OFFICE_DETAIL_SORT_COLUMNS;
+
+#[get("/<key>/offices?<page..>")]
+pub fn index(
+    feeds: State<Feeds>,
+    key: String,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(OFFICE_DETAIL_SORT_COLUMNS)?;
+    // TODO: Remove unwrap
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = OperatorServiceDb::new(gtfs);
+        let offices = service.fetch_offices(&paging).unwrap();
+        let total = service.count_offices().unwrap();
+        Json(page.apply_paged(offices, total).unwrap())
+    }))
+}
@@ -0,0 +1,444 @@
+use std::lazy::SyncOnceCell;
+
+use itertools::Itertools;
+use rocket_contrib::json::Json;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use strum::VariantNames;
+
+use crate::api::utils::feeds::FeedVersion;
+use crate::api::utils::schemas::Schemas;
+use crate::app::block::Block;
+use crate::app::service_route::ServiceRoutePattern;
+use crate::app::stops::StationStructure;
+use crate::external::gtfs::extended::agency_details::AgencyDetail;
+use crate::external::gtfs::extended::leg_fares::LegFare;
+use crate::external::gtfs::extended::nearby_stops::NearbyStop;
+use crate::external::gtfs::extended::node_details::NodeDeparture;
+use crate::external::gtfs::extended::nodes::Node;
+use crate::external::gtfs::extended::office_details::OfficeDetail;
+use crate::external::gtfs::extended::route_details::RouteDetail;
+use crate::external::gtfs::extended::service_route_details::ServiceRouteTrip;
+use crate::external::gtfs::extended::service_routes::ServiceRoute;
+use crate::external::gtfs::extended::stop_time_details::StopTimeDetail;
+use crate::external::gtfs::routes::{Route, RouteType, TransitMode};
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::trips::Trip;
+
+/// スキーマの導出は型ごとに読みこみを繰り返すので、最初の要求で作ったドキュメントを使い回す
+static SPEC: SyncOnceCell<Value> = SyncOnceCell::new();
+
+#[get("/openapi.json")]
+pub fn index() -> Json<Value> {
+    Json(SPEC.get_or_init(spec).clone())
+}
+
+/// `cmd::serve::mount`でmountするすべてのAPIのOpenAPI 3ドキュメント
+pub fn spec() -> Value {
+    let mut spec = Spec::default();
+    // route_typeは未定義のコードもそのまま返すため列挙型にならない
+    spec.schemas.annotate(
+        "route_type",
+        json!({
+            "description": format!(
+                "経路タイプ. 定義済みのコード: {}",
+                RouteType::CODES.iter().join(", ")
+            ),
+        }),
+    );
+
+    let feed_version = spec.schemas.schema_of::<FeedVersion>();
+    spec.add(
+        "/config",
+        "設定の取得",
+        vec![],
+        ok(object(vec![
+            ("version", json!({"type": "string"})),
+            ("feeds", array(feed_version.clone())),
+        ])),
+    );
+    spec.add(
+        "/feeds",
+        "有効なデータベースの取得",
+        vec![],
+        ok(object(vec![
+            ("items", array(feed_version)),
+            (
+                "aliases",
+                json!({"type": "object", "additionalProperties": {"type": "string"}}),
+            ),
+        ])),
+    );
+    spec.add(
+        "/openapi.json",
+        "OpenAPIドキュメントの取得",
+        vec![],
+        ok(json!({"type": "object"})),
+    );
+
+    spec.add_list::<Stop>(
+        "/{key}/stops",
+        "stopの取得",
+        vec![query(
+            "word",
+            string(),
+            true,
+            "stop_nameで部分一致検索する文字列",
+        )],
+    );
+    spec.add_list::<NearbyStop>(
+        "/{key}/stops/nearby",
+        "近くのstopの取得",
+        vec![
+            query("lat", number(), true, "緯度"),
+            query("lon", number(), true, "経度"),
+            query("radius", number(), false, "検索半径(メートル). 省略時は500"),
+        ],
+    );
+    spec.add_list::<Node>(
+        "/{key}/nodes",
+        "nodeの取得",
+        vec![query(
+            "word",
+            string(),
+            false,
+            "node_nameまたはnode_rubyで部分一致検索する",
+        )],
+    );
+    let node = spec.schemas.schema_of::<Node>();
+    let stop = spec.schemas.schema_of::<Stop>();
+    let route = spec.schemas.schema_of::<Route>();
+    let service_route = spec.schemas.schema_of::<ServiceRoute>();
+    spec.add(
+        "/{key}/nodes/{node_id}",
+        "nodeと詳細の取得",
+        vec![path("node_id", integer())],
+        detail(json!({
+            "allOf": [
+                node,
+                object(vec![
+                    ("stops", array(stop)),
+                    ("routes", array(route)),
+                    ("service_routes", array(service_route)),
+                ]),
+            ]
+        })),
+    );
+    spec.add_list::<NodeDeparture>(
+        "/{key}/nodes/{node_id}/departures",
+        "nodeからの出発の取得",
+        vec![
+            path("node_id", integer()),
+            query("date", date(), false, "運行日で絞込"),
+            query("from", time(), false, "この時刻以降のみ"),
+            query("agency_id", string(), false, "事業者で絞込"),
+            query("office_id", string(), false, "営業所で絞込"),
+        ],
+    );
+    let trip = spec.schemas.schema_of::<Trip>();
+    let stop_time = spec.schemas.schema_of::<StopTime>();
+    spec.add_list_of(
+        "/{key}/trips",
+        "tripの取得",
+        vec![
+            query("stop_id", string(), false, "停車するstop_idで絞込"),
+            query(
+                "mode",
+                json!({"type": "string", "enum": TransitMode::VARIANTS}),
+                false,
+                "交通モードで絞込",
+            ),
+            query("agency_id", string(), false, "事業者で絞込"),
+            query("office_id", string(), false, "営業所で絞込"),
+            query("route_id", string(), false, "経路で絞込"),
+            query("service_id", string(), false, "運行日IDで絞込"),
+            query("date", date(), false, "運行日で絞込"),
+            query("direction_id", direction_id(), false, "上下区分で絞込"),
+            query("service_route_id", integer(), false, "サービスルートで絞込"),
+            query(
+                "from",
+                time(),
+                false,
+                "出発時刻がこの時刻以降のみ (stop_id指定時はそのstop、それ以外は始発)",
+            ),
+            query("to", time(), false, "出発時刻がこの時刻以前のみ"),
+            query(
+                "with_stop_times",
+                json!({"type": "boolean"}),
+                false,
+                "trueの場合はstop_timesを含める",
+            ),
+        ],
+        json!({
+            "allOf": [
+                trip,
+                {"type": "object", "properties": {"stop_times": array(stop_time)}},
+            ]
+        }),
+    );
+    spec.add_list::<RouteDetail>(
+        "/{key}/routes",
+        "routeと詳細の取得",
+        vec![
+            query(
+                "word",
+                string(),
+                false,
+                "route_short_nameまたはroute_long_nameで部分一致検索する",
+            ),
+            query(
+                "mode",
+                json!({"type": "string", "enum": TransitMode::VARIANTS}),
+                false,
+                "交通モードで絞込",
+            ),
+            query("agency_id", string(), false, "事業者で絞込"),
+            query("office_id", string(), false, "営業所で絞込"),
+        ],
+    );
+    let route_detail = spec.schemas.schema_of::<RouteDetail>();
+    let stop = spec.schemas.schema_of::<Stop>();
+    let service_route = spec.schemas.schema_of::<ServiceRoute>();
+    spec.add(
+        "/{key}/routes/{route_id}",
+        "routeと停車するstop、サービスルートの取得",
+        vec![path("route_id", string())],
+        detail(json!({
+            "allOf": [
+                route_detail,
+                object(vec![
+                    ("stops", array(stop)),
+                    ("service_routes", array(service_route)),
+                ]),
+            ]
+        })),
+    );
+    spec.add_list::<AgencyDetail>("/{key}/agencies", "事業者の取得", vec![]);
+    spec.add_list::<OfficeDetail>("/{key}/offices", "営業所の取得", vec![]);
+    spec.add_list::<ServiceRoutePattern>(
+        "/{key}/service_routes",
+        "サービスルートの取得",
+        vec![
+            query("route_id", string(), false, "経路で絞込"),
+            query("stop_id", string(), false, "停車するstop_idで絞込"),
+            query("direction_id", direction_id(), false, "上下区分で絞込"),
+        ],
+    );
+    let pattern = spec.schemas.schema_of::<ServiceRoutePattern>();
+    let trip = spec.schemas.schema_of::<ServiceRouteTrip>();
+    spec.add(
+        "/{key}/service_routes/{service_route_id}/{direction_id}",
+        "サービスルートとtripの取得",
+        vec![
+            path("service_route_id", integer()),
+            path("direction_id", direction_id()),
+        ],
+        detail(json!({
+            "allOf": [pattern, object(vec![("trips", array(trip))])]
+        })),
+    );
+    let station = spec.schemas.schema_of::<StationStructure>();
+    spec.add(
+        "/{key}/stations/{stop_id}",
+        "駅構内構造の取得",
+        vec![path("stop_id", string())],
+        detail(station),
+    );
+    spec.add_list::<LegFare>(
+        "/{key}/fares",
+        "区間運賃の取得",
+        vec![
+            query("from_stop_id", string(), true, "乗車するstop_id"),
+            query("to_stop_id", string(), true, "降車するstop_id"),
+            query("route_id", string(), false, "経路で絞込"),
+        ],
+    );
+    let block = spec.schemas.schema_of::<Block>();
+    spec.add(
+        "/{key}/blocks/{block_id}",
+        "運用の取得",
+        vec![
+            path("block_id", string()),
+            query("date", date(), true, "運行日"),
+        ],
+        detail(block),
+    );
+    spec.add_list::<StopTimeDetail>(
+        "/{key}/stop_time_details",
+        "stop_timeと詳細の取得",
+        vec![
+            query("trip_ids", string(), false, "trip_idで絞込 (カンマ区切り)"),
+            query(
+                "stop_name_prefix",
+                string(),
+                false,
+                "stop_nameで前方一致検索する文字列",
+            ),
+            query("agency_id", string(), false, "事業者で絞込"),
+            query("office_id", string(), false, "営業所で絞込"),
+        ],
+    );
+
+    spec.paths.insert(
+        "/{key}/graphql".into(),
+        json!({
+            "post": {
+                "summary": "GraphQL (serve --graphqlで有効)",
+                "parameters": [{"$ref": "#/components/parameters/key"}],
+                "requestBody": {
+                    "required": true,
+                    "content": {"application/json": {"schema": object(vec![
+                        ("query", string()),
+                        ("variables", json!({"type": "object", "nullable": true})),
+                    ])}},
+                },
+                "responses": ok(json!({
+                    "type": "object",
+                    "properties": {
+                        "data": {"type": "object", "nullable": true},
+                        "errors": array(object(vec![("message", string())])),
+                    },
+                })),
+            }
+        }),
+    );
+
+    spec.into_document()
+}
+
+#[derive(Default)]
+struct Spec {
+    schemas: Schemas,
+    paths: Map<String, Value>,
+}
+
+impl Spec {
+    /// `/{key}`から始まるパスはkeyのパラメータを先頭に加える
+    fn add(&mut self, path: &str, summary: &str, mut parameters: Vec<Value>, responses: Value) {
+        if path.starts_with("/{key}") {
+            parameters.insert(0, json!({"$ref": "#/components/parameters/key"}));
+        }
+        self.paths.insert(
+            path.to_string(),
+            json!({
+                "get": {
+                    "summary": summary,
+                    "parameters": parameters,
+                    "responses": responses,
+                }
+            }),
+        );
+    }
+
+    /// ページング・並び替え・項目選択ができる一覧API
+    fn add_list<T: DeserializeOwned>(&mut self, path: &str, summary: &str, parameters: Vec<Value>) {
+        let item = self.schemas.schema_of::<T>();
+        self.add_list_of(path, summary, parameters, item);
+    }
+
+    /// 要素のスキーマを指定する一覧API
+    fn add_list_of(&mut self, path: &str, summary: &str, mut parameters: Vec<Value>, item: Value) {
+        parameters.extend(
+            ["limit", "offset", "sort", "fields"]
+                .iter()
+                .map(|x| json!({ "$ref": format!("#/components/parameters/{}", x) })),
+        );
+        let mut responses = ok(object(vec![
+            ("items", array(item)),
+            ("total", integer()),
+            ("offset", integer()),
+            ("limit", json!({"type": "integer", "nullable": true})),
+        ]));
+        responses["400"] = json!({"description": "クエリの値を解釈できない、または並び替えられない項目をsortに指定した"});
+        self.add(path, summary, parameters, responses);
+    }
+
+    fn into_document(self) -> Value {
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "diamant",
+                "version": clap::crate_version!(),
+            },
+            "paths": self.paths,
+            "components": {
+                "schemas": self.schemas.into_components(),
+                "parameters": {
+                    "key": path("key", string()),
+                    "limit": query("limit", integer(), false, "取得する最大件数. 省略時はすべて"),
+                    "offset": query("offset", integer(), false, "読み飛ばす件数"),
+                    "sort": query("sort", string(), false, "並び替える項目 (カンマ区切り). -を前置すると降順. 並び替えられる項目はAPIごとに決まっている"),
+                    "fields": query("fields", string(), false, "取得する項目 (カンマ区切り). 省略時はすべて"),
+                },
+            },
+        })
+    }
+}
+
+fn ok(schema: Value) -> Value {
+    json!({
+        "200": {
+            "description": "OK",
+            "content": {"application/json": {"schema": schema}},
+        }
+    })
+}
+
+/// 存在しない場合は404を返す詳細API
+fn detail(item: Value) -> Value {
+    let mut responses = ok(object(vec![("item", item)]));
+    responses["404"] = json!({"description": "Not Found"});
+    responses
+}
+
+fn object(properties: Vec<(&str, Value)>) -> Value {
+    let required = properties.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+    json!({
+        "type": "object",
+        "properties": properties.into_iter().map(|(k, v)| (k.to_string(), v)).collect::<Map<_, _>>(),
+        "required": required,
+    })
+}
+
+fn array(items: Value) -> Value {
+    json!({"type": "array", "items": items})
+}
+
+fn path(name: &str, schema: Value) -> Value {
+    json!({"name": name, "in": "path", "required": true, "schema": schema})
+}
+
+fn query(name: &str, schema: Value, required: bool, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": required,
+        "description": description,
+        "schema": schema,
+    })
+}
+
+fn string() -> Value {
+    json!({"type": "string"})
+}
+
+fn integer() -> Value {
+    json!({"type": "integer"})
+}
+
+fn number() -> Value {
+    json!({"type": "number"})
+}
+
+fn date() -> Value {
+    json!({"type": "string", "pattern": "^[0-9]{8}$", "example": "20210510"})
+}
+
+fn time() -> Value {
+    json!({"type": "string", "pattern": "^[0-9]{1,2}:[0-9]{2}:[0-9]{2}$", "example": "08:00:00"})
+}
+
+fn direction_id() -> Value {
+    json!({"type": "integer", "enum": [0, 1]})
+}
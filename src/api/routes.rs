@@ -0,0 +1,57 @@
+use rocket::http::RawStr;
+use rocket::request::LenientForm;
+use rocket::response::status::BadRequest;
+use rocket::State;
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::api::utils::feeds::Feeds;
+use crate::api::utils::pages::{Page, PageQuery};
+use crate::api::utils::queries::valid;
+use crate::app::route::{RouteServiceDb, RouteWithDetails};
+use crate::external::gtfs::extended::route_details::ROUTE_DETAIL_SORT_COLUMNS;
+use crate::external::gtfs::routes::TransitMode;
+use crate::external::gtfs::OperatorScope;
+
+#[get("/<key>/routes?<word>&<mode>&<agency_id>&<office_id>&<page..>")]
+pub fn index(
+    feeds: State<Feeds>,
+    key: String,
+    word: Option<String>,
+    mode: Option<Result<TransitMode, &RawStr>>,
+    agency_id: Option<String>,
+    office_id: Option<String>,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(ROUTE_DETAIL_SORT_COLUMNS)?;
+    let mode = valid("mode", mode)?;
+    let scope = OperatorScope {
+        agency_id,
+        office_id,
+    };
+    // TODO: Remove unwrap
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = RouteServiceDb::new(gtfs);
+        let routes = service
+            .fetch_route_details(word.clone(), mode.as_ref(), &scope, &paging)
+            .unwrap();
+        let total = service
+            .count_route_details(word, mode.as_ref(), &scope)
+            .unwrap();
+        Json(page.apply_paged(routes, total).unwrap())
+    }))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DetailResponse {
+    item: RouteWithDetails,
+}
+
+#[get("/<key>/routes/<route_id>")]
+pub fn detail(feeds: State<Feeds>, key: String, route_id: String) -> Option<Json<DetailResponse>> {
+    // TODO: Remove unwrap
+    let gtfs = feeds.open(&key).unwrap()?;
+    let route = RouteServiceDb::new(gtfs).fetch_route(route_id).unwrap();
+    route.map(|item| Json(DetailResponse { item }))
+}
@@ -0,0 +1,62 @@
+use rocket::request::LenientForm;
+use rocket::response::status::BadRequest;
+use rocket::State;
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::api::utils::feeds::Feeds;
+use crate::api::utils::pages::{Page, PageQuery};
+
+use crate::app::service_route::{ServiceRouteServiceDb, ServiceRouteWithTrips};
+use crate::external::gtfs::extended::service_route_details::SERVICE_ROUTE_SORT_COLUMNS;
+use crate::external::gtfs::extended::service_routes::ServiceRouteId;
+use crate::external::gtfs::DirectionId;
+
+#[get("/<key>/service_routes?<route_id>&<stop_id>&<direction_id>&<page..>")]
+pub fn index(
+    feeds: State<Feeds>,
+    key: String,
+    route_id: Option<String>,
+    stop_id: Option<String>,
+    direction_id: Option<DirectionId>,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(SERVICE_ROUTE_SORT_COLUMNS)?;
+    // TODO: Remove unwrap
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = ServiceRouteServiceDb::new(gtfs);
+        let service_routes = service
+            .fetch_service_routes(
+                route_id.clone(),
+                stop_id.clone(),
+                direction_id.clone(),
+                &paging,
+            )
+            .unwrap();
+        let total = service
+            .count_service_routes(route_id, stop_id, direction_id)
+            .unwrap();
+        Json(page.apply_paged(service_routes, total).unwrap())
+    }))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DetailResponse {
+    item: ServiceRouteWithTrips,
+}
+
+#[get("/<key>/service_routes/<service_route_id>/<direction_id>")]
+pub fn detail(
+    feeds: State<Feeds>,
+    key: String,
+    service_route_id: ServiceRouteId,
+    direction_id: DirectionId,
+) -> Option<Json<DetailResponse>> {
+    // TODO: Remove unwrap
+    let gtfs = feeds.open(&key).unwrap()?;
+    let service_route = ServiceRouteServiceDb::new(gtfs)
+        .fetch_service_route(service_route_id, direction_id)
+        .unwrap();
+    service_route.map(|item| Json(DetailResponse { item }))
+}
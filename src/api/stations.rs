@@ -0,0 +1,22 @@
+use rocket::State;
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::api::utils::feeds::Feeds;
+
+use crate::app::stops::{StationStructure, StopServiceDb};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Response {
+    item: StationStructure,
+}
+
+#[get("/<key>/stations/<stop_id>")]
+pub fn index(feeds: State<Feeds>, key: String, stop_id: String) -> Option<Json<Response>> {
+    // TODO: Remove unwrap
+    let gtfs = feeds.open(&key).unwrap()?;
+    let structure = StopServiceDb::new(gtfs)
+        .fetch_station_structure(stop_id)
+        .unwrap();
+    structure.map(|item| Json(Response { item }))
+}
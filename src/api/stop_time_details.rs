@@ -1,28 +1,43 @@
+use rocket::request::LenientForm;
+use rocket::response::status::BadRequest;
+use rocket::State;
 use rocket_contrib::json::Json;
-use serde::{Deserialize, Serialize};
+
+use crate::api::utils::feeds::Feeds;
+use crate::api::utils::pages::{Page, PageQuery};
 
 use crate::api::utils::queries::CommaSeparatedValues;
 use crate::app::stop_time::StopTimeServiceDb;
-use crate::external::gtfs::extended::stop_time_details::StopTimeDetail;
-use crate::external::gtfsdb::GtfsDb;
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Response {
-    items: Vec<StopTimeDetail>,
-}
+use crate::external::gtfs::extended::stop_time_details::STOP_TIME_DETAIL_SORT_COLUMNS;
+use crate::external::gtfs::OperatorScope;
 
-#[get("/<key>/stop_time_details?<trip_ids>&<stop_name_prefix>")]
+#[get("/<key>/stop_time_details?<trip_ids>&<stop_name_prefix>&<agency_id>&<office_id>&<page..>")]
 pub fn index(
+    feeds: State<Feeds>,
     key: String,
     trip_ids: Option<CommaSeparatedValues>,
     stop_name_prefix: Option<String>,
-) -> Json<Response> {
+    agency_id: Option<String>,
+    office_id: Option<String>,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(STOP_TIME_DETAIL_SORT_COLUMNS)?;
+    let trip_ids = trip_ids.map(|x| x.unwrap());
+    let scope = OperatorScope {
+        agency_id,
+        office_id,
+    };
     // TODO: Remove unwrap
-    let gtfs = GtfsDb::new(GtfsDb::get_default_path(key).as_path()).unwrap();
-    let stop_time_details = StopTimeServiceDb::new(gtfs)
-        .fetch_stop_time_details(trip_ids.map(|x| x.unwrap()), stop_name_prefix)
-        .unwrap();
-    Json(Response {
-        items: stop_time_details,
-    })
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = StopTimeServiceDb::new(gtfs);
+        let stop_time_details = service
+            .fetch_stop_time_details(trip_ids.clone(), stop_name_prefix.clone(), &scope, &paging)
+            .unwrap();
+        let total = service
+            .count_stop_time_details(trip_ids, stop_name_prefix, &scope)
+            .unwrap();
+        Json(page.apply_paged(stop_time_details, total).unwrap())
+    }))
 }
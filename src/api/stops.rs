@@ -1,19 +1,55 @@
+use rocket::request::LenientForm;
+use rocket::response::status::BadRequest;
+use rocket::State;
 use rocket_contrib::json::Json;
-use serde::{Deserialize, Serialize};
+
+use crate::api::utils::feeds::Feeds;
+use crate::api::utils::pages::{Page, PageQuery};
 
 use crate::app::stops::StopServiceDb;
-use crate::external::gtfs::stops::Stop;
-use crate::external::gtfsdb::GtfsDb;
+use crate::external::gtfs::extended::nearby_stops::NEARBY_STOP_SORT_COLUMNS;
+use crate::external::gtfs::stops::STOP_SORT_COLUMNS;
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Response {
-    items: Vec<Stop>,
+#[get("/<key>/stops?<word>&<page..>")]
+pub fn index(
+    feeds: State<Feeds>,
+    key: String,
+    word: String,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(STOP_SORT_COLUMNS)?;
+    // TODO: Remove unwrap
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = StopServiceDb::new(gtfs);
+        let stops = service.fetch_stops(word.clone(), &paging).unwrap();
+        let total = service.count_stops(word).unwrap();
+        Json(page.apply_paged(stops, total).unwrap())
+    }))
 }
 
-#[get("/<key>/stops?<word>")]
-pub fn index(key: String, word: String) -> Json<Response> {
+/// radiusを指定しない場合の検索半径 (メートル)
+const DEFAULT_NEARBY_RADIUS: f64 = 500.0;
+
+#[get("/<key>/stops/nearby?<lat>&<lon>&<radius>&<page..>")]
+pub fn nearby(
+    feeds: State<Feeds>,
+    key: String,
+    lat: f64,
+    lon: f64,
+    radius: Option<f64>,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(NEARBY_STOP_SORT_COLUMNS)?;
+    let radius = radius.unwrap_or(DEFAULT_NEARBY_RADIUS);
     // TODO: Remove unwrap
-    let gtfs = GtfsDb::new(GtfsDb::get_default_path(key).as_path()).unwrap();
-    let stops = StopServiceDb::new(gtfs).fetch_stops(word).unwrap();
-    Json(Response { items: stops })
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = StopServiceDb::new(gtfs);
+        let stops = service
+            .fetch_nearby_stops(lat, lon, radius, &paging)
+            .unwrap();
+        let total = service.count_nearby_stops(lat, lon, radius).unwrap();
+        Json(page.apply_paged(stops, total).unwrap())
+    }))
 }
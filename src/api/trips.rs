@@ -1,19 +1,82 @@
+use rocket::http::RawStr;
+use rocket::request::LenientForm;
+use rocket::response::status::BadRequest;
+use rocket::State;
 use rocket_contrib::json::Json;
-use serde::{Deserialize, Serialize};
 
+use crate::api::utils::feeds::Feeds;
+use crate::api::utils::pages::{Page, PageQuery};
+use crate::api::utils::queries::{valid, DateQuery};
 use crate::app::trip::TripServiceDb;
-use crate::external::gtfs::trips::Trip;
-use crate::external::gtfsdb::GtfsDb;
+use crate::external::gtfs::extended::service_routes::ServiceRouteId;
+use crate::external::gtfs::routes::TransitMode;
+use crate::external::gtfs::trips::{TripQuery, TRIP_SORT_COLUMNS};
+use crate::external::gtfs::{DirectionId, GtfsTime, OperatorScope};
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Response {
-    items: Vec<Trip>,
-}
-
-#[get("/<key>/trips?<stop_id>")]
-pub fn index(key: String, stop_id: String) -> Json<Response> {
+#[allow(clippy::too_many_arguments)]
+#[get(
+    "/<key>/trips?<stop_id>&<mode>&<agency_id>&<office_id>&<route_id>&<service_id>&<date>\
+     &<direction_id>&<service_route_id>&<from>&<to>&<with_stop_times>&<page..>"
+)]
+pub fn index(
+    feeds: State<Feeds>,
+    key: String,
+    stop_id: Option<String>,
+    mode: Option<Result<TransitMode, &RawStr>>,
+    agency_id: Option<String>,
+    office_id: Option<String>,
+    route_id: Option<String>,
+    service_id: Option<String>,
+    date: Option<Result<DateQuery, &RawStr>>,
+    direction_id: Option<Result<DirectionId, &RawStr>>,
+    service_route_id: Option<Result<ServiceRouteId, &RawStr>>,
+    from: Option<Result<GtfsTime, &RawStr>>,
+    to: Option<Result<GtfsTime, &RawStr>>,
+    with_stop_times: Option<Result<bool, &RawStr>>,
+    page: LenientForm<PageQuery>,
+) -> Result<Option<Json<Page>>, BadRequest<String>> {
+    let page = page.into_inner();
+    let paging = page.paging(TRIP_SORT_COLUMNS)?;
+    let mode = valid("mode", mode)?;
+    let date = valid("date", date)?.map(|x| x.unwrap());
+    let direction_id = valid("direction_id", direction_id)?;
+    let service_route_id = valid("service_route_id", service_route_id)?;
+    let from = valid("from", from)?;
+    let to = valid("to", to)?;
+    let with_stop_times = valid("with_stop_times", with_stop_times)?;
+    let query = TripQuery {
+        stop_id,
+        route_id,
+        scope: OperatorScope {
+            agency_id,
+            office_id,
+        },
+        direction_id,
+        service_route_id,
+        from_seconds: from.map(|x| x.seconds()),
+        to_seconds: to.map(|x| x.seconds()),
+        ..Default::default()
+    };
     // TODO: Remove unwrap
-    let gtfs = GtfsDb::new(GtfsDb::get_default_path(key).as_path()).unwrap();
-    let trips = TripServiceDb::new(gtfs).fetch_trips(stop_id).unwrap();
-    Json(Response { items: trips })
+    Ok(feeds.open(&key).unwrap().map(|gtfs| {
+        let mut service = TripServiceDb::new(gtfs);
+        let mut trips = service
+            .fetch_trips(
+                mode.as_ref(),
+                service_id.clone(),
+                date,
+                query.clone(),
+                false,
+                &paging,
+            )
+            .unwrap();
+        // stop_timeはページに含めるtripの分だけ取得する
+        if with_stop_times.unwrap_or(false) {
+            trips = service.attach_stop_times(trips).unwrap();
+        }
+        let total = service
+            .count_trips(mode.as_ref(), service_id, date, query)
+            .unwrap();
+        Json(page.apply_paged(trips, total).unwrap())
+    }))
 }
@@ -1 +1,4 @@
+pub mod feeds;
+pub mod pages;
 pub mod queries;
+pub mod schemas;
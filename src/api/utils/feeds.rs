@@ -0,0 +1,337 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::feed_info::Feed;
+use crate::external::gtfsdb::GtfsDb;
+
+/// データベースのファイル名
+const DATABASE_FILE_NAME: &str = "gtfs.db";
+/// 有効なデータベースのスナップショットを置くディレクトリ (データベースと同じディレクトリに作る)
+const SNAPSHOT_DIR_NAME: &str = ".diamant-active";
+
+/// 有効なデータベースのバージョン
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FeedVersion {
+    /// key
+    pub key: String,
+    /// データベースファイルのパス
+    pub path: PathBuf,
+    /// feed_info.txtの提供データバージョン
+    pub feed_version: Option<String>,
+    /// データベースファイルの更新日時
+    pub modified_at: String,
+    /// 有効にした日時
+    pub loaded_at: String,
+}
+
+/// データベースファイルが書き換えられたかを判定するための情報
+#[derive(Debug, PartialEq, Clone)]
+struct FileSignature {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl FileSignature {
+    fn of(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            modified: metadata.modified()?,
+            len: metadata.len(),
+        })
+    }
+}
+
+/// keyごとに有効なデータベース
+struct ActiveFeed {
+    version: FeedVersion,
+    signature: FileSignature,
+    /// リクエストで開くファイル. 元のファイルが差し替えられても影響を受けないハードリンク (作れなければ元のファイル)
+    snapshot: PathBuf,
+}
+
+impl ActiveFeed {
+    /// pathのスナップショットを作ってから、スナップショットを検証する
+    /// 検証の後に元のファイルが差し替えられても、検証したものと同じファイルを使い続けられる
+    fn load(key: &str, path: &Path, seq: u64) -> Result<Self> {
+        let snapshot = snapshot_of(path, seq);
+        let result = Self::validate(key, path, &snapshot);
+        if result.is_err() && snapshot != path {
+            let _ = fs::remove_file(&snapshot);
+        }
+        result
+    }
+
+    fn validate(key: &str, path: &Path, snapshot: &Path) -> Result<Self> {
+        let signature = FileSignature::of(snapshot)?;
+        let mut gtfs = GtfsDb::open_read_only(snapshot)?;
+        let missing = gtfs.missing_schema()?;
+        if !missing.is_empty() {
+            bail!(
+                "{:?} に必要なテーブル・カラムがありません (db createで作り直してください): {}",
+                path,
+                missing.join(", ")
+            );
+        }
+        let feed_version = gtfs
+            .select_all::<Feed>()?
+            .into_iter()
+            .find_map(|x| x.feed_version);
+
+        Ok(Self {
+            version: FeedVersion {
+                key: key.to_string(),
+                path: path.to_path_buf(),
+                feed_version,
+                modified_at: DateTime::<Local>::from(signature.modified).to_rfc3339(),
+                loaded_at: Local::now().to_rfc3339(),
+            },
+            signature,
+            snapshot: snapshot.to_path_buf(),
+        })
+    }
+}
+
+/// pathのハードリンクを作る. 作れない場合は元のパスを返す
+fn snapshot_of(path: &Path, seq: u64) -> PathBuf {
+    let create = || -> Result<PathBuf> {
+        let dir = path
+            .parent()
+            .context("親ディレクトリがありません")?
+            .join(SNAPSHOT_DIR_NAME);
+        fs::create_dir_all(&dir)?;
+        let file_name = path.file_name().context("ファイル名がありません")?;
+        let snapshot = dir.join(format!(
+            "{}.{}.{}",
+            file_name.to_string_lossy(),
+            std::process::id(),
+            seq
+        ));
+        fs::hard_link(path, &snapshot)?;
+        Ok(snapshot)
+    };
+    match create() {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            warn!(
+                "⚠️ {:?} のスナップショットを作れないため直接開きます: {:#}",
+                path, e
+            );
+            path.to_path_buf()
+        }
+    }
+}
+
+/// このプロセスが以前に作ったpathのスナップショットを削除する
+/// 同じディレクトリを使う他のプロセスのスナップショットは削除しない
+fn remove_snapshots(path: &Path) {
+    let (dir, file_name) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(file_name)) => (dir.join(SNAPSHOT_DIR_NAME), file_name),
+        _ => return,
+    };
+    let prefix = format!("{}.{}.", file_name.to_string_lossy(), std::process::id());
+    if let Ok(entries) = dir.read_dir() {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// APIの`<key>`とデータベースファイルの対応
+/// cloneしたものは有効なデータベースを共有する
+#[derive(Clone)]
+pub struct Feeds {
+    /// 対応を指定しないkeyのデータベースを`<db_root>/<key>/gtfs.db`から探す
+    db_root: PathBuf,
+    /// keyごとのデータベースファイルのパス
+    paths: BTreeMap<String, PathBuf>,
+    /// 別名 => key
+    aliases: BTreeMap<String, String>,
+    active: Arc<RwLock<BTreeMap<String, ActiveFeed>>>,
+    /// 前回の確認で書き換え中だったファイル (次の確認で変わっていなければ有効にする)
+    pending: Arc<RwLock<BTreeMap<String, FileSignature>>>,
+    /// 検証に失敗したファイル (変わるまで再検証しない)
+    rejected: Arc<RwLock<BTreeMap<String, FileSignature>>>,
+    seq: Arc<AtomicU64>,
+}
+
+impl Feeds {
+    pub fn new(
+        db_root: PathBuf,
+        paths: BTreeMap<String, PathBuf>,
+        aliases: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            db_root,
+            paths,
+            aliases,
+            active: Arc::new(RwLock::new(BTreeMap::new())),
+            pending: Arc::new(RwLock::new(BTreeMap::new())),
+            rejected: Arc::new(RwLock::new(BTreeMap::new())),
+            seq: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 別名を辿ってkeyを求める
+    pub fn resolve(&self, key: &str) -> String {
+        let mut current = key;
+        // 別名の循環参照で止まらないよう、辿る回数を制限する
+        for _ in 0..=self.aliases.len() {
+            match self.aliases.get(current) {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+        current.to_string()
+    }
+
+    pub fn path_of(&self, key: &str) -> PathBuf {
+        let key = self.resolve(key);
+        match self.paths.get(&key) {
+            Some(path) => path.clone(),
+            None => self.db_root.join(&key).join(DATABASE_FILE_NAME),
+        }
+    }
+
+    /// keyの有効なデータベースを開く. 有効なデータベースがないkeyはNone
+    /// 開いた後にデータベースが差し替えられても、そのリクエストは開いたデータベースを使い続ける
+    pub fn open(&self, key: &str) -> Result<Option<GtfsDb>> {
+        let resolved = self.resolve(key);
+        let active = self.active.read().unwrap();
+        active
+            .get(&resolved)
+            .map(|feed| GtfsDb::open_read_only(&feed.snapshot))
+            .transpose()
+    }
+
+    /// 有効なデータベースのバージョン
+    pub fn versions(&self) -> Vec<FeedVersion> {
+        self.active
+            .read()
+            .unwrap()
+            .values()
+            .map(|x| x.version.clone())
+            .collect()
+    }
+
+    /// 対応を指定したkeyと、db_root配下でデータベースが見つかったkey (別名は含まない)
+    pub fn keys(&self) -> Result<Vec<String>> {
+        let mut keys: BTreeSet<String> = self.paths.keys().cloned().collect();
+        if self.db_root.is_dir() {
+            for entry in self.db_root.read_dir()? {
+                let entry = entry?;
+                if entry.path().join(DATABASE_FILE_NAME).is_file() {
+                    keys.insert(entry.file_name().to_string_lossy().to_string());
+                }
+            }
+        }
+        Ok(keys.into_iter().collect())
+    }
+
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// すべてのkeyと別名について、データベースが存在し期待するスキーマを持つか検証して有効にする
+    /// 以前に同じPIDで起動したとき (コンテナの再起動など) のスナップショットは削除する
+    pub fn load(&self) -> Result<()> {
+        let mut errors = vec![];
+        for key in self.keys()? {
+            remove_snapshots(&self.path_of(&key));
+            if let Err(e) = self.activate(&key) {
+                errors.push(format!("  - {}: {:#}", key, e));
+            }
+        }
+        for (alias, key) in &self.aliases {
+            let resolved = self.resolve(alias);
+            if self.aliases.contains_key(&resolved) {
+                errors.push(format!("  - {}: 別名が循環しています", alias));
+            } else if !self.active.read().unwrap().contains_key(&resolved) {
+                errors.push(format!(
+                    "  - {}: 別名の参照先 {} が存在しません",
+                    alias, key
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            bail!("利用できないデータベースがあります\n{}", errors.join("\n"));
+        }
+        Ok(())
+    }
+
+    /// 書き換えられたデータベースを有効にする
+    /// 書き換え中のファイルを読まないよう、前回の確認から変わっていないことを確かめてから検証し、
+    /// 検証に失敗した場合は以前のデータベースを使い続ける
+    pub fn reload(&self) -> Result<()> {
+        for key in self.keys()? {
+            let signature = match FileSignature::of(&self.path_of(&key)) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            let unchanged = self
+                .active
+                .read()
+                .unwrap()
+                .get(&key)
+                .map(|x| x.signature == signature)
+                .unwrap_or(false);
+            let rejected = self.rejected.read().unwrap().get(&key) == Some(&signature);
+            if unchanged || rejected {
+                self.pending.write().unwrap().remove(&key);
+                continue;
+            }
+
+            let stable = self.pending.read().unwrap().get(&key) == Some(&signature);
+            if !stable {
+                self.pending.write().unwrap().insert(key, signature);
+                continue;
+            }
+            self.pending.write().unwrap().remove(&key);
+            match self.activate(&key) {
+                Ok(()) => {
+                    self.rejected.write().unwrap().remove(&key);
+                    info!("♻️ Reload {} from {:?}", key, self.path_of(&key))
+                }
+                Err(e) => {
+                    self.rejected
+                        .write()
+                        .unwrap()
+                        .insert(key.clone(), signature);
+                    warn!("⚠️ Keep the current database of {}: {:#}", key, e)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn activate(&self, key: &str) -> Result<()> {
+        let path = self.path_of(key);
+        if !path.is_file() {
+            bail!("{:?} が存在しません", path);
+        }
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst);
+        let feed = ActiveFeed::load(key, &path, seq)?;
+
+        // 書き込みロック中は新たにデータベースを開くリクエストがないため、古いスナップショットを消せる
+        // (開き済みのリクエストはファイルを消しても読み続けられる)
+        let mut active = self.active.write().unwrap();
+        if let Some(old) = active.insert(key.to_string(), feed) {
+            if old.snapshot != old.version.path {
+                if let Err(e) = fs::remove_file(&old.snapshot) {
+                    warn!("⚠️ Fail to remove {:?}: {}", old.snapshot, e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use itertools::Itertools;
+use rocket::response::status::BadRequest;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::api::utils::queries::CommaSeparatedValues;
+use crate::external::gtfs::{Paging, SortColumns, SortKey};
+
+/// 一覧APIに共通するページング・並び替え・項目選択のクエリ
+#[derive(Debug, FromForm)]
+pub struct PageQuery {
+    /// 返す件数の上限 (省略時はすべて)
+    limit: Option<usize>,
+    /// 読み飛ばす件数
+    offset: Option<usize>,
+    /// 並び替える項目 (カンマ区切り. `-`を前置すると降順)
+    sort: Option<CommaSeparatedValues>,
+    /// 返す項目 (カンマ区切り. 省略時はすべて)
+    fields: Option<CommaSeparatedValues>,
+}
+
+/// 一覧APIのレスポンス
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Page {
+    items: Vec<Value>,
+    /// ページングする前の件数
+    total: usize,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl PageQuery {
+    /// データベースで取得する範囲. sortの項目はsortableにあるものだけを受け付ける
+    pub fn paging(&self, sortable: SortColumns) -> Result<Paging, BadRequest<String>> {
+        let sort = match &self.sort {
+            Some(sort) => sort
+                .values()
+                .iter()
+                .map(|x| sort_key(x, sortable))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![],
+        };
+        Ok(Paging {
+            limit: self.limit.map(|x| x.min(u32::MAX as usize) as u32),
+            offset: self.offset.unwrap_or(0).min(u32::MAX as usize) as u32,
+            sort,
+        })
+    }
+
+    /// pagingの範囲で取得したitemsに、別に数えたページング前の件数totalとともに項目選択を適用する
+    pub fn apply_paged<T: Serialize>(
+        self,
+        items: Vec<T>,
+        total: usize,
+    ) -> serde_json::Result<Page> {
+        let items = items
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<serde_json::Result<Vec<_>>>()?;
+        Ok(self.page(items, total))
+    }
+
+    fn page(self, items: Vec<Value>, total: usize) -> Page {
+        let items = match self.fields.map(|x| x.unwrap()) {
+            Some(fields) => items.into_iter().map(|x| project(x, &fields)).collect(),
+            None => items,
+        };
+        Page {
+            items,
+            total,
+            offset: self.offset.unwrap_or(0),
+            limit: self.limit,
+        }
+    }
+}
+
+/// `-`を前置すると降順になる項目名nameを、sortableのSQLの式にする
+fn sort_key(name: &str, sortable: SortColumns) -> Result<SortKey, BadRequest<String>> {
+    let (field, descending) = match name.strip_prefix('-') {
+        Some(field) => (field, true),
+        None => (name, false),
+    };
+    sortable
+        .iter()
+        .find(|(x, _)| *x == field)
+        .map(|(_, column)| SortKey { column, descending })
+        .ok_or_else(|| {
+            BadRequest(Some(format!(
+                "sort: {}では並び替えられません ({})",
+                field,
+                sortable.iter().map(|(x, _)| x).join(", ")
+            )))
+        })
+}
+
+/// fieldsの項目だけを残す
+fn project(value: Value, fields: &[String]) -> Value {
+    match value {
+        Value::Object(mut object) => Value::Object(
+            fields
+                .iter()
+                .filter_map(|k| object.remove(k).map(|v| (k.clone(), v)))
+                .collect::<Map<_, _>>(),
+        ),
+        v => v,
+    }
+}
@@ -1,6 +1,12 @@
+use chrono::NaiveDate;
 use itertools::Itertools;
 use rocket::http::RawStr;
-use rocket::request::FromFormValue;
+use rocket::request::{FromFormValue, FromParam};
+use rocket::response::status::BadRequest;
+
+use crate::external::gtfs::routes::TransitMode;
+use crate::external::gtfs::{DirectionId, GtfsTime};
+use crate::serde_chrono_custom::yyyymmdd;
 
 /// カンマ区切りで複数の値を指定する文字列型クエリ
 #[derive(Debug)]
@@ -23,4 +29,73 @@ impl CommaSeparatedValues {
     pub fn unwrap(self) -> Vec<String> {
         self.0
     }
+
+    pub fn values(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl<'v> FromFormValue<'v> for TransitMode {
+    type Error = &'v RawStr;
+
+    fn from_form_value(v: &'v RawStr) -> Result<Self, Self::Error> {
+        v.url_decode().ok().and_then(|x| x.parse().ok()).ok_or(v)
+    }
+}
+
+impl<'v> FromFormValue<'v> for DirectionId {
+    type Error = &'v RawStr;
+
+    fn from_form_value(v: &'v RawStr) -> Result<Self, Self::Error> {
+        v.parse().map_err(|_| v)
+    }
+}
+
+impl<'a> FromParam<'a> for DirectionId {
+    type Error = &'a RawStr;
+
+    fn from_param(param: &'a RawStr) -> Result<Self, Self::Error> {
+        param.parse().map_err(|_| param)
+    }
+}
+
+/// H:MM:SSまたはHH:MM:SS形式の時刻クエリ
+impl<'v> FromFormValue<'v> for GtfsTime {
+    type Error = &'v RawStr;
+
+    fn from_form_value(v: &'v RawStr) -> Result<Self, Self::Error> {
+        v.url_decode().ok().and_then(|x| x.parse().ok()).ok_or(v)
+    }
+}
+
+/// YYYYMMDD形式の日付クエリ
+#[derive(Debug)]
+pub struct DateQuery(NaiveDate);
+
+impl<'v> FromFormValue<'v> for DateQuery {
+    type Error = &'v RawStr;
+
+    fn from_form_value(v: &'v RawStr) -> Result<Self, Self::Error> {
+        v.url_decode()
+            .ok()
+            .and_then(|x| yyyymmdd::parse(&x).ok())
+            .map(DateQuery)
+            .ok_or(v)
+    }
+}
+
+impl DateQuery {
+    pub fn unwrap(self) -> NaiveDate {
+        self.0
+    }
+}
+
+/// 値を解釈できなかったクエリnameを400にする. 省略した場合はNone
+pub fn valid<T>(
+    name: &str,
+    value: Option<Result<T, &RawStr>>,
+) -> Result<Option<T>, BadRequest<String>> {
+    value
+        .transpose()
+        .map_err(|v| BadRequest(Some(format!("{}: {}は指定できません", name, v))))
 }
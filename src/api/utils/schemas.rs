@@ -0,0 +1,591 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use serde_json::{json, Map, Value};
+
+/// 文字列の項目に試す値 (独自の形式で読みこむ型が受けつける値が見つかるまで順に試す)
+const STRING_SAMPLES: &[&str] = &["1", "00:00:00", "20210101", "2021-01-01", "ja"];
+/// 整数の項目に試す値 (0始まりでない列挙型があるため1から試す)
+const INTEGER_SAMPLES: &[u64] = &[1, 0, 2, 3];
+/// 1つの型の導出で読みこみをやり直す上限
+const MAX_ATTEMPTS: usize = 256;
+/// 整数の項目が列挙型 (`Deserialize_repr`) かを調べるために試す値
+const ENUM_PROBES: std::ops::RangeInclusive<u64> = 0..=63;
+/// 受けつける値がこの数以下の整数の項目は列挙型とみなし、値を列挙する
+const MAX_ENUM_VALUES: usize = 16;
+
+/// OpenAPIのcomponents/schemasを集めながら、型のスキーマを導出する
+#[derive(Debug, Default)]
+pub struct Schemas {
+    components: BTreeMap<String, Value>,
+    /// 項目名ごとにスキーマへ追加する内容
+    annotations: BTreeMap<String, Map<String, Value>>,
+}
+
+impl Schemas {
+    /// Tをダミー値で読みこみ、呼ばれた読みこみ処理からスキーマを導出する
+    /// 構造体はcomponents/schemasに登録して参照を返す. 導出できない場合は任意のobjectとする
+    pub fn schema_of<T: DeserializeOwned>(&mut self) -> Value {
+        let samples = match samples_of::<T>() {
+            Some(samples) => samples,
+            None => return json!({"type": "object"}),
+        };
+        let enums = enums_of::<T>(&samples);
+        let mut state = TraceState::new(&samples, &enums);
+        let (_, out) = trace::<T>(&mut state);
+        for (name, schema) in state.components {
+            self.components.entry(name).or_insert(schema);
+        }
+        out
+    }
+
+    /// 導出できない制約 (未定義の値も受けつけるコードの一覧など) を、名前がpropertyの項目に追加する
+    pub fn annotate(&mut self, property: &str, annotation: Value) {
+        if let Value::Object(annotation) = annotation {
+            self.annotations
+                .entry(property.to_string())
+                .or_default()
+                .extend(annotation);
+        }
+    }
+
+    pub fn into_components(mut self) -> BTreeMap<String, Value> {
+        for schema in self.components.values_mut() {
+            let properties = match schema.get_mut("properties") {
+                Some(Value::Object(properties)) => properties,
+                _ => continue,
+            };
+            for (name, property) in properties.iter_mut() {
+                if let (Some(annotation), Value::Object(property)) =
+                    (self.annotations.get(name), property)
+                {
+                    property.extend(annotation.clone());
+                }
+            }
+        }
+        self.components
+    }
+}
+
+/// Tの読みこみに成功するダミー値の組み合わせを探す
+fn samples_of<T: DeserializeOwned>() -> Option<HashMap<String, usize>> {
+    let mut samples = HashMap::new();
+    let enums = HashMap::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let mut state = TraceState::new(&samples, &enums);
+        match (trace::<T>(&mut state).0, state.failed) {
+            (true, _) => return Some(samples),
+            // 受けつけられなかったダミー値を次の候補に変えてやり直す
+            (false, Some(path)) => *samples.entry(path).or_insert(0) += 1,
+            (false, None) => return None,
+        }
+    }
+    None
+}
+
+/// 整数の項目ごとに受けつける値を調べ、列挙型とみなせる項目の値を返す
+/// `Deserialize_repr`の列挙型は整数として読みこまれるため、値を試さないと区別できない
+fn enums_of<T: DeserializeOwned>(samples: &HashMap<String, usize>) -> HashMap<String, Vec<u64>> {
+    let none = HashMap::new();
+    let mut state = TraceState::new(samples, &none);
+    trace::<T>(&mut state);
+    state
+        .integers
+        .into_iter()
+        .filter_map(|path| {
+            // 列挙型でないと分かった時点で打ち切る
+            let values = ENUM_PROBES
+                .filter(|value| {
+                    let mut state = TraceState::new(samples, &none);
+                    state.probe = Some((path.clone(), *value));
+                    trace::<T>(&mut state).0
+                })
+                .take(MAX_ENUM_VALUES + 1)
+                .collect::<Vec<_>>();
+            if values.is_empty() || values.len() > MAX_ENUM_VALUES {
+                None
+            } else {
+                Some((path, values))
+            }
+        })
+        .collect()
+}
+
+/// stateのダミー値でTを読みこみ、成功したかと導出したスキーマを返す
+fn trace<T: DeserializeOwned>(state: &mut TraceState) -> (bool, Value) {
+    let mut out = Value::Null;
+    let result = T::deserialize(Tracer {
+        state,
+        path: String::new(),
+        out: &mut out,
+    });
+    (result.is_ok(), out)
+}
+
+struct TraceState<'s> {
+    /// 項目のパスごとに試しているダミー値の位置
+    samples: &'s HashMap<String, usize>,
+    /// 列挙型とみなした整数の項目のパスと値
+    enums: &'s HashMap<String, Vec<u64>>,
+    /// ダミー値の代わりに試す整数の項目のパスと値
+    probe: Option<(String, u64)>,
+    /// ダミー値を受けつけなかった項目のパス
+    failed: Option<String>,
+    /// 読みこんだ整数の項目のパス
+    integers: Vec<String>,
+    components: BTreeMap<String, Value>,
+}
+
+impl<'s> TraceState<'s> {
+    fn new(samples: &'s HashMap<String, usize>, enums: &'s HashMap<String, Vec<u64>>) -> Self {
+        TraceState {
+            samples,
+            enums,
+            probe: None,
+            failed: None,
+            integers: vec![],
+            components: BTreeMap::new(),
+        }
+    }
+
+    fn sample<T: Copy>(&self, path: &str, candidates: &[T]) -> Option<T> {
+        candidates
+            .get(self.samples.get(path).copied().unwrap_or(0))
+            .copied()
+    }
+
+    fn check<T>(&mut self, path: &str, result: Result<T, TraceError>) -> Result<T, TraceError> {
+        if result.is_err() && self.failed.is_none() {
+            self.failed = Some(path.to_string());
+        }
+        result
+    }
+}
+
+#[derive(Debug)]
+pub struct TraceError(String);
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl de::Error for TraceError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        TraceError(msg.to_string())
+    }
+}
+
+struct Tracer<'a, 's> {
+    state: &'a mut TraceState<'s>,
+    path: String,
+    out: &'a mut Value,
+}
+
+impl<'a, 's> Tracer<'a, 's> {
+    fn visit_integer<'de, V: Visitor<'de>>(
+        self,
+        visitor: V,
+        signed: bool,
+    ) -> Result<V::Value, TraceError> {
+        *self.out = match self.state.enums.get(&self.path) {
+            Some(values) => json!({"type": "integer", "enum": values}),
+            None => json!({"type": "integer"}),
+        };
+        if !self.state.integers.contains(&self.path) {
+            self.state.integers.push(self.path.clone());
+        }
+        let sample = match &self.state.probe {
+            Some((path, value)) if path == &self.path => *value,
+            _ => self
+                .state
+                .sample(&self.path, INTEGER_SAMPLES)
+                .ok_or_else(|| TraceError("整数のダミー値がありません".into()))?,
+        };
+        let result = if signed {
+            visitor.visit_i64(sample as i64)
+        } else {
+            visitor.visit_u64(sample)
+        };
+        self.state.check(&self.path, result)
+    }
+
+    fn visit_string<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        *self.out = json!({"type": "string"});
+        let sample = self
+            .state
+            .sample(&self.path, STRING_SAMPLES)
+            .ok_or_else(|| TraceError("文字列のダミー値がありません".into()))?;
+        let result = visitor.visit_str(sample);
+        self.state.check(&self.path, result)
+    }
+}
+
+impl<'de, 'a, 's> de::Deserializer<'de> for Tracer<'a, 's> {
+    type Error = TraceError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_string(visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.out = json!({"type": "boolean"});
+        visitor.visit_bool(false)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_integer(visitor, true)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_integer(visitor, true)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_integer(visitor, true)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_integer(visitor, true)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_integer(visitor, false)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_integer(visitor, false)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_integer(visitor, false)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_integer(visitor, false)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.out = json!({"type": "number"});
+        visitor.visit_f64(1.0)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.out = json!({"type": "number"});
+        visitor.visit_f64(1.0)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.out = json!({"type": "string"});
+        visitor.visit_char('1')
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_string(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let out = &mut *self.out;
+        let value = visitor.visit_some(Tracer {
+            state: self.state,
+            path: self.path,
+            out: &mut *out,
+        })?;
+        if let Value::Object(schema) = out {
+            schema.insert("nullable".into(), Value::Bool(true));
+        }
+        Ok(value)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.out = json!({"type": "object"});
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut items = Value::Null;
+        let value = visitor.visit_seq(Elements {
+            state: &mut *self.state,
+            path: format!("{}[]", self.path),
+            out: &mut items,
+            remaining: 1,
+        })?;
+        *self.out = json!({"type": "array", "items": items});
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let mut items = Value::Null;
+        let value = visitor.visit_seq(Elements {
+            state: &mut *self.state,
+            path: format!("{}[]", self.path),
+            out: &mut items,
+            remaining: len,
+        })?;
+        *self.out = json!({"type": "array", "items": items});
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.out = json!({"type": "object", "additionalProperties": {}});
+        visitor.visit_map(Fields {
+            state: self.state,
+            path: self.path,
+            fields: [].iter(),
+            current: None,
+            properties: &mut Map::new(),
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let mut properties = Map::new();
+        let value = visitor.visit_map(Fields {
+            state: &mut *self.state,
+            path: self.path,
+            fields: fields.iter(),
+            current: None,
+            properties: &mut properties,
+        })?;
+        let required = properties
+            .iter()
+            .filter(|(_, schema)| schema.get("nullable").is_none())
+            .map(|(name, _)| Value::String(name.clone()))
+            .collect::<Vec<_>>();
+        let schema = json!({"type": "object", "properties": properties, "required": required});
+        // 名前のない構造体 (列挙子など) はcomponentsに登録しない
+        if name.is_empty() {
+            *self.out = schema;
+        } else {
+            self.state.components.insert(name.to_string(), schema);
+            *self.out = json!({ "$ref": format!("#/components/schemas/{}", name) });
+        }
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.out = json!({"type": "string", "enum": variants});
+        let variant = self
+            .state
+            .sample(&self.path, variants)
+            .ok_or_else(|| TraceError("列挙子がありません".into()))?;
+        let result = visitor.visit_enum(Variant {
+            variant,
+            state: &mut *self.state,
+            path: self.path.clone(),
+        });
+        self.state.check(&self.path, result)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.visit_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf
+    }
+}
+
+/// 配列の要素. スキーマは最初の要素から導出する
+struct Elements<'a, 's> {
+    state: &'a mut TraceState<'s>,
+    path: String,
+    out: &'a mut Value,
+    remaining: usize,
+}
+
+impl<'de, 'a, 's> SeqAccess<'de> for Elements<'a, 's> {
+    type Error = TraceError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        let mut item = Value::Null;
+        let result = seed.deserialize(Tracer {
+            state: &mut *self.state,
+            path: self.path.clone(),
+            out: &mut item,
+        });
+        let value = self.state.check(&self.path, result)?;
+        if self.out.is_null() {
+            *self.out = item;
+        }
+        Ok(Some(value))
+    }
+}
+
+/// 構造体の項目
+struct Fields<'a, 's> {
+    state: &'a mut TraceState<'s>,
+    path: String,
+    fields: std::slice::Iter<'static, &'static str>,
+    current: Option<&'static str>,
+    properties: &'a mut Map<String, Value>,
+}
+
+impl<'de, 'a, 's> MapAccess<'de> for Fields<'a, 's> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.fields.next() {
+            Some(field) => {
+                self.current = Some(field);
+                seed.deserialize(field.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let field = self.current.take().unwrap_or_default();
+        let out = self
+            .properties
+            .entry(field.to_string())
+            .or_insert(Value::Null);
+        // 文字列を読んだ後に独自の形式として解釈する型は、項目の読みこみの失敗として扱う
+        let path = format!("{}.{}", self.path, field);
+        let result = seed.deserialize(Tracer {
+            state: &mut *self.state,
+            path: path.clone(),
+            out,
+        });
+        self.state.check(&path, result)
+    }
+}
+
+/// 列挙型の列挙子
+struct Variant<'a, 's> {
+    variant: &'static str,
+    state: &'a mut TraceState<'s>,
+    path: String,
+}
+
+impl<'de, 'a, 's> EnumAccess<'de> for Variant<'a, 's> {
+    type Error = TraceError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let value = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a, 's> VariantAccess<'de> for Variant<'a, 's> {
+    type Error = TraceError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(Tracer {
+            state: self.state,
+            path: self.path,
+            out: &mut Value::Null,
+        })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_tuple(
+            Tracer {
+                state: self.state,
+                path: self.path,
+                out: &mut Value::Null,
+            },
+            len,
+            visitor,
+        )
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_struct(
+            Tracer {
+                state: self.state,
+                path: self.path,
+                out: &mut Value::Null,
+            },
+            "",
+            fields,
+            visitor,
+        )
+    }
+}
@@ -1,4 +1,9 @@
+pub mod block;
+pub mod fare;
+pub mod graphql;
 pub mod gtfs;
+pub mod node;
+pub mod operator;
 pub mod route;
 pub mod service_route;
 pub mod stop_time;
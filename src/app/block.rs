@@ -0,0 +1,204 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::extended::block_trips::BlockTrip;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{great_circle_distance, GtfsDbTrait, GtfsTime};
+use crate::external::gtfsdb::GtfsDb;
+use crate::serde_chrono_custom::yyyymmdd;
+
+/// 前便の終点と次便の始点がこれ以上離れていたら回送不能な飛びとみなす (メートル)
+const LOCATION_JUMP_DISTANCE: f64 = 500.0;
+
+/// 運用に組み込まれた便
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct BlockedTrip {
+    /// 運用ID
+    pub block_id: String,
+    /// 便ID
+    pub trip_id: TripId,
+    /// 経路ID
+    pub route_id: RouteId,
+    /// 運行日ID
+    pub service_id: ServiceId,
+    /// 始点の標柱ID
+    pub first_stop_id: StopId,
+    /// 始点の停留所名
+    pub first_stop_name: String,
+    /// 始点の出発時刻
+    pub departure_time: GtfsTime,
+    /// 終点の標柱ID
+    pub last_stop_id: StopId,
+    /// 終点の停留所名
+    pub last_stop_name: String,
+    /// 終点の到着時刻
+    pub arrival_time: GtfsTime,
+    /// 前便の到着から出発までの折返し時間 (秒). 運用の最初の便はNone
+    pub layover: Option<i64>,
+    /// 前便の到着より前に出発する
+    pub overlapped: bool,
+    /// 前便の終点と始点が離れている
+    pub location_jumped: bool,
+}
+
+impl BlockedTrip {
+    fn new(block_id: String, trip: BlockTrip, previous: Option<&BlockTrip>) -> Self {
+        let (layover, overlapped, location_jumped) = match previous {
+            Some(prev) => {
+                let layover = trip.departure_time.seconds_since(&prev.arrival_time);
+                (Some(layover), layover < 0, is_location_jumped(prev, &trip))
+            }
+            None => (None, false, false),
+        };
+        Self {
+            block_id,
+            trip_id: trip.trip_id,
+            route_id: trip.route_id,
+            service_id: trip.service_id,
+            first_stop_id: trip.first_stop_id,
+            first_stop_name: trip.first_stop_name,
+            departure_time: trip.departure_time,
+            last_stop_id: trip.last_stop_id,
+            last_stop_name: trip.last_stop_name,
+            arrival_time: trip.arrival_time,
+            layover,
+            overlapped,
+            location_jumped,
+        }
+    }
+
+    /// 前便との接続が成立しない
+    pub fn is_impossible(&self) -> bool {
+        self.overlapped || self.location_jumped
+    }
+}
+
+/// 前便の終点から次便の始点へ移動できない位置関係か
+/// 同じ標柱、同じ親駅、または一定距離以内であれば移動できるとみなす
+fn is_location_jumped(prev: &BlockTrip, next: &BlockTrip) -> bool {
+    if prev.last_stop_id == next.first_stop_id {
+        return false;
+    }
+    if prev.last_parent_station.is_some() && prev.last_parent_station == next.first_parent_station {
+        return false;
+    }
+    great_circle_distance(
+        (prev.last_stop_lat, prev.last_stop_lon),
+        (next.first_stop_lat, next.first_stop_lon),
+    ) > LOCATION_JUMP_DISTANCE
+}
+
+/// 運用 (1つの車両が担当する便の連なり)
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Block {
+    /// 運用ID
+    pub block_id: String,
+    /// 運行日
+    #[serde(with = "yyyymmdd")]
+    pub date: NaiveDate,
+    /// 出発時刻順の便
+    pub trips: Vec<BlockedTrip>,
+    /// 成立しない接続を含むか
+    pub impossible: bool,
+}
+
+/// 運行日ごとの運用の集計
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct BlockSummary {
+    /// 運行日
+    #[serde(with = "yyyymmdd")]
+    pub date: NaiveDate,
+    /// 運用数
+    pub block_count: usize,
+    /// 運用に組み込まれた便数
+    pub blocked_trip_count: usize,
+    /// 運用に組み込まれていない便数
+    pub unblocked_trip_count: usize,
+    /// 必要な車両数 (運用に組み込まれていない便は1便1両とみなす)
+    pub vehicle_count: usize,
+    /// 成立しない接続を含む運用数
+    pub impossible_block_count: usize,
+}
+
+pub struct BlockServiceDb {
+    gtfs: GtfsDb,
+}
+
+impl BlockServiceDb {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    /// dateに運行する運用を取得する. block_idを指定した場合はその運用のみ
+    pub fn fetch_blocks(
+        &mut self,
+        date: NaiveDate,
+        block_id: Option<String>,
+    ) -> Result<Vec<Block>> {
+        let service_ids = self.gtfs.select_service_ids(&date)?;
+        let trips = self.gtfs.select_block_trips(service_ids, block_id)?;
+        Ok(to_blocks(date, trips))
+    }
+
+    pub fn fetch_block(&mut self, date: NaiveDate, block_id: String) -> Result<Option<Block>> {
+        Ok(self.fetch_blocks(date, Some(block_id))?.into_iter().next())
+    }
+
+    /// start_dateからdays日分の運用を運行日ごとに集計する
+    pub fn fetch_summaries(
+        &mut self,
+        start_date: NaiveDate,
+        days: u32,
+    ) -> Result<Vec<BlockSummary>> {
+        (0..days)
+            .map(|i| {
+                let date = start_date + Duration::days(i as i64);
+                let service_ids = self.gtfs.select_service_ids(&date)?;
+                let trips = self.gtfs.select_block_trips(service_ids, None)?;
+                let unblocked_trip_count = trips.iter().filter(|x| x.block_id.is_none()).count();
+                let blocks = to_blocks(date, trips);
+                Ok(BlockSummary {
+                    date,
+                    block_count: blocks.len(),
+                    blocked_trip_count: blocks.iter().map(|x| x.trips.len()).sum(),
+                    unblocked_trip_count,
+                    vehicle_count: blocks.len() + unblocked_trip_count,
+                    impossible_block_count: blocks.iter().filter(|x| x.impossible).count(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// block_id, 出発時刻順の便から運用を組み立てる (block_idのない便は除く)
+fn to_blocks(date: NaiveDate, trips: Vec<BlockTrip>) -> Vec<Block> {
+    trips
+        .into_iter()
+        .filter_map(|x| x.block_id.clone().map(|block_id| (block_id, x)))
+        .group_by(|(block_id, _)| block_id.clone())
+        .into_iter()
+        .map(|(block_id, group)| {
+            let mut previous: Option<BlockTrip> = None;
+            let mut trips = vec![];
+            for (_, trip) in group {
+                trips.push(BlockedTrip::new(
+                    block_id.clone(),
+                    trip.clone(),
+                    previous.as_ref(),
+                ));
+                previous = Some(trip);
+            }
+            Block {
+                impossible: trips.iter().any(|x| x.is_impossible()),
+                block_id,
+                date,
+                trips,
+            }
+        })
+        .collect_vec()
+}
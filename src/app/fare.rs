@@ -0,0 +1,42 @@
+use anyhow::Result;
+
+use crate::external::gtfs::extended::leg_fares::LegFare;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::{GtfsDbTrait, Paging};
+use crate::external::gtfsdb::GtfsDb;
+
+pub struct FareServiceDb {
+    gtfs: GtfsDb,
+}
+
+impl FareServiceDb {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    /// 1区間の乗車に適用される運賃をFares v2の定義から求める
+    /// 該当するルールが複数ある場合は rule_priority が最も高いもののみ返却する (運賃媒体ごとに複数になりうる)
+    pub fn fetch_leg_fares(
+        &mut self,
+        from_stop_id: StopId,
+        to_stop_id: StopId,
+        route_id: Option<RouteId>,
+        paging: &Paging,
+    ) -> Result<Vec<LegFare>> {
+        self.gtfs
+            .select_leg_fares(from_stop_id, to_stop_id, route_id, paging)
+    }
+
+    /// fetch_leg_faresでページングしない場合の件数
+    pub fn count_leg_fares(
+        &mut self,
+        from_stop_id: StopId,
+        to_stop_id: StopId,
+        route_id: Option<RouteId>,
+    ) -> Result<usize> {
+        Ok(self
+            .gtfs
+            .count_leg_fares(from_stop_id, to_stop_id, route_id)? as usize)
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+use anyhow::{anyhow, bail, Context, Result};
+use rusqlite::types::Value as SqlValue;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::app::graphql::parser::Field;
+use crate::app::graphql::schema::{DateFilter, ObjectType, Relation};
+use crate::external::gtfs::extended::records::{Condition, Record, RecordQuery};
+use crate::external::gtfs::trips::Trip;
+use crate::external::gtfs::GtfsDbTrait;
+use crate::external::gtfsdb::{GtfsDb, Table};
+use crate::serde_chrono_custom::yyyymmdd;
+
+pub mod parser;
+pub mod schema;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphqlRequest {
+    pub query: String,
+    #[serde(default)]
+    pub variables: Option<Map<String, Value>>,
+    /// 1つのオペレーションのみ扱うため使わない
+    #[serde(default)]
+    pub operation_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GraphqlError {
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GraphqlResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<GraphqlError>,
+}
+
+/// クエリの大きさの上限
+#[derive(Debug, Clone)]
+pub struct GraphqlLimits {
+    /// 選択の入れ子の深さ (`{ stops { stop_id } }`を1とする)
+    pub max_depth: usize,
+    /// 選択したフィールドの総数
+    pub max_complexity: usize,
+    /// リストのフィールドでlimitを省略した場合の件数 (関連は親ごと)
+    pub default_limit: u32,
+    /// リストのフィールドのlimitの上限
+    pub max_limit: u32,
+}
+
+pub struct GraphqlServiceDb {
+    gtfs: GtfsDb,
+    types: HashMap<&'static str, ObjectType>,
+}
+
+impl GraphqlServiceDb {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self {
+            gtfs,
+            types: schema::object_types()
+                .into_iter()
+                .map(|x| (x.name, x))
+                .collect(),
+        }
+    }
+
+    /// エラーはレスポンスのerrorsとして返す (いずれかのフィールドでエラーになった場合はdataを返さない)
+    pub fn execute(&mut self, request: &GraphqlRequest, limits: &GraphqlLimits) -> GraphqlResponse {
+        match self.try_execute(request, limits) {
+            Ok(data) => GraphqlResponse {
+                data: Some(data),
+                errors: vec![],
+            },
+            Err(e) => GraphqlResponse {
+                data: None,
+                errors: vec![GraphqlError {
+                    message: format!("{:#}", e),
+                }],
+            },
+        }
+    }
+
+    fn try_execute(&mut self, request: &GraphqlRequest, limits: &GraphqlLimits) -> Result<Value> {
+        // 深さは解釈しながら確かめる
+        let fields = parser::parse(
+            &request.query,
+            request.variables.as_ref().unwrap_or(&Map::new()),
+            limits.max_depth,
+        )
+        .map_err(|e| anyhow!(e))?;
+
+        let complexity = fields.iter().map(|x| x.complexity()).sum::<usize>();
+        if complexity > limits.max_complexity {
+            bail!(
+                "クエリのフィールド数 {} が上限 {} を超えています",
+                complexity,
+                limits.max_complexity
+            );
+        }
+
+        let mut executor = Executor {
+            gtfs: &mut self.gtfs,
+            types: &self.types,
+            limits,
+        };
+        let mut data = Map::new();
+        for field in &fields {
+            data.insert(field.key().to_string(), executor.resolve_root(field)?);
+        }
+        Ok(Value::Object(data))
+    }
+}
+
+struct Executor<'a> {
+    gtfs: &'a mut GtfsDb,
+    types: &'a HashMap<&'static str, ObjectType>,
+    limits: &'a GraphqlLimits,
+}
+
+impl<'a> Executor<'a> {
+    fn resolve_root(&mut self, field: &Field) -> Result<Value> {
+        if field.name == "__typename" {
+            return Ok(Value::String("Query".into()));
+        }
+        let root = schema::root_fields()
+            .into_iter()
+            .find(|x| x.name == field.name)
+            .with_context(|| format!("Queryに{}フィールドはありません", field.name))?;
+        let ty = &self.types[root.target];
+
+        let (mut query, limit, offset) = self.record_query(ty, field)?;
+        query.limit = Some(if root.list {
+            self.list_limit(limit)?
+        } else {
+            1
+        });
+        query.offset = offset;
+        let records = self.gtfs.select_records(&query)?;
+        let values = self.resolve_records(ty, &field.selection, records)?;
+        Ok(if root.list {
+            Value::Array(values)
+        } else {
+            values.into_iter().next().unwrap_or(Value::Null)
+        })
+    }
+
+    /// リストのフィールドに適用する件数. 省略時は既定の件数とし、上限を超える場合はエラー
+    fn list_limit(&self, limit: Option<u32>) -> Result<u32> {
+        match limit {
+            Some(x) if x > self.limits.max_limit => {
+                bail!("limitは{}以下で指定してください", self.limits.max_limit)
+            }
+            Some(x) => Ok(x),
+            None => Ok(self.limits.default_limit),
+        }
+    }
+
+    /// 引数から絞込条件を作る. limitとoffsetは呼び出し元で適用する
+    fn record_query(
+        &mut self,
+        ty: &ObjectType,
+        field: &Field,
+    ) -> Result<(RecordQuery, Option<u32>, Option<u32>)> {
+        if field.selection.is_empty() {
+            bail!("{}({})にはフィールドの選択が必要です", field.name, ty.name);
+        }
+
+        let mut query = RecordQuery {
+            table: ty.table,
+            conditions: vec![],
+            order_by: ty.order_by,
+            partition_by: vec![],
+            limit: None,
+            offset: None,
+        };
+        let (mut limit, mut offset) = (None, None);
+        for (name, value) in &field.arguments {
+            if value.is_null() {
+                continue;
+            }
+            match name.as_str() {
+                "limit" => limit = Some(as_u32(name, value)?),
+                "offset" => offset = Some(as_u32(name, value)?),
+                "name" if !ty.name_columns.is_empty() => {
+                    let word = value
+                        .as_str()
+                        .with_context(|| format!("{}は文字列で指定してください", name))?;
+                    query
+                        .conditions
+                        .push(Condition::Like(ty.name_columns, word.to_string()));
+                }
+                "date" if ty.date_filter.is_some() => {
+                    let date = value
+                        .as_str()
+                        .and_then(|x| yyyymmdd::parse(x).ok())
+                        .with_context(|| format!("{}はYYYYMMDD形式で指定してください", name))?;
+                    let service_ids = self
+                        .gtfs
+                        .select_service_ids(&date)?
+                        .into_iter()
+                        .map(SqlValue::Text)
+                        .collect();
+                    let condition = Condition::In("service_id", service_ids);
+                    query.conditions.push(match ty.date_filter {
+                        Some(DateFilter::TripId) => Condition::InSelect {
+                            column: "trip_id",
+                            table: Trip::table_name(),
+                            select: "trip_id",
+                            condition: Box::new(condition),
+                        },
+                        _ => condition,
+                    });
+                }
+                _ => {
+                    let column = ty.column(name).with_context(|| {
+                        format!("{}の{}に{}引数はありません", ty.name, field.name, name)
+                    })?;
+                    let values = match value {
+                        Value::Array(values) => values.iter().filter_map(to_sql).collect(),
+                        v => to_sql(v).into_iter().collect(),
+                    };
+                    query.conditions.push(Condition::In(column, values));
+                }
+            }
+        }
+        Ok((query, limit, offset))
+    }
+
+    fn resolve_records(
+        &mut self,
+        ty: &ObjectType,
+        selection: &[Field],
+        records: Vec<Record>,
+    ) -> Result<Vec<Value>> {
+        let mut outputs = vec![Map::new(); records.len()];
+        for field in selection {
+            let key = field.key().to_string();
+            if field.name == "__typename" {
+                for output in outputs.iter_mut() {
+                    output.insert(key.clone(), Value::String(ty.name.into()));
+                }
+            } else if let Some(column) = ty.column(&field.name) {
+                if !field.selection.is_empty() {
+                    bail!("{}の{}はフィールドを選択できません", ty.name, field.name);
+                }
+                for (output, record) in outputs.iter_mut().zip(&records) {
+                    output.insert(
+                        key.clone(),
+                        record.get(column).cloned().unwrap_or(Value::Null),
+                    );
+                }
+            } else if let Some(relation) = ty.relations.iter().find(|x| x.name == field.name) {
+                let values = self.resolve_relation(relation, field, &records)?;
+                for (output, value) in outputs.iter_mut().zip(values) {
+                    output.insert(key.clone(), value);
+                }
+            } else {
+                bail!("{}に{}フィールドはありません", ty.name, field.name);
+            }
+        }
+        Ok(outputs.into_iter().map(Value::Object).collect())
+    }
+
+    /// すべての親の関連先をまとめて取得してから、親ごとに振り分ける
+    fn resolve_relation(
+        &mut self,
+        relation: &Relation,
+        field: &Field,
+        parents: &[Record],
+    ) -> Result<Vec<Value>> {
+        let types = self.types;
+        let target = &types[relation.target];
+        let (mut query, limit, offset) = self.record_query(target, field)?;
+        let (limit, offset) = if relation.list {
+            (self.list_limit(limit)?, offset.unwrap_or(0))
+        } else {
+            (1, 0)
+        };
+
+        // 親ごとに、関連先と結合するレコード (中間テーブルがなければ親自身)
+        let sources: Vec<Vec<Record>> = match &relation.via {
+            None => parents.iter().map(|x| vec![x.clone()]).collect(),
+            Some(via) => {
+                let mappings = self.gtfs.select_records(&RecordQuery {
+                    table: via.table,
+                    conditions: via
+                        .join
+                        .iter()
+                        .map(|(p, m)| Condition::In(m, distinct_values(parents.iter(), p)))
+                        .collect(),
+                    order_by: &[],
+                    partition_by: vec![],
+                    limit: None,
+                    offset: None,
+                })?;
+                let pairs = via.join.iter().map(|(p, m)| (*p, *m)).collect::<Vec<_>>();
+                let index = index_by(&mappings, pairs.iter().map(|(_, m)| *m));
+                parents
+                    .iter()
+                    .map(|x| {
+                        key_of(x, pairs.iter().map(|(p, _)| *p))
+                            .and_then(|k| index.get(&k))
+                            .map(|is| is.iter().map(|&i| mappings[i].clone()).collect())
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            }
+        };
+
+        for (s, t) in relation.join {
+            query.conditions.push(Condition::In(
+                t,
+                distinct_values(sources.iter().flatten(), s),
+            ));
+        }
+        // 親ごとに必要な先頭offset + limit件までを、結合するカラムの値の組ごとに取得する
+        query.partition_by = relation.join.iter().map(|(_, t)| *t).collect();
+        query.limit = Some(offset.saturating_add(limit));
+        let targets = self.gtfs.select_records(&query)?;
+        let index = index_by(&targets, relation.join.iter().map(|(_, t)| *t));
+
+        let matched = sources
+            .iter()
+            .map(|xs| {
+                let mut is = xs
+                    .iter()
+                    .filter_map(|x| key_of(x, relation.join.iter().map(|(s, _)| *s)))
+                    .filter_map(|k| index.get(&k))
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>();
+                // 関連先の並び順を保つ
+                is.sort_unstable();
+                is.dedup();
+                is.into_iter()
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let flat = matched
+            .iter()
+            .flatten()
+            .map(|&i| targets[i].clone())
+            .collect();
+        let mut resolved = self
+            .resolve_records(target, &field.selection, flat)?
+            .into_iter();
+        Ok(matched
+            .iter()
+            .map(|is| {
+                let values = resolved.by_ref().take(is.len()).collect::<Vec<_>>();
+                if relation.list {
+                    Value::Array(values)
+                } else {
+                    values.into_iter().next().unwrap_or(Value::Null)
+                }
+            })
+            .collect())
+    }
+}
+
+fn as_u32(name: &str, value: &Value) -> Result<u32> {
+    value
+        .as_u64()
+        .and_then(|x| u32::try_from(x).ok())
+        .with_context(|| format!("{}は0以上の整数で指定してください", name))
+}
+
+fn to_sql(value: &Value) -> Option<SqlValue> {
+    match value {
+        Value::String(x) => Some(SqlValue::Text(x.clone())),
+        Value::Bool(x) => Some(SqlValue::Integer(*x as i64)),
+        Value::Number(x) => x
+            .as_i64()
+            .map(SqlValue::Integer)
+            .or_else(|| x.as_f64().map(SqlValue::Real)),
+        _ => None,
+    }
+}
+
+fn distinct_values<'r>(records: impl Iterator<Item = &'r Record>, column: &str) -> Vec<SqlValue> {
+    let mut seen = HashSet::new();
+    records
+        .filter_map(|x| x.get(column))
+        .filter(|x| seen.insert(x.to_string()))
+        .filter_map(to_sql)
+        .collect()
+}
+
+/// カラムの値の組. いずれかがnullの場合は関連しない
+fn key_of<'c>(record: &Record, columns: impl Iterator<Item = &'c str>) -> Option<String> {
+    columns
+        .map(|x| {
+            record
+                .get(x)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_string())
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|x| x.join("\u{1f}"))
+}
+
+fn index_by<'c>(
+    records: &[Record],
+    columns: impl Iterator<Item = &'c str> + Clone,
+) -> HashMap<String, Vec<usize>> {
+    let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, record) in records.iter().enumerate() {
+        if let Some(key) = key_of(record, columns.clone()) {
+            index.entry(key).or_default().push(i);
+        }
+    }
+    index
+}
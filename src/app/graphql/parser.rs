@@ -0,0 +1,325 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use serde_json::{Map, Value};
+
+/// クエリで選択したフィールド
+#[derive(Debug, PartialEq, Clone)]
+pub struct Field {
+    /// 別名 (レスポンスのキー)
+    pub alias: Option<String>,
+    pub name: String,
+    /// 変数を値に置き換えた引数
+    pub arguments: Map<String, Value>,
+    pub selection: Vec<Field>,
+}
+
+impl Field {
+    pub fn key(&self) -> &str {
+        self.alias.as_ref().unwrap_or(&self.name)
+    }
+
+    /// 選択の入れ子の深さ (ex: `stops { stop_id }`は1, `stop_id`は0)
+    pub fn depth(&self) -> usize {
+        if self.selection.is_empty() {
+            return 0;
+        }
+        1 + self.selection.iter().map(|x| x.depth()).max().unwrap_or(0)
+    }
+
+    /// 自身と子孫のフィールド数
+    pub fn complexity(&self) -> usize {
+        1 + self.selection.iter().map(|x| x.complexity()).sum::<usize>()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Name(String),
+    Variable(String),
+    String(String),
+    Number(String),
+    Punctuator(char),
+}
+
+/// 選択やリストの入れ子の深さの上限 (max_depthにかかわらず、再帰でスタックを使い切らないようにする)
+pub const MAX_NESTING: usize = 64;
+
+/// 1つのqueryオペレーションのみ解釈する (mutation, subscription, fragment, directiveは扱わない)
+/// 選択の入れ子がmax_depthを超えた時点でエラーにする
+pub fn parse(
+    query: &str,
+    variables: &Map<String, Value>,
+    max_depth: usize,
+) -> Result<Vec<Field>, String> {
+    let tokens = tokenize(query)?;
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+        variables: variables.clone(),
+        max_depth: max_depth.min(MAX_NESTING),
+        depth: 0,
+        nesting: 0,
+    };
+    let fields = parser.operation()?;
+    match parser.tokens.next() {
+        None => Ok(fields),
+        Some(t) => Err(format!("複数のオペレーションには対応していません: {:?}", t)),
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let mut chars = query.chars().peekable();
+    let mut tokens = vec![];
+    while let Some(&c) = chars.peek() {
+        match c {
+            // カンマは空白と同じ扱い
+            c if c.is_whitespace() || c == ',' || c == '\u{feff}' => {
+                chars.next();
+            }
+            '#' => while !matches!(chars.next(), None | Some('\n')) {},
+            '{' | '}' | '(' | ')' | ':' | '[' | ']' | '!' | '=' => {
+                tokens.push(Token::Punctuator(c));
+                chars.next();
+            }
+            '.' => return Err("fragmentには対応していません".into()),
+            '@' => return Err("directiveには対応していません".into()),
+            '$' => {
+                chars.next();
+                tokens.push(Token::Variable(name(&mut chars)));
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::String(string(&mut chars)?));
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '+' || c == '.' {
+                        number.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Number(number));
+            }
+            c if c == '_' || c.is_ascii_alphabetic() => tokens.push(Token::Name(name(&mut chars))),
+            c => return Err(format!("解釈できない文字です: {}", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn name(chars: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if c == '_' || c.is_ascii_alphanumeric() {
+            name.push(c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    name
+}
+
+fn string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            None | Some('\n') => return Err("文字列が閉じられていません".into()),
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some('r') => s.push('\r'),
+                Some('u') => {
+                    let code = chars.by_ref().take(4).collect::<String>();
+                    let c = u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(std::char::from_u32)
+                        .ok_or(format!("不正なエスケープです: \\u{}", code))?;
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+                None => return Err("文字列が閉じられていません".into()),
+            },
+            Some(c) => s.push(c),
+        }
+    }
+}
+
+struct Parser<I: Iterator<Item = Token>> {
+    tokens: Peekable<I>,
+    variables: Map<String, Value>,
+    max_depth: usize,
+    /// 選択の入れ子の深さ (queryの直下を0とする)
+    depth: usize,
+    /// 値や型のリストの入れ子の深さ
+    nesting: usize,
+}
+
+impl<I: Iterator<Item = Token>> Parser<I> {
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.tokens.next() {
+            Some(Token::Punctuator(x)) if x == c => Ok(()),
+            t => Err(format!("{} が必要です: {:?}", c, t)),
+        }
+    }
+
+    fn next_is(&mut self, c: char) -> bool {
+        self.tokens.peek() == Some(&Token::Punctuator(c))
+    }
+
+    /// リストに入る. 入れ子が上限を超える場合はエラー
+    fn enter_list(&mut self) -> Result<(), String> {
+        if self.nesting >= MAX_NESTING {
+            return Err(format!(
+                "リストの入れ子が上限 {} を超えています",
+                MAX_NESTING
+            ));
+        }
+        self.nesting += 1;
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        match self.tokens.next() {
+            Some(Token::Name(x)) => Ok(x),
+            t => Err(format!("名前が必要です: {:?}", t)),
+        }
+    }
+
+    /// `{ ... }` または `query Name($a: Type = default) { ... }`
+    fn operation(&mut self) -> Result<Vec<Field>, String> {
+        if let Some(Token::Name(keyword)) = self.tokens.peek() {
+            if keyword != "query" {
+                return Err(format!("{} には対応していません", keyword));
+            }
+            self.tokens.next();
+            if let Some(Token::Name(_)) = self.tokens.peek() {
+                self.tokens.next();
+            }
+            if self.next_is('(') {
+                self.variable_definitions()?;
+            }
+        }
+        self.selection_set()
+    }
+
+    /// 変数の型は検証せず、既定値のみ読みこむ
+    fn variable_definitions(&mut self) -> Result<(), String> {
+        self.expect('(')?;
+        while !self.next_is(')') {
+            let variable = match self.tokens.next() {
+                Some(Token::Variable(x)) => x,
+                t => return Err(format!("変数が必要です: {:?}", t)),
+            };
+            self.expect(':')?;
+            self.type_reference()?;
+            if self.next_is('=') {
+                self.tokens.next();
+                let default = self.value()?;
+                self.variables.entry(variable).or_insert(default);
+            }
+        }
+        self.expect(')')
+    }
+
+    fn type_reference(&mut self) -> Result<(), String> {
+        if self.next_is('[') {
+            self.tokens.next();
+            self.enter_list()?;
+            self.type_reference()?;
+            self.expect(']')?;
+            self.nesting -= 1;
+        } else {
+            self.name()?;
+        }
+        if self.next_is('!') {
+            self.tokens.next();
+        }
+        Ok(())
+    }
+
+    fn selection_set(&mut self) -> Result<Vec<Field>, String> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        while !self.next_is('}') {
+            fields.push(self.field()?);
+        }
+        self.expect('}')?;
+        Ok(fields)
+    }
+
+    fn field(&mut self) -> Result<Field, String> {
+        let first = self.name()?;
+        let (alias, name) = if self.next_is(':') {
+            self.tokens.next();
+            (Some(first), self.name()?)
+        } else {
+            (None, first)
+        };
+
+        let mut arguments = Map::new();
+        if self.next_is('(') {
+            self.tokens.next();
+            while !self.next_is(')') {
+                let key = self.name()?;
+                self.expect(':')?;
+                arguments.insert(key, self.value()?);
+            }
+            self.expect(')')?;
+        }
+
+        let selection = if self.next_is('{') {
+            if self.depth >= self.max_depth {
+                return Err(format!(
+                    "クエリの深さが上限 {} を超えています",
+                    self.max_depth
+                ));
+            }
+            self.depth += 1;
+            let selection = self.selection_set()?;
+            self.depth -= 1;
+            selection
+        } else {
+            vec![]
+        };
+
+        Ok(Field {
+            alias,
+            name,
+            arguments,
+            selection,
+        })
+    }
+
+    /// 列挙値は文字列として扱う
+    fn value(&mut self) -> Result<Value, String> {
+        match self.tokens.next() {
+            Some(Token::Variable(x)) => Ok(self.variables.get(&x).cloned().unwrap_or(Value::Null)),
+            Some(Token::String(x)) => Ok(Value::String(x)),
+            Some(Token::Number(x)) => {
+                serde_json::from_str(&x).map_err(|_| format!("不正な数値です: {}", x))
+            }
+            Some(Token::Name(x)) => Ok(match x.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => Value::String(x),
+            }),
+            Some(Token::Punctuator('[')) => {
+                self.enter_list()?;
+                let mut values = vec![];
+                while !self.next_is(']') {
+                    values.push(self.value()?);
+                }
+                self.expect(']')?;
+                self.nesting -= 1;
+                Ok(Value::Array(values))
+            }
+            t => Err(format!("値が必要です: {:?}", t)),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::external::gtfs::agency::Agency;
+use crate::external::gtfs::calendar::Calendar;
+use crate::external::gtfs::calendar_dates::CalendarDate;
+use crate::external::gtfs::extended::nodes::{Node, Stop2Node};
+use crate::external::gtfs::extended::service_routes::ServiceRoute;
+use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
+use crate::external::gtfs::routes::Route;
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::trips::Trip;
+use crate::external::gtfsdb::Table;
+
+/// `date`引数で運行日に絞り込む方法
+#[derive(Debug, Clone, Copy)]
+pub enum DateFilter {
+    /// service_idが運行日のservice_idのいずれか
+    ServiceId,
+    /// trip_idが運行日に運行するtripのいずれか
+    TripId,
+}
+
+/// テーブルのレコードを表すGraphQLの型. カラムをそのままフィールドとする
+#[derive(Debug)]
+pub struct ObjectType {
+    pub name: &'static str,
+    pub table: &'static str,
+    pub columns: &'static [&'static str],
+    pub order_by: &'static [&'static str],
+    /// `name`引数で部分一致検索するカラム
+    pub name_columns: &'static [&'static str],
+    pub date_filter: Option<DateFilter>,
+    pub relations: Vec<Relation>,
+}
+
+impl ObjectType {
+    fn of<T: Table>(name: &'static str, order_by: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            table: T::table_name(),
+            columns: T::column_names(),
+            order_by,
+            name_columns: &[],
+            date_filter: None,
+            relations: vec![],
+        }
+    }
+
+    fn name_columns(mut self, columns: &'static [&'static str]) -> Self {
+        self.name_columns = columns;
+        self
+    }
+
+    fn date_filter(mut self, filter: DateFilter) -> Self {
+        self.date_filter = Some(filter);
+        self
+    }
+
+    fn relation(mut self, relation: Relation) -> Self {
+        self.relations.push(relation);
+        self
+    }
+
+    pub fn column(&self, name: &str) -> Option<&'static str> {
+        self.columns.iter().find(|x| **x == name).copied()
+    }
+}
+
+/// 中間テーブル
+#[derive(Debug)]
+pub struct Via {
+    pub table: &'static str,
+    /// (親のカラム, 中間テーブルのカラム)
+    pub join: &'static [(&'static str, &'static str)],
+}
+
+/// 型の間の関連. joinのカラムがすべて一致するレコードを関連とする
+#[derive(Debug)]
+pub struct Relation {
+    pub name: &'static str,
+    pub target: &'static str,
+    pub list: bool,
+    /// (親または中間テーブルのカラム, 関連先のカラム)
+    pub join: &'static [(&'static str, &'static str)],
+    pub via: Option<Via>,
+}
+
+impl Relation {
+    fn one(
+        name: &'static str,
+        target: &'static str,
+        join: &'static [(&'static str, &'static str)],
+    ) -> Self {
+        Self {
+            name,
+            target,
+            list: false,
+            join,
+            via: None,
+        }
+    }
+
+    fn many(
+        name: &'static str,
+        target: &'static str,
+        join: &'static [(&'static str, &'static str)],
+    ) -> Self {
+        Self {
+            list: true,
+            ..Self::one(name, target, join)
+        }
+    }
+
+    fn via<T: Table>(mut self, join: &'static [(&'static str, &'static str)]) -> Self {
+        self.via = Some(Via {
+            table: T::table_name(),
+            join,
+        });
+        self
+    }
+}
+
+/// queryの直下に置くフィールド. listでない場合は最初のレコードのみ返す
+#[derive(Debug)]
+pub struct RootField {
+    pub name: &'static str,
+    pub target: &'static str,
+    pub list: bool,
+}
+
+pub fn object_types() -> Vec<ObjectType> {
+    vec![
+        ObjectType::of::<Agency>("Agency", &["agency_id"])
+            .name_columns(&["agency_name"])
+            .relation(Relation::many(
+                "routes",
+                "Route",
+                &[("agency_id", "agency_id")],
+            )),
+        ObjectType::of::<Route>("Route", &["route_id"])
+            .name_columns(&["route_short_name", "route_long_name"])
+            .relation(Relation::one(
+                "agency",
+                "Agency",
+                &[("agency_id", "agency_id")],
+            ))
+            .relation(Relation::many("trips", "Trip", &[("route_id", "route_id")])),
+        ObjectType::of::<Trip>("Trip", &["trip_id"])
+            .name_columns(&["trip_headsign", "trip_short_name"])
+            .date_filter(DateFilter::ServiceId)
+            .relation(Relation::one("route", "Route", &[("route_id", "route_id")]))
+            .relation(Relation::many(
+                "stop_times",
+                "StopTime",
+                &[("trip_id", "trip_id")],
+            ))
+            .relation(Relation::one(
+                "calendar",
+                "Calendar",
+                &[("service_id", "service_id")],
+            ))
+            .relation(Relation::many(
+                "calendar_dates",
+                "CalendarDate",
+                &[("service_id", "service_id")],
+            ))
+            .relation(
+                Relation::one(
+                    "service_route",
+                    "ServiceRoute",
+                    &[
+                        ("service_route_id", "service_route_id"),
+                        ("service_route_direction_id", "direction_id"),
+                    ],
+                )
+                .via::<Trip2ServiceRoute>(&[("trip_id", "trip_id")]),
+            ),
+        ObjectType::of::<StopTime>("StopTime", &["trip_id", "stop_sequence"])
+            .date_filter(DateFilter::TripId)
+            .relation(Relation::one("trip", "Trip", &[("trip_id", "trip_id")]))
+            .relation(Relation::one("stop", "Stop", &[("stop_id", "stop_id")])),
+        ObjectType::of::<Stop>("Stop", &["stop_id"])
+            .name_columns(&["stop_name"])
+            .relation(Relation::one(
+                "parent",
+                "Stop",
+                &[("parent_station", "stop_id")],
+            ))
+            .relation(Relation::many(
+                "children",
+                "Stop",
+                &[("stop_id", "parent_station")],
+            ))
+            .relation(Relation::many(
+                "stop_times",
+                "StopTime",
+                &[("stop_id", "stop_id")],
+            ))
+            .relation(
+                Relation::one("node", "Node", &[("node_id", "node_id")])
+                    .via::<Stop2Node>(&[("stop_id", "stop_id")]),
+            ),
+        ObjectType::of::<Calendar>("Calendar", &["service_id"])
+            .date_filter(DateFilter::ServiceId)
+            .relation(Relation::many(
+                "trips",
+                "Trip",
+                &[("service_id", "service_id")],
+            ))
+            .relation(Relation::many(
+                "calendar_dates",
+                "CalendarDate",
+                &[("service_id", "service_id")],
+            )),
+        ObjectType::of::<CalendarDate>("CalendarDate", &["service_id", "date"])
+            .date_filter(DateFilter::ServiceId),
+        ObjectType::of::<ServiceRoute>("ServiceRoute", &["service_route_id", "direction_id"])
+            .name_columns(&["service_route_name"])
+            .relation(
+                Relation::many("trips", "Trip", &[("trip_id", "trip_id")])
+                    .via::<Trip2ServiceRoute>(&[
+                        ("service_route_id", "service_route_id"),
+                        ("direction_id", "service_route_direction_id"),
+                    ]),
+            ),
+        ObjectType::of::<Node>("Node", &["node_id"])
+            .name_columns(&["node_name", "node_ruby"])
+            .relation(
+                Relation::many("stops", "Stop", &[("stop_id", "stop_id")])
+                    .via::<Stop2Node>(&[("node_id", "node_id")]),
+            ),
+    ]
+}
+
+pub fn root_fields() -> Vec<RootField> {
+    [
+        ("agencies", "Agency", true),
+        ("agency", "Agency", false),
+        ("routes", "Route", true),
+        ("route", "Route", false),
+        ("trips", "Trip", true),
+        ("trip", "Trip", false),
+        ("stop_times", "StopTime", true),
+        ("stops", "Stop", true),
+        ("stop", "Stop", false),
+        ("calendars", "Calendar", true),
+        ("calendar_dates", "CalendarDate", true),
+        ("service_routes", "ServiceRoute", true),
+        ("service_route", "ServiceRoute", false),
+        ("nodes", "Node", true),
+        ("node", "Node", false),
+    ]
+    .iter()
+    .map(|&(name, target, list)| RootField { name, target, list })
+    .collect()
+}
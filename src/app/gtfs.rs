@@ -1,15 +1,55 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::path::PathBuf;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use itertools::Itertools;
-use log::info;
+use log::{info, warn};
+use ordered_float::OrderedFloat;
+use rusqlite::types::Value;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use crate::external;
-use crate::external::gtfs::extended::nodes::Node;
+use crate::external::gtfs::agency::Agency;
+use crate::external::gtfs::agency_jp::AgencyJp;
+use crate::external::gtfs::areas::Area;
+use crate::external::gtfs::attributions::Attribution;
+use crate::external::gtfs::booking_rules::BookingRule;
+use crate::external::gtfs::calendar::Calendar;
+use crate::external::gtfs::calendar_dates::CalendarDate;
+use crate::external::gtfs::extended::extras::{key_values, ExtraColumns, ExtraRecord};
+use crate::external::gtfs::extended::nodes::{generate_nodes, NodeStrategy};
+use crate::external::gtfs::extended::service_route_problems::{ProblemKind, ServiceRouteProblem};
 use crate::external::gtfs::extended::service_routes;
 use crate::external::gtfs::extended::service_routes::ServiceRouteGenerator;
-use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
-use crate::external::gtfs::translations::Translation;
+use crate::external::gtfs::extended::walking_transfers::WalkingTransfer;
+use crate::external::gtfs::fare_attributes::FareAttribute;
+use crate::external::gtfs::fare_leg_rules::FareLegRule;
+use crate::external::gtfs::fare_media::FareMedia;
+use crate::external::gtfs::fare_products::FareProduct;
+use crate::external::gtfs::fare_rules::FareRule;
+use crate::external::gtfs::fare_transfer_rules::FareTransferRule;
+use crate::external::gtfs::feed_info::Feed;
+use crate::external::gtfs::frequencies::Frequency;
+use crate::external::gtfs::levels::Level;
+use crate::external::gtfs::networks::Network;
+use crate::external::gtfs::office_jp::OfficeJp;
+use crate::external::gtfs::pathways::Pathway;
+use crate::external::gtfs::route_networks::RouteNetwork;
+use crate::external::gtfs::routes::Route;
+use crate::external::gtfs::routes_jp::RouteJp;
+use crate::external::gtfs::shapes::Shape;
+use crate::external::gtfs::stop_areas::StopArea;
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::{Stop, StopId};
+use crate::external::gtfs::transfers::Transfer;
+use crate::external::gtfs::translations::{Translation, LEGACY_TRANSLATABLE_FIELDS};
+use crate::external::gtfs::trips::{Trip, TripId};
+use crate::external::gtfs::{great_circle_distance, OperatorScope, Paging, Second};
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+use crate::io;
 
 pub struct GtfsService<CSV, DB>
 where
@@ -18,6 +58,8 @@ where
 {
     gtfs_csv: CSV,
     gtfs_db: DB,
+    /// insert_tablesで隔離したtripのID
+    quarantined_trip_ids: HashSet<TripId>,
 }
 
 /// GTFS全体を横断するアプリケーションサービス
@@ -27,7 +69,11 @@ where
     DB: external::gtfs::GtfsDbTrait,
 {
     pub fn new(gtfs_csv: CSV, gtfs_db: DB) -> Self {
-        Self { gtfs_csv, gtfs_db }
+        Self {
+            gtfs_csv,
+            gtfs_db,
+            quarantined_trip_ids: HashSet::new(),
+        }
     }
 
     pub fn create_tables(&mut self) -> Result<()> {
@@ -38,33 +84,8 @@ where
     }
 
     /// GTFS-JPの仕様に含まれるテーブルにすべてのレコードを挿入する
-    /// table_nameの指定がないtranslationテーブルを使っている場合はlegacy_translationsをtrueにする。
-    pub fn insert_tables(&mut self, legacy_translations: bool) -> Result<()> {
-        // translationのlegacyフラグが間違っている場合に失敗するため最初に実行
-        if legacy_translations {
-            // 昔のtranslation
-            info!("ℹ️ [translations] Load legacy translations");
-            let translations = self.gtfs_csv.load_legacy_translations().context(
-                "translations.txtのパースに失敗しました。ファイルに問題がない場合はtranslationの定義が新しい仕様に準拠していないか確認してください",
-            )?;
-            let translations = translations
-                .iter()
-                .unique()
-                .flat_map(Translation::from_legacy)
-                .collect_vec();
-            info!("ℹ️ [translations] {} records", translations.len());
-            self.gtfs_db.insert_translations(&translations)?;
-            info!("  ✨ Success");
-        } else {
-            let translations = self.gtfs_csv.load_translations().context(
-                "translations.txtのパースに失敗しました。ファイルに問題がない場合はtranslationが古い仕様に準拠していないか確認してください",
-            )?;
-            let translations = translations.into_iter().unique().collect_vec();
-            info!("ℹ️ [translations] {} records", translations.len());
-            self.gtfs_db.insert_translations(&translations)?;
-            info!("  ✨ Success");
-        }
-
+    /// 通過時刻を補間できないtripがある場合、lenientならそのtripのstop_timesを除外して隔離し、そうでなければ問題を列挙して失敗する
+    pub fn insert_tables(&mut self, lenient: bool) -> Result<()> {
         let agencies = self.gtfs_csv.load_agencies()?;
         let agencies = agencies.into_iter().unique().collect_vec();
         info!("ℹ️ [agencies] {} records", agencies.len());
@@ -137,6 +158,21 @@ where
 
         let stop_times = self.gtfs_csv.load_stop_times()?;
         let stop_times = stop_times.into_iter().unique().collect_vec();
+        let (stop_times, problems) = interpolate_stop_times(stop_times, &stops)?;
+        if !problems.is_empty() {
+            if !lenient {
+                bail!(
+                    "通過時刻を補間できないtripが{}件あります。データを修正するか、--lenientを指定して該当tripを除外してください。\n{}",
+                    problems.len(),
+                    problems.iter().map(|x| format!("  - {}", x)).join("\n")
+                );
+            }
+            for problem in &problems {
+                warn!("⚠️ Quarantine {}", problem);
+            }
+            self.gtfs_db.insert_service_route_problems(&problems)?;
+            self.quarantined_trip_ids = problems.into_iter().map(|x| x.trip_id).collect();
+        }
         info!("ℹ️ [stop_times] {} records", stop_times.len());
         self.gtfs_db.insert_stop_times(&stop_times)?;
         info!("  ✨ Success");
@@ -191,6 +227,130 @@ where
             info!("ℹ️ [transfers] Skip because transfers.txt was not found");
         }
 
+        if self.gtfs_csv.has_levels() {
+            let levels = self.gtfs_csv.load_levels()?;
+            let levels = levels.into_iter().unique().collect_vec();
+            info!("ℹ️ [levels] {} records", levels.len());
+            self.gtfs_db.insert_levels(&levels)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [levels] Skip because levels.txt was not found");
+        }
+
+        if self.gtfs_csv.has_pathways() {
+            let pathways = self.gtfs_csv.load_pathways()?;
+            let pathways = pathways.into_iter().unique().collect_vec();
+            info!("ℹ️ [pathways] {} records", pathways.len());
+            self.gtfs_db.insert_pathways(&pathways)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [pathways] Skip because pathways.txt was not found");
+        }
+
+        if self.gtfs_csv.has_attributions() {
+            let attributions = self.gtfs_csv.load_attributions()?;
+            let attributions = attributions.into_iter().unique().collect_vec();
+            info!("ℹ️ [attributions] {} records", attributions.len());
+            self.gtfs_db.insert_attributions(&attributions)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [attributions] Skip because attributions.txt was not found");
+        }
+
+        if self.gtfs_csv.has_fare_media() {
+            let fare_media = self.gtfs_csv.load_fare_media()?;
+            let fare_media = fare_media.into_iter().unique().collect_vec();
+            info!("ℹ️ [fare_media] {} records", fare_media.len());
+            self.gtfs_db.insert_fare_media(&fare_media)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [fare_media] Skip because fare_media.txt was not found");
+        }
+
+        if self.gtfs_csv.has_fare_products() {
+            let fare_products = self.gtfs_csv.load_fare_products()?;
+            let fare_products = fare_products.into_iter().unique().collect_vec();
+            info!("ℹ️ [fare_products] {} records", fare_products.len());
+            self.gtfs_db.insert_fare_products(&fare_products)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [fare_products] Skip because fare_products.txt was not found");
+        }
+
+        if self.gtfs_csv.has_fare_leg_rules() {
+            let fare_leg_rules = self.gtfs_csv.load_fare_leg_rules()?;
+            let fare_leg_rules = fare_leg_rules.into_iter().unique().collect_vec();
+            info!("ℹ️ [fare_leg_rules] {} records", fare_leg_rules.len());
+            self.gtfs_db.insert_fare_leg_rules(&fare_leg_rules)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [fare_leg_rules] Skip because fare_leg_rules.txt was not found");
+        }
+
+        if self.gtfs_csv.has_fare_transfer_rules() {
+            let fare_transfer_rules = self.gtfs_csv.load_fare_transfer_rules()?;
+            let fare_transfer_rules = fare_transfer_rules.into_iter().unique().collect_vec();
+            info!(
+                "ℹ️ [fare_transfer_rules] {} records",
+                fare_transfer_rules.len()
+            );
+            self.gtfs_db
+                .insert_fare_transfer_rules(&fare_transfer_rules)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [fare_transfer_rules] Skip because fare_transfer_rules.txt was not found");
+        }
+
+        if self.gtfs_csv.has_areas() {
+            let areas = self.gtfs_csv.load_areas()?;
+            let areas = areas.into_iter().unique().collect_vec();
+            info!("ℹ️ [areas] {} records", areas.len());
+            self.gtfs_db.insert_areas(&areas)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [areas] Skip because areas.txt was not found");
+        }
+
+        if self.gtfs_csv.has_stop_areas() {
+            let stop_areas = self.gtfs_csv.load_stop_areas()?;
+            let stop_areas = stop_areas.into_iter().unique().collect_vec();
+            info!("ℹ️ [stop_areas] {} records", stop_areas.len());
+            self.gtfs_db.insert_stop_areas(&stop_areas)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [stop_areas] Skip because stop_areas.txt was not found");
+        }
+
+        if self.gtfs_csv.has_networks() {
+            let networks = self.gtfs_csv.load_networks()?;
+            let networks = networks.into_iter().unique().collect_vec();
+            info!("ℹ️ [networks] {} records", networks.len());
+            self.gtfs_db.insert_networks(&networks)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [networks] Skip because networks.txt was not found");
+        }
+
+        if self.gtfs_csv.has_route_networks() {
+            let route_networks = self.gtfs_csv.load_route_networks()?;
+            let route_networks = route_networks.into_iter().unique().collect_vec();
+            info!("ℹ️ [route_networks] {} records", route_networks.len());
+            self.gtfs_db.insert_route_networks(&route_networks)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [route_networks] Skip because route_networks.txt was not found");
+        }
+
+        if self.gtfs_csv.has_booking_rules() {
+            let booking_rules = self.gtfs_csv.load_booking_rules()?;
+            let booking_rules = booking_rules.into_iter().unique().collect_vec();
+            info!("ℹ️ [booking_rules] {} records", booking_rules.len());
+            self.gtfs_db.insert_booking_rules(&booking_rules)?;
+            info!("  ✨ Success");
+        } else {
+            info!("ℹ️ [booking_rules] Skip because booking_rules.txt was not found");
+        }
+
         // GTFS-JPでは必須
         let feeds = self.gtfs_csv.load_feeds()?;
         let feeds = feeds.into_iter().unique().collect_vec();
@@ -198,14 +358,276 @@ where
         self.gtfs_db.insert_feeds(&feeds)?;
         info!("  ✨ Success");
 
+        // 古い仕様の場合は翻訳元の値が使われているテーブルを参照するため最後に実行
+        self.insert_translations()?;
+
+        Ok(())
+    }
+
+    /// translationsテーブルにレコードを挿入する
+    /// table_nameの指定がない古い仕様のtranslations.txtはヘッダから判別し、新しい仕様に変換する
+    fn insert_translations(&mut self) -> Result<()> {
+        let translations = if self.gtfs_csv.is_legacy_translations()? {
+            info!("ℹ️ [translations] Load legacy translations");
+            let legacy_translations = self
+                .gtfs_csv
+                .load_legacy_translations()
+                .context("translations.txt(古い仕様)のパースに失敗しました")?;
+
+            let mut values_by_field = vec![];
+            for (table_name, field_name) in LEGACY_TRANSLATABLE_FIELDS {
+                let values: HashSet<String> = self
+                    .gtfs_db
+                    .select_translatable_values(table_name, field_name)?
+                    .into_iter()
+                    .collect();
+                values_by_field.push((table_name, field_name, values));
+            }
+
+            legacy_translations
+                .iter()
+                .unique()
+                .flat_map(|legacy| {
+                    values_by_field
+                        .iter()
+                        .filter(move |(_, _, values)| values.contains(&legacy.trans_id))
+                        .map(move |(table_name, field_name, _)| {
+                            Translation::from_legacy(legacy, table_name, field_name)
+                        })
+                })
+                .collect_vec()
+        } else {
+            let translations = self
+                .gtfs_csv
+                .load_translations()
+                .context("translations.txtのパースに失敗しました")?;
+            translations.into_iter().unique().collect_vec()
+        };
+
+        info!("ℹ️ [translations] {} records", translations.len());
+        self.gtfs_db.insert_translations(&translations)?;
+        info!("  ✨ Success");
+
+        Ok(())
+    }
+
+    /// GTFSの仕様外のカラムとファイルをすべて保持する
+    ///   - 既知のファイルに含まれる仕様外カラムは、各テーブルのextrasカラムにJSONとして格納する
+    ///   - 未知の.txtファイルは、extra_recordsテーブルにkey/value形式で格納する
+    pub fn insert_extras(&mut self) -> Result<()> {
+        // 古い仕様のtranslationsは変換後のレコードと1対1に対応しないため対象外
+        let translations = if self.gtfs_csv.is_legacy_translations()? {
+            Translation::file_name()
+        } else {
+            self.insert_extra_columns::<Translation>()?
+        };
+
+        let known_file_names = vec![
+            self.insert_extra_columns::<Agency>()?,
+            self.insert_extra_columns::<AgencyJp>()?,
+            self.insert_extra_columns::<OfficeJp>()?,
+            self.insert_extra_columns::<Calendar>()?,
+            self.insert_extra_columns::<CalendarDate>()?,
+            self.insert_extra_columns::<Stop>()?,
+            self.insert_extra_columns::<Route>()?,
+            self.insert_extra_columns::<RouteJp>()?,
+            self.insert_extra_columns::<Trip>()?,
+            self.insert_extra_columns::<StopTime>()?,
+            self.insert_extra_columns::<FareAttribute>()?,
+            self.insert_extra_columns::<FareRule>()?,
+            self.insert_extra_columns::<Shape>()?,
+            self.insert_extra_columns::<Frequency>()?,
+            self.insert_extra_columns::<Transfer>()?,
+            self.insert_extra_columns::<Level>()?,
+            self.insert_extra_columns::<Pathway>()?,
+            self.insert_extra_columns::<Attribution>()?,
+            self.insert_extra_columns::<FareMedia>()?,
+            self.insert_extra_columns::<FareProduct>()?,
+            self.insert_extra_columns::<FareLegRule>()?,
+            self.insert_extra_columns::<FareTransferRule>()?,
+            self.insert_extra_columns::<Area>()?,
+            self.insert_extra_columns::<StopArea>()?,
+            self.insert_extra_columns::<Network>()?,
+            self.insert_extra_columns::<RouteNetwork>()?,
+            self.insert_extra_columns::<BookingRule>()?,
+            self.insert_extra_columns::<Feed>()?,
+            translations,
+        ];
+
+        for file_name in self.gtfs_csv.list_file_names()? {
+            if known_file_names.contains(&file_name.as_str()) {
+                continue;
+            }
+            let records = self.gtfs_csv.load_raw(&file_name)?;
+            let extra_records = ExtraRecord::from_raw(&file_name, &records);
+            info!(
+                "ℹ️ [extra_records] {} records from {}",
+                extra_records.len(),
+                file_name
+            );
+            self.gtfs_db.insert_extra_records(&extra_records)?;
+            info!("  ✨ Success");
+        }
+
+        Ok(())
+    }
+
+    /// ファイルに仕様外のカラムがあればテーブルのextrasカラムに格納する. 処理したファイル名を返却する
+    fn insert_extra_columns<T>(&mut self) -> Result<&'static str>
+    where
+        T: GTFSFile + Table + DeserializeOwned + Serialize + Eq + Hash,
+    {
+        let file_name = T::file_name();
+        if !self.gtfs_csv.has_file(file_name) {
+            return Ok(file_name);
+        }
+
+        let headers = self.gtfs_csv.load_headers(file_name)?;
+        let extra_columns = headers
+            .into_iter()
+            .filter(|x| !T::column_names().contains(&x.as_str()))
+            .collect_vec();
+        if extra_columns.is_empty() {
+            return Ok(file_name);
+        }
+
+        // 主キーがないテーブルは、挿入順 (重複を除いたファイルの行順) のrowidでレコードを特定する
+        let primary_keys = T::primary_key_names();
+        let mut inserted = HashSet::new();
+        let mut extras = vec![];
+        for raw in self.gtfs_csv.load_raw(file_name)? {
+            let record: T = io::deserialize_raw(&raw)?;
+            let keys = if primary_keys.is_empty() {
+                vec![(
+                    "rowid".to_string(),
+                    Value::Integer(inserted.len() as i64 + 1),
+                )]
+            } else {
+                key_values(&record, primary_keys)?
+            };
+            if inserted.insert(record) {
+                extras.push(ExtraColumns::new(keys, &raw, &extra_columns));
+            }
+        }
+        info!(
+            "ℹ️ [{}] Preserve extra columns: {}",
+            T::table_name(),
+            extra_columns.join(",")
+        );
+        self.gtfs_db.update_extras(T::table_name(), &extras)?;
+        info!("  ✨ Success");
+
+        Ok(file_name)
+    }
+
+    /// stopの近傍検索に使う空間インデックスを作成する
+    pub fn insert_spatial_index(&mut self) -> Result<()> {
+        let count = self.gtfs_db.insert_stops_rtree()?;
+        info!("ℹ️ [stops_rtree] {} records", count);
+        info!("  ✨ Success");
+        Ok(())
+    }
+
+    /// max_distanceメートル以内にある標柱間の徒歩乗換をtransfersに挿入する
+    /// 乗換時間は大円距離をwalking_speed (メートル毎秒) で歩く時間とし、乗換先のstopを通る交通モードの既定乗換時間より短くはしない
+    /// 事業者が提供した乗換と同じ組は生成しない
+    pub fn insert_walking_transfers(
+        &mut self,
+        max_distance: f64,
+        walking_speed: f64,
+    ) -> Result<()> {
+        if walking_speed <= 0.0 {
+            bail!(
+                "歩行速度は0より大きい値を指定してください: {}",
+                walking_speed
+            );
+        }
+        let provided: HashSet<(StopId, StopId)> = self
+            .gtfs_db
+            .select_transfers()?
+            .into_iter()
+            .map(|x| (x.from_stop_id, x.to_stop_id))
+            .collect();
+        let poles = self
+            .gtfs_db
+            .select_stop_details()?
+            .into_iter()
+            .filter(|x| x.is_pole())
+            .collect_vec();
+        let pole_ids: HashSet<&StopId> = poles.iter().map(|x| &x.stop_id).collect();
+        // 乗換先のstopを通る交通モードの既定乗換時間のうち最大のもの
+        let mode_transfer_times: HashMap<StopId, Second> = self
+            .gtfs_db
+            .select_route_types_by_stop()?
+            .into_iter()
+            .map(|(stop_id, route_type)| (stop_id, route_type.mode().default_min_transfer_time()))
+            .into_grouping_map()
+            .max();
+
+        let mut walking_transfers = vec![];
+        for from in &poles {
+            let nearby = self.gtfs_db.select_stops_in_bounds(
+                from.stop_lat.0 as f64,
+                from.stop_lon.0 as f64,
+                max_distance,
+                &Paging::default(),
+            )?;
+            for to in nearby {
+                if to.stop_id == from.stop_id
+                    || !pole_ids.contains(&to.stop_id)
+                    || provided.contains(&(from.stop_id.clone(), to.stop_id.clone()))
+                {
+                    continue;
+                }
+                let distance = great_circle_distance(
+                    (from.stop_lat, from.stop_lon),
+                    (to.stop_lat, to.stop_lon),
+                );
+                if distance > max_distance {
+                    continue;
+                }
+                walking_transfers.push(WalkingTransfer {
+                    from_stop_id: from.stop_id.clone(),
+                    to_stop_id: to.stop_id,
+                    distance: OrderedFloat(distance),
+                });
+            }
+        }
+        walking_transfers.sort_by(|a, b| {
+            (&a.from_stop_id, &a.to_stop_id).cmp(&(&b.from_stop_id, &b.to_stop_id))
+        });
+        let transfers = walking_transfers
+            .iter()
+            .map(|x| {
+                let walking_time = (x.distance.0 / walking_speed).ceil() as Second;
+                let min_transfer_time = mode_transfer_times
+                    .get(&x.to_stop_id)
+                    .map_or(walking_time, |t| walking_time.max(*t));
+                Transfer::walking(
+                    x.from_stop_id.clone(),
+                    x.to_stop_id.clone(),
+                    min_transfer_time,
+                )
+            })
+            .collect_vec();
+
+        info!(
+            "ℹ️ [transfers] {} generated walking records",
+            transfers.len()
+        );
+        self.gtfs_db.insert_transfers(&transfers)?;
+        self.gtfs_db.insert_walking_transfers(&walking_transfers)?;
+        info!("  ✨ Success");
         Ok(())
     }
 
     /// 独自の概念service_routeに関するテーブルにすべてのレコードを挿入する
+    /// service_routeを決められないtripがある場合、lenientならそのtripを除外して隔離し、そうでなければ問題を列挙して失敗する
     pub fn insert_service_routes_tables(
         &mut self,
         service_route_identify_strategy: &service_routes::IdentifyStrategy,
         service_route_identity_path: Option<&PathBuf>,
+        lenient: bool,
     ) -> Result<()> {
         let service_route_identities = match service_route_identity_path {
             Some(path) => Some(self.gtfs_csv.load_service_route_identity(path)?),
@@ -214,27 +636,55 @@ where
         let mut service_route_generator = ServiceRouteGenerator::new(
             service_route_identify_strategy,
             service_route_identities.as_ref(),
-        );
+        )
+        .with_nodes(&self.gtfs_db.select_stops2nodes()?);
 
         // trips2service_routes
-        let stop_time_details = self.gtfs_db.select_stop_time_details(None, None)?;
-        let trip_ids2service_route_ids = stop_time_details
+        let stop_time_details_by_trip_id = self
+            .gtfs_db
+            .select_stop_time_details(None, None, &OperatorScope::default(), &Paging::default())?
             .into_iter()
             .into_group_map_by(|x| x.trip_id.clone())
             .into_iter()
             .sorted_by_key(|(trip_id, _)| trip_id.clone())
-            .map(|(trip_id, details)| {
-                // TODO: ちゃんと例外処理したい。。
-                let service_route = service_route_generator.generate(&details).unwrap();
-                Trip2ServiceRoute {
-                    trip_id,
-                    service_route_id: service_route.service_route_id,
-                    service_route_direction_id: service_route.direction_id,
-                }
+            .collect_vec();
+        let (trip_ids2service_route_ids, generate_problems) =
+            service_route_generator.generate_all(stop_time_details_by_trip_id)?;
+        let mut problems = self
+            .gtfs_db
+            .select_trip_ids_without_stop_times()?
+            .into_iter()
+            .filter(|trip_id| !self.quarantined_trip_ids.contains(trip_id))
+            .map(|trip_id| {
+                ServiceRouteProblem::new(
+                    &trip_id,
+                    ProblemKind::NoStopTimes,
+                    "stop_timesが存在しません",
+                )
             })
+            .collect_vec();
+        problems.extend(generate_problems);
+        let trip_ids2service_route_ids = trip_ids2service_route_ids
+            .into_iter()
             .sorted_by_key(|x| x.service_route_id)
             .collect_vec();
 
+        if !problems.is_empty() {
+            if !lenient {
+                bail!(
+                    "service_routeを作成できないtripが{}件あります。データを修正するか、--lenientを指定して該当tripを除外してください。\n{}",
+                    problems.len(),
+                    problems.iter().map(|x| format!("  - {}", x)).join("\n")
+                );
+            }
+            for problem in &problems {
+                warn!("⚠️ Quarantine {}", problem);
+            }
+            info!("ℹ️ [service_route_problems] {} records", problems.len());
+            self.gtfs_db.insert_service_route_problems(&problems)?;
+            info!("  ✨ Success");
+        }
+
         info!(
             "ℹ️ [trips2service_routes] {} records",
             trip_ids2service_route_ids.len()
@@ -254,29 +704,50 @@ where
         self.gtfs_db.insert_service_routes(&service_routes)?;
         info!("  ✨ Success");
 
+        // service_route_merges
+        let merges = service_route_generator.merges();
+        info!("ℹ️ [service_route_merges] {} records", merges.len());
+        for (reason, group) in merges
+            .iter()
+            .into_group_map_by(|x| x.reason.to_string())
+            .into_iter()
+            .sorted_by_key(|(reason, _)| reason.clone())
+        {
+            info!("  ・{}: {} trips", reason, group.len());
+        }
+        self.gtfs_db.insert_service_route_merges(merges)?;
+        info!("  ✨ Success");
+
         Ok(())
     }
 
     /// 独自の概念nodeに関するテーブルにすべてのレコードを挿入する
-    pub fn insert_nodes_tables(&mut self) -> Result<()> {
+    /// node_distanceはSameName戦略で同名のstopをまとめる距離 (メートル)
+    pub fn insert_nodes_tables(
+        &mut self,
+        node_strategy: &NodeStrategy,
+        node_distance: f64,
+        node_mapping_path: Option<&PathBuf>,
+    ) -> Result<()> {
+        let mappings = match node_mapping_path {
+            Some(path) => self.gtfs_csv.load_node_mappings(path)?,
+            None if matches!(node_strategy, NodeStrategy::Mapping) => {
+                bail!("mapping戦略ではnode対応ファイルの指定が必要です")
+            }
+            None => vec![],
+        };
         let stop_details = self.gtfs_db.select_stop_details()?;
-        let mut i = 0;
-        let nodes = stop_details
-            .into_iter()
-            .filter(|x| x.parent_station.is_none())
-            .map(|x| {
-                i += 1;
-                Node {
-                    node_id: i,
-                    node_name: x.stop_name.clone(),
-                    node_ruby: x.stop_ruby,
-                }
-            })
-            .collect_vec();
+        let (nodes, stops2nodes) =
+            generate_nodes(&stop_details, node_strategy, node_distance, &mappings);
+
         info!("ℹ️ [nodes] {} records", nodes.len());
         self.gtfs_db.insert_nodes(&nodes)?;
         info!("  ✨ Success");
 
+        info!("ℹ️ [stops2nodes] {} records", stops2nodes.len());
+        self.gtfs_db.insert_stops2nodes(&stops2nodes)?;
+        info!("  ✨ Success");
+
         Ok(())
     }
 
@@ -287,3 +758,104 @@ where
         Ok(())
     }
 }
+
+/// 空欄の到着・出発時刻を前後のタイムポイントから補間する
+/// 全ての通過時刻にshape_dist_traveledがある便はその距離で、それ以外は停留所間の距離で按分する
+/// 始点または終点に時刻がない便は補間できないため、stop_timesを除外して問題として返す
+fn interpolate_stop_times(
+    stop_times: Vec<StopTime>,
+    stops: &[Stop],
+) -> Result<(Vec<StopTime>, Vec<ServiceRouteProblem>)> {
+    let stops_by_id: HashMap<_, _> = stops.iter().map(|x| (&x.stop_id, x)).collect();
+    let mut interpolated_count = 0;
+
+    let mut results = Vec::with_capacity(stop_times.len());
+    let mut problems = vec![];
+    for (trip_id, trip_stop_times) in &stop_times
+        .into_iter()
+        .sorted_by(|a, b| {
+            a.trip_id
+                .cmp(&b.trip_id)
+                .then(a.stop_sequence.cmp(&b.stop_sequence))
+        })
+        .group_by(|x| x.trip_id.clone())
+    {
+        let mut trip_stop_times = trip_stop_times.collect_vec();
+        // 到着・出発のどちらかのみ指定されている場合は同じ時刻とみなす
+        for st in trip_stop_times.iter_mut() {
+            if st.arrival_time.is_none() {
+                st.arrival_time = st.departure_time;
+            }
+            if st.departure_time.is_none() {
+                st.departure_time = st.arrival_time;
+            }
+        }
+
+        let timepoints = trip_stop_times
+            .iter()
+            .positions(|x| x.arrival_time.is_some())
+            .collect_vec();
+        if timepoints.len() == trip_stop_times.len() {
+            results.extend(trip_stop_times);
+            continue;
+        }
+        if timepoints.first() != Some(&0) || timepoints.last() != Some(&(trip_stop_times.len() - 1))
+        {
+            problems.push(ServiceRouteProblem::new(
+                &trip_id,
+                ProblemKind::Uninterpolatable,
+                "始点または終点に時刻がないため補間できません",
+            ));
+            continue;
+        }
+
+        let distances: Vec<f64> = if trip_stop_times
+            .iter()
+            .all(|x| x.shape_dist_traveled.is_some())
+        {
+            trip_stop_times
+                .iter()
+                .map(|x| x.shape_dist_traveled.unwrap().into_inner())
+                .collect()
+        } else {
+            let mut distance = 0.0;
+            let mut distances = vec![distance];
+            for (from, to) in trip_stop_times.iter().tuple_windows() {
+                let (from_stop, to_stop) =
+                    match (stops_by_id.get(&from.stop_id), stops_by_id.get(&to.stop_id)) {
+                        (Some(f), Some(t)) => (f, t),
+                        _ => bail!("便 {} の停留所がstops.txtに存在しません", trip_id),
+                    };
+                distance += from_stop.distance(to_stop);
+                distances.push(distance);
+            }
+            distances
+        };
+
+        for (&from, &to) in timepoints.iter().tuple_windows() {
+            let departure = trip_stop_times[from].departure_time.unwrap();
+            let arrival = trip_stop_times[to].arrival_time.unwrap();
+            let duration = arrival.seconds_since(&departure).max(0) as f64;
+            let total_distance = distances[to] - distances[from];
+            for i in from + 1..to {
+                let ratio = if total_distance > 0.0 {
+                    (distances[i] - distances[from]) / total_distance
+                } else {
+                    (i - from) as f64 / (to - from) as f64
+                };
+                let time = departure + (duration * ratio).round() as Second;
+                let st = &mut trip_stop_times[i];
+                st.arrival_time = Some(time);
+                st.departure_time = Some(time);
+                st.interpolated = true;
+                interpolated_count += 1;
+            }
+        }
+        results.extend(trip_stop_times);
+    }
+
+    if interpolated_count > 0 {
+        info!("ℹ️ [stop_times] {} records interpolated", interpolated_count);
+    }
+    Ok((results, problems))
+}
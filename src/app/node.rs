@@ -0,0 +1,96 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::extended::node_details::NodeDeparture;
+use crate::external::gtfs::extended::nodes::{Node, NodeId};
+use crate::external::gtfs::extended::service_routes::ServiceRoute;
+use crate::external::gtfs::routes::Route;
+use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::{GtfsDbTrait, GtfsTime, OperatorScope, Paging};
+use crate::external::gtfsdb::GtfsDb;
+
+/// ノードと、それに属するstop、ノードを通るrouteとサービスルート
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NodeWithDetails {
+    #[serde(flatten)]
+    pub node: Node,
+    /// ノードに属するstop (親駅・標柱)
+    pub stops: Vec<Stop>,
+    /// ノードを通るroute
+    pub routes: Vec<Route>,
+    /// ノードを通るサービスルート
+    pub service_routes: Vec<ServiceRoute>,
+}
+
+pub struct NodeServiceDb {
+    gtfs: GtfsDb,
+}
+
+impl NodeServiceDb {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    /// node_nameまたはnode_rubyにwordを含むノードを取得する. wordを指定しない場合はすべて
+    /// node_nameまたはnode_rubyにwordを含むノードをpagingの範囲だけ取得する. wordを指定しない場合はすべて
+    pub fn fetch_nodes(&mut self, word: Option<String>, paging: &Paging) -> Result<Vec<Node>> {
+        self.gtfs.select_nodes(word, paging)
+    }
+
+    /// fetch_nodesでページングしない場合の件数
+    pub fn count_nodes(&mut self, word: Option<String>) -> Result<usize> {
+        Ok(self.gtfs.count_nodes(word)? as usize)
+    }
+
+    pub fn fetch_node(&mut self, node_id: NodeId) -> Result<Option<NodeWithDetails>> {
+        let node = match self.gtfs.select_node(node_id)? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        Ok(Some(NodeWithDetails {
+            stops: self.gtfs.select_node_stops(node_id)?,
+            routes: self.gtfs.select_node_routes(node_id)?,
+            service_routes: self.gtfs.select_node_service_routes(node_id)?,
+            node,
+        }))
+    }
+
+    /// ノードに属するすべてのstopからの出発を出発時刻順に取得する
+    /// dateを指定した場合はその日に運行するtripのみ、fromを指定した場合はその時刻以降のみ、
+    /// scopeを指定した場合はその事業者・営業所のtripのみ. pagingの範囲だけを返す
+    pub fn fetch_departures(
+        &mut self,
+        node_id: NodeId,
+        date: Option<NaiveDate>,
+        from: Option<GtfsTime>,
+        scope: &OperatorScope,
+        paging: &Paging,
+    ) -> Result<Vec<NodeDeparture>> {
+        let service_ids = self.service_ids_of(date)?;
+        self.gtfs
+            .select_node_departures(node_id, service_ids, from.as_ref(), scope, paging)
+    }
+
+    /// fetch_departuresでページングしない場合の件数
+    pub fn count_departures(
+        &mut self,
+        node_id: NodeId,
+        date: Option<NaiveDate>,
+        from: Option<GtfsTime>,
+        scope: &OperatorScope,
+    ) -> Result<usize> {
+        let service_ids = self.service_ids_of(date)?;
+        Ok(self
+            .gtfs
+            .count_node_departures(node_id, service_ids, from.as_ref(), scope)? as usize)
+    }
+
+    fn service_ids_of(&mut self, date: Option<NaiveDate>) -> Result<Option<Vec<ServiceId>>> {
+        Ok(match date {
+            Some(d) => Some(self.gtfs.select_service_ids(&d)?),
+            None => None,
+        })
+    }
+}
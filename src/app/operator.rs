@@ -0,0 +1,36 @@
+use anyhow::Result;
+
+use crate::external::gtfs::extended::agency_details::AgencyDetail;
+use crate::external::gtfs::extended::office_details::OfficeDetail;
+use crate::external::gtfs::{GtfsDbTrait, Paging};
+use crate::external::gtfsdb::GtfsDb;
+
+pub struct OperatorServiceDb {
+    gtfs: GtfsDb,
+}
+
+impl OperatorServiceDb {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    /// 事業者をpagingの範囲だけ取得する
+    pub fn fetch_agencies(&mut self, paging: &Paging) -> Result<Vec<AgencyDetail>> {
+        self.gtfs.select_agency_details(paging)
+    }
+
+    /// fetch_agenciesでページングしない場合の件数
+    pub fn count_agencies(&mut self) -> Result<usize> {
+        Ok(self.gtfs.count_agency_details()? as usize)
+    }
+
+    /// 営業所をpagingの範囲だけ取得する
+    pub fn fetch_offices(&mut self, paging: &Paging) -> Result<Vec<OfficeDetail>> {
+        self.gtfs.select_office_details(paging)
+    }
+
+    /// fetch_officesでページングしない場合の件数
+    pub fn count_offices(&mut self) -> Result<usize> {
+        Ok(self.gtfs.count_office_details()? as usize)
+    }
+}
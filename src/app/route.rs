@@ -1,12 +1,49 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
-use crate::external::gtfs::routes::Route;
-use crate::external::gtfs::{GtfsCsvTrait, GtfsDbTrait};
+use crate::external::gtfs::extended::route_details::RouteDetail;
+use crate::external::gtfs::extended::service_routes::ServiceRoute;
+use crate::external::gtfs::routes::{Route, RouteId, RouteType, TransitMode};
+use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::{GtfsCsvTrait, GtfsDbTrait, OperatorScope, Paging};
 use crate::external::gtfscsv::GtfsCsv;
 use crate::external::gtfsdb::GtfsDb;
 
 pub trait RouteService {
-    fn fetch(&mut self) -> Result<Vec<Route>>;
+    /// modeを指定した場合はその交通モードのrouteのみ取得する
+    /// scopeを指定した場合はその事業者・営業所のrouteのみ取得する
+    fn fetch(&mut self, mode: Option<&TransitMode>, scope: &OperatorScope) -> Result<Vec<Route>>;
+}
+
+/// 交通モードに属する経路タイプのうち、データベースのrouteで使われているものを取得する
+/// (未定義のコードも含めてSQLで経路タイプを絞り込むため)
+pub fn route_types_of(gtfs: &mut GtfsDb, mode: &TransitMode) -> Result<Vec<RouteType>> {
+    Ok(gtfs
+        .select_route_types()?
+        .into_iter()
+        .filter(|x| &x.mode() == mode)
+        .collect_vec())
+}
+
+fn filter_by_mode(routes: Vec<Route>, mode: Option<&TransitMode>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .filter(|x| mode.map_or(true, |m| &x.mode() == m))
+        .collect_vec()
+}
+
+/// routeの詳細と、routeのtripが停車するstop、属するサービスルート
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RouteWithDetails {
+    #[serde(flatten)]
+    pub route: RouteDetail,
+    /// routeのtripが停車するstop
+    pub stops: Vec<Stop>,
+    /// routeのtripが属するサービスルート
+    pub service_routes: Vec<ServiceRoute>,
 }
 
 pub struct RouteServiceCsv {
@@ -20,8 +57,30 @@ impl RouteServiceCsv {
 }
 
 impl RouteService for RouteServiceCsv {
-    fn fetch(&mut self) -> Result<Vec<Route>> {
-        self.gtfs.load_routes()
+    fn fetch(&mut self, mode: Option<&TransitMode>, scope: &OperatorScope) -> Result<Vec<Route>> {
+        let office_route_ids: Option<HashSet<_>> = match &scope.office_id {
+            Some(office_id) => Some(
+                self.gtfs
+                    .load_trips()?
+                    .into_iter()
+                    .filter(|x| x.jp_office_id.as_ref() == Some(office_id))
+                    .map(|x| x.route_id)
+                    .collect(),
+            ),
+            None => None,
+        };
+        let routes = self
+            .gtfs
+            .load_routes()?
+            .into_iter()
+            .filter(|x| scope.agency_id.as_ref().map_or(true, |a| &x.agency_id == a))
+            .filter(|x| {
+                office_route_ids
+                    .as_ref()
+                    .map_or(true, |ids| ids.contains(&x.route_id))
+            })
+            .collect_vec();
+        Ok(filter_by_mode(routes, mode))
     }
 }
 
@@ -33,10 +92,66 @@ impl RouteServiceDb {
     pub fn new(gtfs: GtfsDb) -> Self {
         Self { gtfs }
     }
+
+    /// route_short_nameまたはroute_long_nameにwordを含むrouteの詳細を取得する. wordを指定しない場合はすべて
+    /// modeを指定した場合はその交通モードのrouteのみ、scopeを指定した場合はその事業者・営業所のrouteのみ取得する
+    /// pagingの範囲だけを返す
+    pub fn fetch_route_details(
+        &mut self,
+        word: Option<String>,
+        mode: Option<&TransitMode>,
+        scope: &OperatorScope,
+        paging: &Paging,
+    ) -> Result<Vec<RouteDetail>> {
+        let route_types = self.route_types(mode)?;
+        self.gtfs
+            .select_route_details(None, word, route_types, scope, paging)
+    }
+
+    /// fetch_route_detailsでページングしない場合の件数
+    pub fn count_route_details(
+        &mut self,
+        word: Option<String>,
+        mode: Option<&TransitMode>,
+        scope: &OperatorScope,
+    ) -> Result<usize> {
+        let route_types = self.route_types(mode)?;
+        Ok(self.gtfs.count_route_details(word, route_types, scope)? as usize)
+    }
+
+    fn route_types(&mut self, mode: Option<&TransitMode>) -> Result<Option<Vec<RouteType>>> {
+        match mode {
+            Some(m) => Ok(Some(route_types_of(&mut self.gtfs, m)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn fetch_route(&mut self, route_id: RouteId) -> Result<Option<RouteWithDetails>> {
+        let route = match self
+            .gtfs
+            .select_route_details(
+                Some(route_id.clone()),
+                None,
+                None,
+                &OperatorScope::default(),
+                &Paging::default(),
+            )?
+            .into_iter()
+            .next()
+        {
+            Some(route) => route,
+            None => return Ok(None),
+        };
+        Ok(Some(RouteWithDetails {
+            stops: self.gtfs.select_route_stops(route_id.clone())?,
+            service_routes: self.gtfs.select_route_service_routes(route_id)?,
+            route,
+        }))
+    }
 }
 
 impl RouteService for RouteServiceDb {
-    fn fetch(&mut self) -> Result<Vec<Route>> {
-        self.gtfs.select_routes()
+    fn fetch(&mut self, mode: Option<&TransitMode>, scope: &OperatorScope) -> Result<Vec<Route>> {
+        Ok(filter_by_mode(self.gtfs.select_routes(scope)?, mode))
     }
 }
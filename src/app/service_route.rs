@@ -1,9 +1,154 @@
+use std::collections::{HashMap, HashSet};
+
 use anyhow::Result;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
 
+use crate::external::gtfs::extended::service_route_details::{
+    ServiceRouteSummary, ServiceRouteTrip,
+};
 use crate::external::gtfs::extended::service_route_identity::ServiceRouteIdentity;
-use crate::external::gtfs::GtfsDbTrait;
+use crate::external::gtfs::extended::service_routes::{
+    IdentifyStrategy, ServiceRouteGenerator, ServiceRouteId,
+};
+use crate::external::gtfs::extended::stop_time_details::StopTimeDetail;
+use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{
+    DirectionId, GtfsDbTrait, GtfsTime, Latitude, Longitude, OperatorScope, Paging, Sequence,
+};
 use crate::external::gtfsdb::GtfsDb;
 
+/// サービスルートで停車するstop
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct ServiceRouteStop {
+    /// 通過順位
+    pub stop_sequence: Sequence,
+    /// 標柱ID
+    pub stop_id: StopId,
+    /// 停留所・標柱名称
+    pub stop_name: String,
+    /// 緯度
+    pub stop_lat: Latitude,
+    /// 経度
+    pub stop_lon: Longitude,
+}
+
+impl From<StopTimeDetail> for ServiceRouteStop {
+    fn from(detail: StopTimeDetail) -> Self {
+        Self {
+            stop_sequence: detail.stop_sequence,
+            stop_id: detail.stop_id,
+            stop_name: detail.stop_name,
+            stop_lat: detail.stop_lat,
+            stop_lon: detail.stop_lon,
+        }
+    }
+}
+
+/// サービスルート (停車パターン) と運行の概要
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct ServiceRoutePattern {
+    /// サービスルートID
+    pub service_route_id: ServiceRouteId,
+    /// 上下区分
+    pub direction_id: DirectionId,
+    /// サービスルート名
+    pub service_route_name: String,
+    /// 停車順のstop
+    pub stops: Vec<ServiceRouteStop>,
+    /// trip数
+    pub trip_count: u32,
+    /// 始発の出発時刻
+    pub first_departure_time: GtfsTime,
+    /// 最終の出発時刻
+    pub last_departure_time: GtfsTime,
+}
+
+/// サービスルートとそのtrip
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct ServiceRouteWithTrips {
+    #[serde(flatten)]
+    pub pattern: ServiceRoutePattern,
+    /// 出発時刻順のtrip
+    pub trips: Vec<ServiceRouteTrip>,
+}
+
+/// service_route識別ファイルの問題の種類
+#[derive(Debug, Deserialize, Serialize, Display, Eq, PartialEq, Clone, Hash)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum IdentityIssueKind {
+    /// データベースに存在しないtrip_id
+    UnknownTripId,
+    /// 複数のサービスルートに指定されたtrip_id
+    DuplicateTripId,
+    /// 複数のサービスルートで同じidentifier
+    DuplicateIdentifier,
+    /// tripの上下区分とサービスルートの上下区分が異なる
+    ConflictingDirection,
+    /// 同じサービスルートID・上下区分が異なるサービスルートに使われている
+    IdCollision,
+}
+
+/// service_route識別ファイルの問題
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct IdentityIssue {
+    /// 問題の種類
+    pub kind: IdentityIssueKind,
+    /// サービスルートID
+    pub service_route_id: ServiceRouteId,
+    /// サービスルートの上下区分
+    pub service_route_direction_id: DirectionId,
+    /// 問題のあるtrip_id
+    pub trip_id: Option<TripId>,
+    /// 詳細
+    pub message: String,
+}
+
+impl IdentityIssue {
+    fn new(
+        kind: IdentityIssueKind,
+        identity: &ServiceRouteIdentity,
+        trip_id: Option<&str>,
+        message: String,
+    ) -> Self {
+        Self {
+            kind,
+            service_route_id: identity.service_route_id,
+            service_route_direction_id: identity.service_route_direction_id.clone(),
+            trip_id: trip_id.map(String::from),
+            message,
+        }
+    }
+}
+
+/// service_route識別ファイルを適用した場合のtripの割当の変化
+/// サービスルートは `service_route_id^direction_id` の形式で表す
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct AssignmentDiff {
+    /// 便ID
+    pub trip_id: TripId,
+    /// 現在のサービスルート
+    pub before: Option<String>,
+    /// 適用後のサービスルート
+    pub after: Option<String>,
+}
+
+/// service_route識別ファイルの検証結果
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct IdentityCheckReport {
+    pub issues: Vec<IdentityIssue>,
+    pub diffs: Vec<AssignmentDiff>,
+}
+
+fn full_id_of(service_route_id: ServiceRouteId, direction_id: &DirectionId) -> String {
+    format!("{}^{}", service_route_id, direction_id.clone() as u8)
+}
+
 pub struct ServiceRouteServiceDb {
     gtfs: GtfsDb,
 }
@@ -16,4 +161,257 @@ impl ServiceRouteServiceDb {
     pub fn fetch_service_route_identity(&mut self) -> Result<Vec<ServiceRouteIdentity>> {
         self.gtfs.select_service_route_identity()
     }
+
+    /// service_route識別ファイルをデータベースに対して検証し、適用した場合のtripの割当の変化を求める
+    pub fn check_service_route_identity(
+        &mut self,
+        identities: &[ServiceRouteIdentity],
+        strategy: &IdentifyStrategy,
+    ) -> Result<IdentityCheckReport> {
+        let stop_time_details_by_trip_id = self
+            .gtfs
+            .select_stop_time_details(None, None, &OperatorScope::default(), &Paging::default())?
+            .into_iter()
+            .into_group_map_by(|x| x.trip_id.clone())
+            .into_iter()
+            .sorted_by_key(|(trip_id, _)| trip_id.clone())
+            .collect_vec();
+        let direction_by_trip_id: HashMap<TripId, Option<DirectionId>> =
+            stop_time_details_by_trip_id
+                .iter()
+                .map(|(trip_id, details)| {
+                    (
+                        trip_id.clone(),
+                        details.first().and_then(|x| x.direction_id.clone()),
+                    )
+                })
+                .collect();
+        let trip_ids_without_stop_times: HashSet<TripId> = self
+            .gtfs
+            .select_trip_ids_without_stop_times()?
+            .into_iter()
+            .collect();
+
+        let mut issues = vec![];
+        let mut identity_by_trip_id: HashMap<&str, &ServiceRouteIdentity> = HashMap::new();
+        let mut identity_by_identifier: HashMap<String, &ServiceRouteIdentity> = HashMap::new();
+        let mut identity_by_full_id: HashMap<String, &ServiceRouteIdentity> = HashMap::new();
+        for identity in identities {
+            let full_id = full_id_of(
+                identity.service_route_id,
+                &identity.service_route_direction_id,
+            );
+            match identity_by_full_id.get(&full_id) {
+                Some(other) if other.stop_names != identity.stop_names => {
+                    issues.push(IdentityIssue::new(
+                        IdentityIssueKind::IdCollision,
+                        identity,
+                        None,
+                        format!(
+                            "{}が異なる停車パターン ({} / {}) に使われています",
+                            full_id, other.stop_names, identity.stop_names
+                        ),
+                    ))
+                }
+                _ => {
+                    identity_by_full_id.insert(full_id.clone(), identity);
+                }
+            }
+
+            if let Some(identifier) = strategy.identifier_of(identity) {
+                match identity_by_identifier.get(&identifier) {
+                    Some(other)
+                        if other.service_route_id != identity.service_route_id
+                            || other.service_route_direction_id
+                                != identity.service_route_direction_id =>
+                    {
+                        issues.push(IdentityIssue::new(
+                            IdentityIssueKind::DuplicateIdentifier,
+                            identity,
+                            None,
+                            format!(
+                                "identifier ({}) が{}にも指定されています",
+                                identifier,
+                                full_id_of(
+                                    other.service_route_id,
+                                    &other.service_route_direction_id
+                                )
+                            ),
+                        ))
+                    }
+                    _ => {
+                        identity_by_identifier.insert(identifier, identity);
+                    }
+                }
+            }
+
+            for trip_id in identity.trip_ids.split(',').map(|x| x.trim()) {
+                if let Some(other) = identity_by_trip_id.insert(trip_id, identity) {
+                    issues.push(IdentityIssue::new(
+                        IdentityIssueKind::DuplicateTripId,
+                        identity,
+                        Some(trip_id),
+                        format!(
+                            "{}にも指定されています",
+                            full_id_of(other.service_route_id, &other.service_route_direction_id)
+                        ),
+                    ));
+                }
+                match direction_by_trip_id.get(trip_id) {
+                    Some(Some(direction_id))
+                        if *direction_id != identity.service_route_direction_id =>
+                    {
+                        issues.push(IdentityIssue::new(
+                            IdentityIssueKind::ConflictingDirection,
+                            identity,
+                            Some(trip_id),
+                            format!("tripの上下区分は{}です", direction_id.clone() as u8),
+                        ))
+                    }
+                    Some(_) => {}
+                    None if trip_ids_without_stop_times.contains(trip_id) => {}
+                    None => issues.push(IdentityIssue::new(
+                        IdentityIssueKind::UnknownTripId,
+                        identity,
+                        Some(trip_id),
+                        "データベースに存在しません".to_string(),
+                    )),
+                }
+            }
+        }
+
+        let identities = identities.to_vec();
+        let mut generator = ServiceRouteGenerator::new(strategy, Some(&identities))
+            .with_nodes(&self.gtfs.select_stops2nodes()?);
+        let (after, _) = generator.generate_all(stop_time_details_by_trip_id)?;
+        let mut after: HashMap<TripId, String> = after
+            .into_iter()
+            .map(|x| {
+                let full_id = full_id_of(x.service_route_id, &x.service_route_direction_id);
+                (x.trip_id, full_id)
+            })
+            .collect();
+        let before: HashMap<TripId, String> = self
+            .gtfs
+            .select_all::<Trip2ServiceRoute>()?
+            .into_iter()
+            .map(|x| {
+                let full_id = full_id_of(x.service_route_id, &x.service_route_direction_id);
+                (x.trip_id, full_id)
+            })
+            .collect();
+
+        let mut diffs = before
+            .into_iter()
+            .map(|(trip_id, before)| {
+                let after = after.remove(&trip_id);
+                AssignmentDiff {
+                    trip_id,
+                    before: Some(before),
+                    after,
+                }
+            })
+            .collect_vec();
+        diffs.extend(after.into_iter().map(|(trip_id, after)| AssignmentDiff {
+            trip_id,
+            before: None,
+            after: Some(after),
+        }));
+        diffs.retain(|x| x.before != x.after);
+        diffs.sort_by(|a, b| a.trip_id.cmp(&b.trip_id));
+
+        Ok(IdentityCheckReport { issues, diffs })
+    }
+
+    /// route_id, stop_id, direction_idで絞り込んだサービスルートをpagingの範囲だけ取得する
+    pub fn fetch_service_routes(
+        &mut self,
+        route_id: Option<RouteId>,
+        stop_id: Option<StopId>,
+        direction_id: Option<DirectionId>,
+        paging: &Paging,
+    ) -> Result<Vec<ServiceRoutePattern>> {
+        let summaries = self.gtfs.select_service_route_summaries(
+            None,
+            route_id,
+            stop_id,
+            direction_id,
+            paging,
+        )?;
+        self.build_patterns(summaries)
+    }
+
+    /// fetch_service_routesでページングしない場合の件数
+    pub fn count_service_routes(
+        &mut self,
+        route_id: Option<RouteId>,
+        stop_id: Option<StopId>,
+        direction_id: Option<DirectionId>,
+    ) -> Result<usize> {
+        Ok(self
+            .gtfs
+            .count_service_route_summaries(route_id, stop_id, direction_id)? as usize)
+    }
+
+    pub fn fetch_service_route(
+        &mut self,
+        service_route_id: ServiceRouteId,
+        direction_id: DirectionId,
+    ) -> Result<Option<ServiceRouteWithTrips>> {
+        let summaries = self.gtfs.select_service_route_summaries(
+            Some(service_route_id),
+            None,
+            None,
+            Some(direction_id.clone()),
+            &Paging::default(),
+        )?;
+        let pattern = match self.build_patterns(summaries)?.into_iter().next() {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        let trips = self
+            .gtfs
+            .select_service_route_trips(service_route_id, direction_id)?;
+        Ok(Some(ServiceRouteWithTrips { pattern, trips }))
+    }
+
+    /// 代表tripの停車順をstop一覧としてサービスルートを組み立てる
+    fn build_patterns(
+        &mut self,
+        summaries: Vec<ServiceRouteSummary>,
+    ) -> Result<Vec<ServiceRoutePattern>> {
+        if summaries.is_empty() {
+            return Ok(vec![]);
+        }
+        let trip_ids = summaries
+            .iter()
+            .map(|x| x.representative_trip_id.clone())
+            .collect_vec();
+        let mut stops_by_trip_id: HashMap<_, Vec<ServiceRouteStop>> = self
+            .gtfs
+            .select_stop_time_details(
+                Some(trip_ids),
+                None,
+                &OperatorScope::default(),
+                &Paging::default(),
+            )?
+            .into_iter()
+            .map(|x| (x.trip_id.clone(), x.into()))
+            .into_group_map();
+
+        Ok(summaries
+            .into_iter()
+            .map(|x| ServiceRoutePattern {
+                stops: stops_by_trip_id
+                    .remove(&x.representative_trip_id)
+                    .unwrap_or_default(),
+                service_route_id: x.service_route_id,
+                direction_id: x.direction_id,
+                service_route_name: x.service_route_name,
+                trip_count: x.trip_count,
+                first_departure_time: GtfsTime::from_seconds(x.first_departure_seconds),
+                last_departure_time: GtfsTime::from_seconds(x.last_departure_seconds),
+            })
+            .collect_vec())
+    }
 }
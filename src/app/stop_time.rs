@@ -2,7 +2,7 @@ use anyhow::Result;
 
 use crate::external::gtfs::extended::stop_time_details::StopTimeDetail;
 use crate::external::gtfs::trips::TripId;
-use crate::external::gtfs::GtfsDbTrait;
+use crate::external::gtfs::{GtfsDbTrait, OperatorScope, Paging};
 use crate::external::gtfsdb::GtfsDb;
 
 pub struct StopTimeServiceDb {
@@ -18,8 +18,22 @@ impl StopTimeServiceDb {
         &mut self,
         trip_ids: Option<Vec<TripId>>,
         stop_name_prefix: Option<String>,
+        scope: &OperatorScope,
+        paging: &Paging,
     ) -> Result<Vec<StopTimeDetail>> {
         self.gtfs
-            .select_stop_time_details(trip_ids, stop_name_prefix)
+            .select_stop_time_details(trip_ids, stop_name_prefix, scope, paging)
+    }
+
+    /// fetch_stop_time_detailsでページングしない場合の件数
+    pub fn count_stop_time_details(
+        &mut self,
+        trip_ids: Option<Vec<TripId>>,
+        stop_name_prefix: Option<String>,
+        scope: &OperatorScope,
+    ) -> Result<usize> {
+        Ok(self
+            .gtfs
+            .count_stop_time_details(trip_ids, stop_name_prefix, scope)? as usize)
     }
 }
@@ -1,9 +1,36 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
+use itertools::Itertools;
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
 
-use crate::external::gtfs::stops::Stop;
-use crate::external::gtfs::GtfsDbTrait;
+use crate::external::gtfs::extended::nearby_stops::NearbyStop;
+use crate::external::gtfs::levels::Level;
+use crate::external::gtfs::pathways::Pathway;
+use crate::external::gtfs::stops::{LocationType, Stop, StopId};
+use crate::external::gtfs::{great_circle_distance, GtfsDbTrait, Paging};
 use crate::external::gtfsdb::GtfsDb;
 
+/// 駅(親stop)の構内構造
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StationStructure {
+    /// 駅
+    pub station: Stop,
+    /// のりば (location_type=0)
+    pub platforms: Vec<Stop>,
+    /// 乗車エリア (location_type=4)
+    pub boarding_areas: Vec<Stop>,
+    /// 出入口 (location_type=2)
+    pub entrances: Vec<Stop>,
+    /// 一般ノード (location_type=3)
+    pub generic_nodes: Vec<Stop>,
+    /// 構内のstopが属する階層
+    pub levels: Vec<Level>,
+    /// 構内のstopを結ぶ通路
+    pub pathways: Vec<Pathway>,
+}
+
 pub struct StopServiceDb {
     gtfs: GtfsDb,
 }
@@ -13,7 +40,122 @@ impl StopServiceDb {
         Self { gtfs }
     }
 
-    pub fn fetch_stops(&mut self, word: String) -> Result<Vec<Stop>> {
-        self.gtfs.select_stops(word)
+    /// stop_nameにwordを含むstopをpagingの範囲だけ取得する
+    pub fn fetch_stops(&mut self, word: String, paging: &Paging) -> Result<Vec<Stop>> {
+        self.gtfs.select_stops(word, paging)
+    }
+
+    /// fetch_stopsでページングしない場合の件数
+    pub fn count_stops(&mut self, word: String) -> Result<usize> {
+        Ok(self.gtfs.count_stops(word)? as usize)
+    }
+
+    /// 座標からradiusメートル以内にあるstopを近い順 (paging.sortを指定した場合はその順) にpagingの範囲だけ取得する
+    pub fn fetch_nearby_stops(
+        &mut self,
+        lat: f64,
+        lon: f64,
+        radius: f64,
+        paging: &Paging,
+    ) -> Result<Vec<NearbyStop>> {
+        let center = (OrderedFloat(lat as f32), OrderedFloat(lon as f32));
+        let stops = self
+            .gtfs
+            .select_stops_in_bounds(lat, lon, radius, paging)?
+            .into_iter()
+            .map(|mut x| {
+                x.distance = great_circle_distance(center, (x.stop_lat, x.stop_lon));
+                x
+            });
+        if !paging.sort.is_empty() {
+            return Ok(stops.collect_vec());
+        }
+        // 近似距離で選んだ範囲を正確な距離で並べ直す
+        Ok(stops
+            .sorted_by(|a, b| {
+                OrderedFloat(a.distance)
+                    .cmp(&OrderedFloat(b.distance))
+                    .then(a.stop_id.cmp(&b.stop_id))
+            })
+            .collect_vec())
+    }
+
+    /// fetch_nearby_stopsでページングしない場合の件数
+    pub fn count_nearby_stops(&mut self, lat: f64, lon: f64, radius: f64) -> Result<usize> {
+        Ok(self.gtfs.count_stops_in_bounds(lat, lon, radius)? as usize)
+    }
+
+    /// stop_idのstopが属する駅の構内構造を取得する
+    /// のりばや出入口のstop_idを指定した場合は親を辿って駅を求め、駅に属さない場合はNoneとする
+    pub fn fetch_station_structure(&mut self, stop_id: StopId) -> Result<Option<StationStructure>> {
+        let mut current = self.gtfs.select_stop(stop_id)?;
+        // 親駅の循環参照で止まらないよう、一度辿ったstopに戻ったら打ち切る
+        let mut visited = HashSet::new();
+        let station = loop {
+            let stop = match current {
+                Some(stop) => stop,
+                None => return Ok(None),
+            };
+            if stop.location_type() == LocationType::Stop {
+                break stop;
+            }
+            if !visited.insert(stop.stop_id.clone()) {
+                return Ok(None);
+            }
+            current = match stop.parent_station {
+                Some(parent_station) => self.gtfs.select_stop(parent_station)?,
+                None => None,
+            };
+        };
+
+        let children = self
+            .gtfs
+            .select_stops_by_parents(vec![station.stop_id.clone()])?;
+        let (platforms, children): (Vec<_>, Vec<_>) = children
+            .into_iter()
+            .partition(|x| x.location_type() == LocationType::Pole);
+        let (entrances, children): (Vec<_>, Vec<_>) = children
+            .into_iter()
+            .partition(|x| x.location_type() == LocationType::Entrance);
+        let (generic_nodes, children): (Vec<_>, Vec<_>) = children
+            .into_iter()
+            .partition(|x| x.location_type() == LocationType::GenericNode);
+
+        // 乗車エリアの親はのりば
+        let boarding_areas = self
+            .gtfs
+            .select_stops_by_parents(platforms.iter().map(|x| x.stop_id.clone()).collect())?
+            .into_iter()
+            .chain(children)
+            .filter(|x| x.location_type() == LocationType::BoardingArea)
+            .collect_vec();
+
+        let members = vec![&station]
+            .into_iter()
+            .chain(platforms.iter())
+            .chain(boarding_areas.iter())
+            .chain(entrances.iter())
+            .chain(generic_nodes.iter())
+            .collect_vec();
+        let pathways = self
+            .gtfs
+            .select_pathways(members.iter().map(|x| x.stop_id.clone()).collect())?;
+        let levels = self.gtfs.select_levels(
+            members
+                .iter()
+                .filter_map(|x| x.level_id.clone())
+                .unique()
+                .collect(),
+        )?;
+
+        Ok(Some(StationStructure {
+            station,
+            platforms,
+            boarding_areas,
+            entrances,
+            generic_nodes,
+            levels,
+            pathways,
+        }))
     }
 }
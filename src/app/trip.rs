@@ -1,13 +1,32 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
+use chrono::NaiveDate;
+use itertools::Itertools;
+use serde::Serialize;
 
-use crate::external::gtfs::stops::StopId;
-use crate::external::gtfs::trips::Trip;
-use crate::external::gtfs::{GtfsCsvTrait, GtfsDbTrait};
+use crate::app::route::route_types_of;
+use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::routes::TransitMode;
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::trips::{Trip, TripQuery};
+use crate::external::gtfs::{GtfsCsvTrait, GtfsDbTrait, OperatorScope, Paging};
 use crate::external::gtfscsv::GtfsCsv;
 use crate::external::gtfsdb::GtfsDb;
 
 pub trait TripService {
-    fn fetch(&mut self) -> Result<Vec<Trip>>;
+    /// modeを指定した場合はその交通モードのrouteに属するtripのみ取得する
+    /// scopeを指定した場合はその事業者・営業所のtripのみ取得する
+    fn fetch(&mut self, mode: Option<&TransitMode>, scope: &OperatorScope) -> Result<Vec<Trip>>;
+}
+
+/// stop_timeを含めたtrip
+#[derive(Debug, Serialize)]
+pub struct TripWithStopTimes {
+    #[serde(flatten)]
+    pub trip: Trip,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_times: Option<Vec<StopTime>>,
 }
 
 pub struct TripServiceCsv {
@@ -21,8 +40,34 @@ impl TripServiceCsv {
 }
 
 impl TripService for TripServiceCsv {
-    fn fetch(&mut self) -> Result<Vec<Trip>> {
-        self.gtfs.load_trips()
+    fn fetch(&mut self, mode: Option<&TransitMode>, scope: &OperatorScope) -> Result<Vec<Trip>> {
+        let trips = self
+            .gtfs
+            .load_trips()?
+            .into_iter()
+            .filter(|x| {
+                scope
+                    .office_id
+                    .as_ref()
+                    .map_or(true, |o| x.jp_office_id.as_ref() == Some(o))
+            })
+            .collect_vec();
+        if mode.is_none() && scope.agency_id.is_none() {
+            return Ok(trips);
+        }
+
+        let route_ids: HashSet<_> = self
+            .gtfs
+            .load_routes()?
+            .into_iter()
+            .filter(|x| mode.map_or(true, |m| &x.mode() == m))
+            .filter(|x| scope.agency_id.as_ref().map_or(true, |a| &x.agency_id == a))
+            .map(|x| x.route_id)
+            .collect();
+        Ok(trips
+            .into_iter()
+            .filter(|x| route_ids.contains(&x.route_id))
+            .collect_vec())
     }
 }
 
@@ -35,7 +80,92 @@ impl TripServiceDb {
         Self { gtfs }
     }
 
-    pub fn fetch_trips(&mut self, stop_id: StopId) -> Result<Vec<Trip>> {
-        self.gtfs.select_trips(stop_id)
+    /// modeを指定した場合はその交通モードのrouteに属するtripのみ、
+    /// service_idやdateを指定した場合はそのservice_idで運行する・その日に運行するtripのみ取得する
+    /// with_stop_timesを指定した場合は各tripのstop_timeを含める. pagingの範囲だけを返す
+    pub fn fetch_trips(
+        &mut self,
+        mode: Option<&TransitMode>,
+        service_id: Option<ServiceId>,
+        date: Option<NaiveDate>,
+        query: TripQuery,
+        with_stop_times: bool,
+        paging: &Paging,
+    ) -> Result<Vec<TripWithStopTimes>> {
+        let query = self.resolve_query(mode, service_id, date, query)?;
+        let trips = self
+            .gtfs
+            .select_trips(&query, paging)?
+            .into_iter()
+            .map(|trip| TripWithStopTimes {
+                trip,
+                stop_times: None,
+            })
+            .collect_vec();
+        if with_stop_times {
+            self.attach_stop_times(trips)
+        } else {
+            Ok(trips)
+        }
+    }
+
+    /// 各tripのstop_timeを含める. ページに含めるtripを選んでから呼ぶこと
+    pub fn attach_stop_times(
+        &mut self,
+        trips: Vec<TripWithStopTimes>,
+    ) -> Result<Vec<TripWithStopTimes>> {
+        let mut stop_times = self
+            .gtfs
+            .select_stop_times(trips.iter().map(|x| x.trip.trip_id.clone()).collect_vec())?
+            .into_iter()
+            .into_group_map_by(|x| x.trip_id.clone());
+        Ok(trips
+            .into_iter()
+            .map(|x| TripWithStopTimes {
+                stop_times: Some(stop_times.remove(&x.trip.trip_id).unwrap_or_default()),
+                trip: x.trip,
+            })
+            .collect_vec())
+    }
+
+    /// fetch_tripsでページングしない場合の件数
+    pub fn count_trips(
+        &mut self,
+        mode: Option<&TransitMode>,
+        service_id: Option<ServiceId>,
+        date: Option<NaiveDate>,
+        query: TripQuery,
+    ) -> Result<usize> {
+        let query = self.resolve_query(mode, service_id, date, query)?;
+        Ok(self.gtfs.count_trips(&query)? as usize)
+    }
+
+    /// mode, service_id, dateをTripQueryの条件に変換する
+    fn resolve_query(
+        &mut self,
+        mode: Option<&TransitMode>,
+        service_id: Option<ServiceId>,
+        date: Option<NaiveDate>,
+        query: TripQuery,
+    ) -> Result<TripQuery> {
+        let service_ids = match date {
+            Some(d) => Some(
+                self.gtfs
+                    .select_service_ids(&d)?
+                    .into_iter()
+                    .filter(|x| service_id.as_ref().map_or(true, |s| x == s))
+                    .collect_vec(),
+            ),
+            None => service_id.map(|x| vec![x]),
+        };
+        let route_types = match mode {
+            Some(m) => Some(route_types_of(&mut self.gtfs, m)?),
+            None => None,
+        };
+        Ok(TripQuery {
+            route_types,
+            service_ids,
+            ..query
+        })
     }
 }
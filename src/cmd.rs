@@ -1,3 +1,38 @@
 pub mod db;
 pub mod get;
+pub mod openapi;
 pub mod serve;
+
+use clap::Clap;
+
+use crate::external::gtfs::OperatorScope;
+
+/// 事業者・営業所による絞り込みのオプション
+#[derive(Clap, Debug)]
+pub struct ScopeOpts {
+    /// 事業者IDで絞り込む
+    #[clap(long)]
+    agency_id: Option<String>,
+    /// 営業所IDで絞り込む
+    #[clap(long)]
+    office_id: Option<String>,
+}
+
+impl ScopeOpts {
+    pub fn to_scope(&self) -> OperatorScope {
+        OperatorScope {
+            agency_id: self.agency_id.clone(),
+            office_id: self.office_id.clone(),
+        }
+    }
+}
+
+/// `--x`と`--no-x`の組で指定する真偽値のオプションを、設定ファイルの値より優先して求める
+/// どちらも指定しない場合は設定ファイルの値、それもなければfalse
+pub fn flag(on: bool, off: bool, conf: Option<bool>) -> bool {
+    match (on, off) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => conf.unwrap_or(false),
+    }
+}
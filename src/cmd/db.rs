@@ -2,7 +2,9 @@ use anyhow::Result;
 use clap::Clap;
 
 use crate::cmd;
+use crate::config::FeedConfig;
 
+pub mod check;
 pub mod convert;
 pub mod create;
 pub mod get;
@@ -21,12 +23,15 @@ pub enum SubCommand {
     Get(cmd::db::get::Opts),
     /// データベースからデータを変換する
     Convert(cmd::db::convert::Opts),
+    /// データベースに対してファイルを検証する
+    Check(cmd::db::check::Opts),
 }
 
-pub fn run(opts: &Opts) -> Result<()> {
+pub fn run(opts: &Opts, feed: &FeedConfig) -> Result<()> {
     match &opts.subcmd {
-        SubCommand::Create(op) => cmd::db::create::run(op),
-        SubCommand::Get(op) => cmd::db::get::run(op),
-        SubCommand::Convert(op) => cmd::db::convert::run(op),
+        SubCommand::Create(op) => cmd::db::create::run(op, feed),
+        SubCommand::Get(op) => cmd::db::get::run(op, feed),
+        SubCommand::Convert(op) => cmd::db::convert::run(op, feed),
+        SubCommand::Check(op) => cmd::db::check::run(op, feed),
     }
 }
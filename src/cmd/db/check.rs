@@ -0,0 +1,27 @@
+use anyhow::Result;
+use clap::Clap;
+
+use crate::cmd;
+use crate::config::FeedConfig;
+
+pub mod service_route_identity;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(subcommand)]
+    pub subcmd: SubCommand,
+}
+
+#[derive(Clap, Debug)]
+pub enum SubCommand {
+    /// service_route識別ファイルをデータベースに対して検証する
+    ServiceRouteIdentity(cmd::db::check::service_route_identity::Opts),
+}
+
+pub fn run(opts: &Opts, feed: &FeedConfig) -> Result<()> {
+    match &opts.subcmd {
+        SubCommand::ServiceRouteIdentity(op) => {
+            cmd::db::check::service_route_identity::run(&op, feed)
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+use log::warn;
+use strum::VariantNames;
+
+use crate::app::service_route::ServiceRouteServiceDb;
+use crate::config::FeedConfig;
+use crate::external::gtfs::extended::service_routes;
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    /// 検証するservice_route識別ファイルのパス (拡張子がjson, yaml, ymlの場合はその形式、それ以外はTSVとして読みこむ)
+    #[clap(parse(from_os_str))]
+    identity: PathBuf,
+    /// 読み込むデータベースファイルのパス (省略時は設定ファイルのfeedのdatabase、なければgtfs.db)
+    #[clap(short, long, parse(from_os_str))]
+    database: Option<PathBuf>,
+    /// service_routeの一意性戦略 (省略時は設定ファイルのfeedのcreate、なければstop_names)
+    #[clap(
+        short = 'S',
+        long,
+        possible_values(service_routes::IdentifyStrategy::VARIANTS)
+    )]
+    service_route_identify_strategy: Option<service_routes::IdentifyStrategy>,
+    /// 割当の変化の出力フォーマット
+    #[clap(short, long, default_value = "tsv", possible_values(Format::VARIANTS))]
+    format: Format,
+}
+
+pub fn run(op: &Opts, feed: &FeedConfig) -> Result<()> {
+    let identities = io::read(&op.identity, &Format::from_path(&op.identity))?;
+    let gtfs = external::gtfsdb::GtfsDb::new(&feed.database_or(op.database.as_ref()))?;
+    let report = ServiceRouteServiceDb::new(gtfs).check_service_route_identity(
+        &identities,
+        op.service_route_identify_strategy
+            .as_ref()
+            .or_else(|| feed.create.service_route_identify_strategy.as_ref())
+            .unwrap_or(&service_routes::IdentifyStrategy::StopNames),
+    )?;
+
+    io::write(&report.diffs, &op.format)?;
+    if !report.issues.is_empty() {
+        for issue in &report.issues {
+            warn!(
+                "⚠️ [{}] {}^{} {}{}",
+                issue.kind,
+                issue.service_route_id,
+                issue.service_route_direction_id.clone() as u8,
+                issue
+                    .trip_id
+                    .as_ref()
+                    .map(|x| format!("trip_id={}: ", x))
+                    .unwrap_or_default(),
+                issue.message
+            );
+        }
+        bail!(
+            "service_route識別ファイルに{}件の問題があります",
+            report.issues.len()
+        );
+    }
+    Ok(())
+}
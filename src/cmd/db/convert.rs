@@ -2,6 +2,7 @@ use anyhow::Result;
 use clap::Clap;
 
 use crate::cmd;
+use crate::config::FeedConfig;
 
 pub mod service_route_identity;
 
@@ -17,8 +18,10 @@ pub enum SubCommand {
     ServiceRouteIdentity(cmd::db::convert::service_route_identity::Opts),
 }
 
-pub fn run(opts: &Opts) -> Result<()> {
+pub fn run(opts: &Opts, feed: &FeedConfig) -> Result<()> {
     match &opts.subcmd {
-        SubCommand::ServiceRouteIdentity(op) => cmd::db::convert::service_route_identity::run(&op),
+        SubCommand::ServiceRouteIdentity(op) => {
+            cmd::db::convert::service_route_identity::run(&op, feed)
+        }
     }
 }
@@ -5,21 +5,22 @@ use clap::Clap;
 use strum::VariantNames;
 
 use crate::app::service_route::ServiceRouteServiceDb;
+use crate::config::FeedConfig;
 use crate::io::Format;
 use crate::{external, io};
 
 #[derive(Clap, Debug)]
 pub struct Opts {
-    /// 読み込むデータベースファイルのパス
-    #[clap(short, long, parse(from_os_str), default_value = "gtfs.db")]
-    database: PathBuf,
+    /// 読み込むデータベースファイルのパス (省略時は設定ファイルのfeedのdatabase、なければgtfs.db)
+    #[clap(short, long, parse(from_os_str))]
+    database: Option<PathBuf>,
     /// 出力フォーマット
     #[clap(short, long, default_value = "tsv", possible_values(Format::VARIANTS))]
     format: Format,
 }
 
-pub fn run(op: &Opts) -> Result<()> {
-    let gtfs = external::gtfsdb::GtfsDb::new(&op.database)?;
+pub fn run(op: &Opts, feed: &FeedConfig) -> Result<()> {
+    let gtfs = external::gtfsdb::GtfsDb::new(&feed.database_or(op.database.as_ref()))?;
     let identity = ServiceRouteServiceDb::new(gtfs).fetch_service_route_identity()?;
     io::write(&identity, &op.format)?;
     Ok(())
@@ -5,48 +5,122 @@ use clap::Clap;
 use strum::VariantNames;
 
 use crate::app::gtfs::GtfsService;
-use crate::external;
-use crate::external::gtfs::extended::service_routes;
+use crate::config::FeedConfig;
+use crate::external::gtfs::extended::{nodes, service_routes};
+use crate::{cmd, external};
 
-#[derive(Clap, Debug)]
+/// service_routeの一意性戦略を指定しない場合の戦略
+const DEFAULT_SERVICE_ROUTE_IDENTIFY_STRATEGY: service_routes::IdentifyStrategy =
+    service_routes::IdentifyStrategy::StopNames;
+/// nodeの生成戦略を指定しない場合の戦略
+const DEFAULT_NODE_STRATEGY: nodes::NodeStrategy = nodes::NodeStrategy::ParentStation;
+/// 歩行速度を指定しない場合の速度 (メートル毎秒)
+const DEFAULT_WALKING_SPEED: f64 = 1.3;
+/// same_name戦略の距離を指定しない場合の距離 (メートル)
+const DEFAULT_NODE_DISTANCE: f64 = 300.0;
+
+/// 省略したオプションは設定ファイルのfeedのcreateから補い、それもなければ既定値とする
+#[derive(Clap, Debug, Default)]
 pub struct Opts {
-    /// 読みこむGTFSが配置されたディレクトリのパス
+    /// 読みこむGTFSが配置されたディレクトリのパス (省略時は設定ファイルのfeedのsource)
     #[clap(parse(from_os_str))]
-    pub gtfs_dir: PathBuf,
-    /// 作成するデータベースファイルのパス
-    #[clap(short, long, parse(from_os_str), default_value = "gtfs.db")]
-    pub database: PathBuf,
-    /// translationsの古い定義を使うかどうか
-    #[clap(short, long)]
-    pub legacy_translations: bool,
-    /// service_routeの一意性戦略
+    pub gtfs_dir: Option<PathBuf>,
+    /// 作成するデータベースファイルのパス (省略時は設定ファイルのfeedのdatabase、なければgtfs.db)
+    #[clap(short, long, parse(from_os_str))]
+    pub database: Option<PathBuf>,
+    /// service_routeの一意性戦略 (既定: stop_names)
     #[clap(
         short = 'S',
         long,
-        default_value = "stop_names",
         possible_values(service_routes::IdentifyStrategy::VARIANTS)
     )]
-    pub service_route_identify_strategy: service_routes::IdentifyStrategy,
+    pub service_route_identify_strategy: Option<service_routes::IdentifyStrategy>,
     /// service_route識別ファイルのパス
     #[clap(short = 's', long, parse(from_os_str))]
     pub service_route_identify: Option<PathBuf>,
+    /// GTFSの仕様外のカラムとファイルを保持するかどうか
+    #[clap(short = 'x', long)]
+    pub preserve_extras: bool,
+    /// 設定ファイルのpreserve_extrasを無効にする
+    #[clap(long, conflicts_with = "preserve-extras")]
+    pub no_preserve_extras: bool,
+    /// service_routeを作成できないtripを除外して作成を続けるかどうか (除外したtripはservice_route_problemsテーブルに記録する)
+    #[clap(long)]
+    pub lenient: bool,
+    /// 設定ファイルのlenientを無効にする
+    #[clap(long, conflicts_with = "lenient")]
+    pub no_lenient: bool,
+    /// 指定した距離 (メートル) 以内にある標柱間の徒歩乗換をtransfersに生成する
+    #[clap(long)]
+    pub walking_transfer_distance: Option<f64>,
+    /// 徒歩乗換の乗換時間を求める歩行速度 (メートル毎秒) (既定: 1.3)
+    #[clap(long)]
+    pub walking_speed: Option<f64>,
+    /// nodeの生成戦略 (既定: parent_station)
+    #[clap(short = 'N', long, possible_values(nodes::NodeStrategy::VARIANTS))]
+    pub node_strategy: Option<nodes::NodeStrategy>,
+    /// same_name戦略で同名のstopを1つのnodeにまとめる距離 (メートル) (既定: 300)
+    #[clap(long)]
+    pub node_distance: Option<f64>,
+    /// mapping戦略で使うnode対応ファイル (stop_id, node_key) のパス
+    #[clap(long, parse(from_os_str))]
+    pub node_mapping: Option<PathBuf>,
 }
 
-pub fn run(op: &Opts) -> Result<()> {
-    let gtfs_csv = external::gtfscsv::GtfsCsv::new(&op.gtfs_dir)?;
-    let gtfs_db = external::gtfsdb::init(&op.database)?;
+pub fn run(op: &Opts, feed: &FeedConfig) -> Result<()> {
+    let conf = &feed.create;
+    let gtfs_csv = external::gtfscsv::GtfsCsv::new(&feed.source_or(op.gtfs_dir.as_ref())?)?;
+    let gtfs_db = external::gtfsdb::init(&feed.database_or(op.database.as_ref()))?;
 
     let mut service = GtfsService::new(gtfs_csv, gtfs_db);
+    let lenient = cmd::flag(op.lenient, op.no_lenient, conf.lenient);
 
     service.drop_tables()?;
     service.create_tables()?;
-    service.insert_tables(op.legacy_translations)?;
+    service.insert_tables(lenient)?;
+    service.insert_spatial_index()?;
+    if let Some(distance) = op
+        .walking_transfer_distance
+        .or(conf.walking_transfer_distance)
+    {
+        let speed = op
+            .walking_speed
+            .or(conf.walking_speed)
+            .unwrap_or(DEFAULT_WALKING_SPEED);
+        service.insert_walking_transfers(distance, speed)?;
+    }
+    if cmd::flag(
+        op.preserve_extras,
+        op.no_preserve_extras,
+        conf.preserve_extras,
+    ) {
+        service.insert_extras()?;
+    }
+
+    // ノードの並びでサービスルートを識別できるよう、ノードを先に作成する
+    service.insert_nodes_tables(
+        op.node_strategy
+            .as_ref()
+            .or_else(|| conf.node_strategy.as_ref())
+            .unwrap_or(&DEFAULT_NODE_STRATEGY),
+        op.node_distance
+            .or(conf.node_distance)
+            .unwrap_or(DEFAULT_NODE_DISTANCE),
+        op.node_mapping
+            .as_ref()
+            .or_else(|| conf.node_mapping.as_ref()),
+    )?;
 
     service.insert_service_routes_tables(
-        &op.service_route_identify_strategy,
-        op.service_route_identify.as_ref(),
+        op.service_route_identify_strategy
+            .as_ref()
+            .or_else(|| conf.service_route_identify_strategy.as_ref())
+            .unwrap_or(&DEFAULT_SERVICE_ROUTE_IDENTIFY_STRATEGY),
+        op.service_route_identify
+            .as_ref()
+            .or_else(|| conf.service_route_identify.as_ref()),
+        lenient,
     )?;
-    service.insert_nodes_tables()?;
 
     Ok(())
 }
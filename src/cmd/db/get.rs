@@ -2,8 +2,12 @@ use anyhow::Result;
 use clap::Clap;
 
 use crate::cmd;
+use crate::config::FeedConfig;
 
+pub mod blocks;
 pub mod routes;
+pub mod service_route_merges;
+pub mod stops;
 
 #[derive(Clap, Debug)]
 pub struct Opts {
@@ -13,12 +17,21 @@ pub struct Opts {
 
 #[derive(Clap, Debug)]
 pub enum SubCommand {
-    /// データベースからrouteを取得する
+    /// データベースから運用(block)を取得する
+    Blocks(cmd::db::get::blocks::Opts),
+    /// データベースからrouteと、事業者名・routes_jpの情報・停車するstopなどの数を取得する
     Routes(cmd::db::get::routes::Opts),
+    /// データベースから異なる停車パターンをまとめたservice_routeのtripを取得する
+    ServiceRouteMerges(cmd::db::get::service_route_merges::Opts),
+    /// データベースからstopを取得する
+    Stops(cmd::db::get::stops::Opts),
 }
 
-pub fn run(opts: &Opts) -> Result<()> {
+pub fn run(opts: &Opts, feed: &FeedConfig) -> Result<()> {
     match &opts.subcmd {
-        SubCommand::Routes(op) => cmd::db::get::routes::run(&op),
+        SubCommand::Blocks(op) => cmd::db::get::blocks::run(&op, feed),
+        SubCommand::Routes(op) => cmd::db::get::routes::run(&op, feed),
+        SubCommand::ServiceRouteMerges(op) => cmd::db::get::service_route_merges::run(&op, feed),
+        SubCommand::Stops(op) => cmd::db::get::stops::run(&op, feed),
     }
 }
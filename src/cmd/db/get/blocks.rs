@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use clap::Clap;
+use itertools::Itertools;
+use strum::VariantNames;
+
+use crate::app::block::BlockServiceDb;
+use crate::config::FeedConfig;
+use crate::io::Format;
+use crate::serde_chrono_custom::yyyymmdd;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    /// 読み込むデータベースファイルのパス (省略時は設定ファイルのfeedのdatabase、なければgtfs.db)
+    #[clap(short, long, parse(from_os_str))]
+    database: Option<PathBuf>,
+    /// 出力フォーマット
+    #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
+    format: Format,
+    /// 運行日 (ex: 20210510)
+    #[clap(long, parse(try_from_str = yyyymmdd::parse))]
+    date: NaiveDate,
+    /// 運用IDで絞り込む
+    #[clap(short, long)]
+    block_id: Option<String>,
+    /// 便の代わりに運行日ごとの集計を出力する
+    #[clap(short, long)]
+    summary: bool,
+    /// 集計する日数 (--summary指定時のみ)
+    #[clap(long, default_value = "1")]
+    days: u32,
+}
+
+pub fn run(op: &Opts, feed: &FeedConfig) -> Result<()> {
+    let gtfs = external::gtfsdb::GtfsDb::new(&feed.database_or(op.database.as_ref()))?;
+    let mut service = BlockServiceDb::new(gtfs);
+    if op.summary {
+        let summaries = service.fetch_summaries(op.date, op.days)?;
+        io::write(&summaries, &op.format)?;
+    } else {
+        let trips = service
+            .fetch_blocks(op.date, op.block_id.clone())?
+            .into_iter()
+            .flat_map(|x| x.trips)
+            .collect_vec();
+        io::write(&trips, &op.format)?;
+    }
+    Ok(())
+}
@@ -4,23 +4,40 @@ use anyhow::Result;
 use clap::Clap;
 use strum::VariantNames;
 
-use crate::app::route::{RouteService, RouteServiceDb};
+use crate::app::route::RouteServiceDb;
+use crate::cmd::ScopeOpts;
+use crate::config::FeedConfig;
+use crate::external::gtfs::routes::TransitMode;
+use crate::external::gtfs::Paging;
 use crate::io::Format;
 use crate::{external, io};
 
 #[derive(Clap, Debug)]
 pub struct Opts {
-    /// 読み込むデータベースファイルのパス
-    #[clap(short, long, parse(from_os_str), default_value = "gtfs.db")]
-    database: PathBuf,
+    /// 読み込むデータベースファイルのパス (省略時は設定ファイルのfeedのdatabase、なければgtfs.db)
+    #[clap(short, long, parse(from_os_str))]
+    database: Option<PathBuf>,
     /// 出力フォーマット
     #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
     format: Format,
+    /// route_short_nameまたはroute_long_nameで部分一致検索する文字列
+    #[clap(short, long)]
+    word: Option<String>,
+    /// 交通モードで絞り込む
+    #[clap(short, long, possible_values(TransitMode::VARIANTS))]
+    mode: Option<TransitMode>,
+    #[clap(flatten)]
+    scope: ScopeOpts,
 }
 
-pub fn run(op: &Opts) -> Result<()> {
-    let gtfs = external::gtfsdb::GtfsDb::new(&op.database)?;
-    let routes = RouteServiceDb::new(gtfs).fetch()?;
+pub fn run(op: &Opts, feed: &FeedConfig) -> Result<()> {
+    let gtfs = external::gtfsdb::GtfsDb::new(&feed.database_or(op.database.as_ref()))?;
+    let routes = RouteServiceDb::new(gtfs).fetch_route_details(
+        op.word.clone(),
+        op.mode.as_ref(),
+        &op.scope.to_scope(),
+        &Paging::default(),
+    )?;
     io::write(&routes, &op.format)?;
     Ok(())
 }
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+use strum::VariantNames;
+
+use crate::config::FeedConfig;
+use crate::external::gtfs::extended::service_route_merges::ServiceRouteMerge;
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    /// 読み込むデータベースファイルのパス (省略時は設定ファイルのfeedのdatabase、なければgtfs.db)
+    #[clap(short, long, parse(from_os_str))]
+    database: Option<PathBuf>,
+    /// 出力フォーマット
+    #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
+    format: Format,
+}
+
+pub fn run(op: &Opts, feed: &FeedConfig) -> Result<()> {
+    let mut gtfs = external::gtfsdb::GtfsDb::new(&feed.database_or(op.database.as_ref()))?;
+    let merges = gtfs.select_all::<ServiceRouteMerge>()?;
+    io::write(&merges, &op.format)?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::Clap;
+use strum::VariantNames;
+
+use crate::app::stops::StopServiceDb;
+use crate::config::FeedConfig;
+use crate::external::gtfs::Paging;
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    /// 読み込むデータベースファイルのパス (省略時は設定ファイルのfeedのdatabase、なければgtfs.db)
+    #[clap(short, long, parse(from_os_str))]
+    database: Option<PathBuf>,
+    /// 出力フォーマット
+    #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
+    format: Format,
+    /// stop_nameで部分一致検索する文字列
+    #[clap(short, long)]
+    word: Option<String>,
+    /// 指定した座標 (緯度,経度) から近い順に取得する (ex: 35.681,139.767)
+    #[clap(long, parse(try_from_str = parse_coordinate))]
+    near: Option<(f64, f64)>,
+    /// --nearの検索半径 (メートル)
+    #[clap(long, default_value = "500")]
+    radius: f64,
+    /// 取得する最大件数
+    #[clap(long)]
+    limit: Option<u32>,
+}
+
+fn parse_coordinate(s: &str) -> Result<(f64, f64)> {
+    let (lat, lon) = s
+        .split_once(',')
+        .context("緯度,経度の形式で指定してください")?;
+    Ok((lat.trim().parse()?, lon.trim().parse()?))
+}
+
+pub fn run(op: &Opts, feed: &FeedConfig) -> Result<()> {
+    let gtfs = external::gtfsdb::GtfsDb::new(&feed.database_or(op.database.as_ref()))?;
+    let mut service = StopServiceDb::new(gtfs);
+    let paging = Paging {
+        limit: op.limit,
+        ..Default::default()
+    };
+    match op.near {
+        Some((lat, lon)) => {
+            let stops = service.fetch_nearby_stops(lat, lon, op.radius, &paging)?;
+            io::write(&stops, &op.format)?;
+        }
+        None => {
+            let stops = service.fetch_stops(op.word.clone().unwrap_or_default(), &paging)?;
+            io::write(&stops, &op.format)?;
+        }
+    }
+    Ok(())
+}
@@ -2,6 +2,7 @@ use anyhow::Result;
 use clap::Clap;
 
 use crate::cmd;
+use crate::config::FeedConfig;
 
 pub mod routes;
 pub mod trips;
@@ -20,9 +21,9 @@ pub enum SubCommand {
     Trips(cmd::get::trips::Opts),
 }
 
-pub fn run(opts: &Opts) -> Result<()> {
+pub fn run(opts: &Opts, feed: &FeedConfig) -> Result<()> {
     match &opts.subcmd {
-        SubCommand::Trips(op) => cmd::get::trips::run(&op),
-        SubCommand::Routes(op) => cmd::get::routes::run(&op),
+        SubCommand::Trips(op) => cmd::get::trips::run(&op, feed),
+        SubCommand::Routes(op) => cmd::get::routes::run(&op, feed),
     }
 }
@@ -5,22 +5,30 @@ use clap::Clap;
 use strum::VariantNames;
 
 use crate::app::trip::{TripService, TripServiceCsv};
+use crate::cmd::ScopeOpts;
+use crate::config::FeedConfig;
+use crate::external::gtfs::routes::TransitMode;
 use crate::io::Format;
 use crate::{external, io};
 
 #[derive(Clap, Debug)]
 pub struct Opts {
-    /// 読みこむGTFSが配置されたディレクトリのパス
+    /// 読みこむGTFSが配置されたディレクトリのパス (省略時は設定ファイルのfeedのsource)
     #[clap(parse(from_os_str))]
-    gtfs_dir: PathBuf,
+    gtfs_dir: Option<PathBuf>,
     /// 出力フォーマット
     #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
     format: Format,
+    /// 交通モードで絞り込む
+    #[clap(short, long, possible_values(TransitMode::VARIANTS))]
+    mode: Option<TransitMode>,
+    #[clap(flatten)]
+    scope: ScopeOpts,
 }
 
-pub fn run(op: &Opts) -> Result<()> {
-    let gtfs = external::gtfscsv::GtfsCsv::new(&op.gtfs_dir)?;
-    let trips = TripServiceCsv::new(gtfs).fetch()?;
+pub fn run(op: &Opts, feed: &FeedConfig) -> Result<()> {
+    let gtfs = external::gtfscsv::GtfsCsv::new(&feed.source_or(op.gtfs_dir.as_ref())?)?;
+    let trips = TripServiceCsv::new(gtfs).fetch(op.mode.as_ref(), &op.scope.to_scope())?;
     io::write(&trips, &op.format)?;
     Ok(())
 }
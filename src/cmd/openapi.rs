@@ -0,0 +1,23 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::api;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    /// 出力するファイルのパス (省略時は標準出力)
+    #[clap(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let spec = serde_json::to_string_pretty(&api::openapi::spec())?;
+    match &op.output {
+        Some(path) => fs::write(path, spec)?,
+        None => println!("{}", spec),
+    }
+    Ok(())
+}
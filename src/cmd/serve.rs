@@ -1,10 +1,19 @@
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
 use clap::Clap;
+use log::{info, warn};
 use rocket::config::{Config, Environment};
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::http::Header;
 use rocket::{Request, Response};
 
 use crate::api;
+use crate::api::utils::feeds::Feeds;
+use crate::app::graphql::GraphqlLimits;
+use crate::{cmd, config};
 
 pub struct CORS;
 
@@ -29,34 +38,162 @@ impl Fairing for CORS {
 
 #[derive(Clap, Debug)]
 pub struct Opts {
-    /// ポート番号
-    #[clap(short, long, default_value = "8000")]
-    port: u16,
+    /// ポート番号 (省略時は設定ファイルのserver、なければ8000)
+    #[clap(short, long)]
+    port: Option<u16>,
     /// CORSを許可するか
     #[clap(long)]
     cors: bool,
+    /// 設定ファイルのcorsを無効にする
+    #[clap(long, conflicts_with = "cors")]
+    no_cors: bool,
+    /// `<db_root>/<key>/gtfs.db`を`<key>`のデータベースとする (省略時は設定ファイルのserver、なければdb)
+    #[clap(long, parse(from_os_str))]
+    db_root: Option<PathBuf>,
+    /// keyの別名 (ex: latest=20210510). 複数指定可
+    #[clap(long, parse(try_from_str = parse_alias), multiple_occurrences = true)]
+    alias: Vec<(String, String)>,
+    /// データベースの差し替えを確認する間隔 (秒). 0の場合は確認しない (省略時は設定ファイルのserver、なければ10)
+    #[clap(long)]
+    watch_interval: Option<u64>,
+    /// `/<key>/graphql`でGraphQLのクエリを受けつけるか
+    #[clap(long)]
+    graphql: bool,
+    /// 設定ファイルのgraphqlを無効にする
+    #[clap(long, conflicts_with = "graphql")]
+    no_graphql: bool,
+    /// GraphQLのクエリの深さの上限 (省略時は設定ファイルのserver、なければ6)
+    #[clap(long)]
+    graphql_max_depth: Option<usize>,
+    /// GraphQLのクエリのフィールド数の上限 (省略時は設定ファイルのserver、なければ200)
+    #[clap(long)]
+    graphql_max_complexity: Option<usize>,
+    /// GraphQLのリストのフィールドのlimitの上限 (省略時は設定ファイルのserver、なければ1000)
+    #[clap(long)]
+    graphql_max_limit: Option<u32>,
+}
+
+/// データベースの差し替えを確認する間隔を指定しない場合の間隔 (秒)
+const DEFAULT_WATCH_INTERVAL: u64 = 10;
+/// GraphQLのクエリの深さの上限を指定しない場合の上限
+const DEFAULT_GRAPHQL_MAX_DEPTH: usize = 6;
+/// GraphQLのクエリのフィールド数の上限を指定しない場合の上限
+const DEFAULT_GRAPHQL_MAX_COMPLEXITY: usize = 200;
+/// GraphQLのリストのフィールドのlimitの上限を指定しない場合の上限
+const DEFAULT_GRAPHQL_MAX_LIMIT: u32 = 1000;
+/// GraphQLのリストのフィールドでlimitを省略した場合の件数
+const DEFAULT_GRAPHQL_LIMIT: u32 = 100;
+
+fn parse_alias(s: &str) -> Result<(String, String)> {
+    let (alias, key) = s
+        .split_once('=')
+        .context("別名=keyの形式で指定してください")?;
+    Ok((alias.to_string(), key.to_string()))
+}
+
+/// `/`にマウントするAPIのルート (GraphQLを除く)
+pub fn api_routes() -> Vec<rocket::Route> {
+    routes![
+        api::agencies::index,
+        api::blocks::index,
+        api::fares::index,
+        api::feeds::index,
+        api::nodes::index,
+        api::nodes::detail,
+        api::nodes::departures,
+        api::offices::index,
+        api::openapi::index,
+        api::routes::index,
+        api::routes::detail,
+        api::service_routes::index,
+        api::service_routes::detail,
+        api::stations::index,
+        api::stop_time_details::index,
+        api::stops::index,
+        api::stops::nearby,
+        api::trips::index
+    ]
 }
 
-pub fn run(opts: &Opts) {
+/// すべてのAPIをmountする. graphqlを有効にする場合はGraphqlLimitsをmanageしておくこと
+pub fn mount(app: rocket::Rocket, graphql: bool) -> rocket::Rocket {
+    let app = app
+        .mount("/config", routes![api::config::index])
+        .mount("/", api_routes());
+    if graphql {
+        app.mount("/", routes![api::graphql::index])
+    } else {
+        app
+    }
+}
+
+/// 設定ファイルのfeedのうちdatabaseを指定したものは、feed名をkeyとしてそのデータベースを使う
+pub fn run(opts: &Opts, conf: &config::Config) -> Result<()> {
+    let server = &conf.server;
+    let paths = conf
+        .feeds
+        .iter()
+        .filter_map(|(name, feed)| feed.database.clone().map(|x| (name.clone(), x)))
+        .collect();
+    let mut aliases = server.aliases.clone();
+    aliases.extend(opts.alias.iter().cloned());
+    let feeds = Feeds::new(
+        opts.db_root
+            .clone()
+            .or_else(|| server.db_root.clone())
+            .unwrap_or_else(|| PathBuf::from("db")),
+        paths,
+        aliases,
+    );
+    feeds.load()?;
+    for key in feeds.keys()? {
+        info!("ℹ️ Serve {} from {:?}", key, feeds.path_of(&key));
+    }
+
+    let interval = opts
+        .watch_interval
+        .or(server.watch_interval)
+        .unwrap_or(DEFAULT_WATCH_INTERVAL);
+    if interval > 0 {
+        let watched = feeds.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(interval));
+            if let Err(e) = watched.reload() {
+                warn!("⚠️ Fail to reload databases: {:#}", e);
+            }
+        });
+    }
+
     let config = Config::build(Environment::active().unwrap_or(Environment::Development))
-        .port(opts.port)
+        .port(opts.port.or(server.port).unwrap_or(8000))
         .finalize()
         .unwrap();
 
-    let mut app = rocket::custom(config)
-        .mount("/config", routes![api::config::index])
-        .mount(
-            "/",
-            routes![
-                api::stop_time_details::index,
-                api::stops::index,
-                api::trips::index
-            ],
-        );
-
-    if opts.cors {
+    let graphql = cmd::flag(opts.graphql, opts.no_graphql, server.graphql);
+    let mut app = rocket::custom(config).manage(feeds);
+    if graphql {
+        let max_limit = opts
+            .graphql_max_limit
+            .or(server.graphql_max_limit)
+            .unwrap_or(DEFAULT_GRAPHQL_MAX_LIMIT);
+        app = app.manage(GraphqlLimits {
+            max_depth: opts
+                .graphql_max_depth
+                .or(server.graphql_max_depth)
+                .unwrap_or(DEFAULT_GRAPHQL_MAX_DEPTH),
+            max_complexity: opts
+                .graphql_max_complexity
+                .or(server.graphql_max_complexity)
+                .unwrap_or(DEFAULT_GRAPHQL_MAX_COMPLEXITY),
+            default_limit: DEFAULT_GRAPHQL_LIMIT.min(max_limit),
+            max_limit,
+        });
+    }
+    let mut app = mount(app, graphql);
+
+    if cmd::flag(opts.cors, opts.no_cors, server.cors) {
         app = app.attach(CORS);
     }
 
-    app.launch();
+    Err(app.launch().into())
 }
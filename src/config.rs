@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::extended::nodes::NodeStrategy;
+use crate::external::gtfs::extended::service_routes::IdentifyStrategy;
+
+/// --configを指定しない場合に読みこむ設定ファイル (存在しなければ読みこまない)
+pub const DEFAULT_CONFIG_PATH: &str = ".diamant.yaml";
+
+/// 設定ファイル
+/// CLIのオプションを指定した場合はそちらを優先する
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// serveコマンドの設定
+    #[serde(default)]
+    pub server: ServerConfig,
+    /// feed名ごとの設定
+    #[serde(default)]
+    pub feeds: BTreeMap<String, FeedConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    /// ポート番号
+    pub port: Option<u16>,
+    /// CORSを許可するか
+    pub cors: Option<bool>,
+    /// `<db_root>/<key>/gtfs.db`を`<key>`のデータベースとする
+    pub db_root: Option<PathBuf>,
+    /// データベースの差し替えを確認する間隔 (秒). 0の場合は確認しない
+    pub watch_interval: Option<u64>,
+    /// GraphQLのエンドポイントを有効にするか
+    pub graphql: Option<bool>,
+    /// GraphQLのクエリの深さの上限
+    pub graphql_max_depth: Option<usize>,
+    /// GraphQLのクエリのフィールド数の上限
+    pub graphql_max_complexity: Option<usize>,
+    /// GraphQLのリストのフィールドのlimitの上限
+    pub graphql_max_limit: Option<u32>,
+    /// keyの別名 (別名 => key)
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FeedConfig {
+    /// GTFSが配置されたディレクトリのパス
+    pub source: Option<PathBuf>,
+    /// データベースファイルのパス
+    pub database: Option<PathBuf>,
+    /// db createコマンドの設定
+    #[serde(default)]
+    pub create: CreateConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CreateConfig {
+    pub service_route_identify_strategy: Option<IdentifyStrategy>,
+    pub service_route_identify: Option<PathBuf>,
+    pub preserve_extras: Option<bool>,
+    pub lenient: Option<bool>,
+    pub walking_transfer_distance: Option<f64>,
+    pub walking_speed: Option<f64>,
+    pub node_strategy: Option<NodeStrategy>,
+    pub node_distance: Option<f64>,
+    pub node_mapping: Option<PathBuf>,
+}
+
+impl Config {
+    /// pathを指定しない場合、DEFAULT_CONFIG_PATHがあれば読みこみ、なければ空の設定とする
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let path = match path {
+            Some(p) => p,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Path::new(DEFAULT_CONFIG_PATH),
+            None => return Ok(Config::default()),
+        };
+        let file = File::open(path)
+            .with_context(|| format!("{:?} が読み込めませんでした", &path.to_str()))?;
+        let mut config: Config = serde_yaml::from_reader(file)
+            .with_context(|| format!("{:?} のパースに問題が発生しました", &path.to_str()))?;
+        config.resolve_paths(path.parent().unwrap_or_else(|| Path::new("")));
+        Ok(config)
+    }
+
+    /// 設定ファイルに書いた相対パスを、カレントディレクトリではなく設定ファイルのディレクトリからのパスにする
+    fn resolve_paths(&mut self, base: &Path) {
+        let resolve = |path: &mut Option<PathBuf>| {
+            if let Some(p) = path {
+                *p = base.join(&p);
+            }
+        };
+        resolve(&mut self.server.db_root);
+        for feed in self.feeds.values_mut() {
+            resolve(&mut feed.source);
+            resolve(&mut feed.database);
+            resolve(&mut feed.create.service_route_identify);
+            resolve(&mut feed.create.node_mapping);
+        }
+    }
+
+    /// feed名の設定を取得する. feed名を指定しない場合は空の設定
+    pub fn feed(&self, name: Option<&str>) -> Result<FeedConfig> {
+        match name {
+            Some(n) => match self.feeds.get(n) {
+                Some(feed) => Ok(feed.clone()),
+                None => bail!("設定ファイルにfeed {} がありません", n),
+            },
+            None => Ok(FeedConfig::default()),
+        }
+    }
+}
+
+impl FeedConfig {
+    /// CLIで指定したパス、feedのdatabase、gtfs.dbの順に優先してデータベースファイルのパスを求める
+    pub fn database_or(&self, cli: Option<&PathBuf>) -> PathBuf {
+        cli.or_else(|| self.database.as_ref())
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from("gtfs.db"))
+    }
+
+    /// CLIで指定したパス、feedのsourceの順に優先してGTFSのディレクトリを求める
+    pub fn source_or(&self, cli: Option<&PathBuf>) -> Result<PathBuf> {
+        cli.or_else(|| self.source.as_ref())
+            .cloned()
+            .context("GTFSのディレクトリを指定するか、設定ファイルのfeedにsourceを記載してください")
+    }
+}
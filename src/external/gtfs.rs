@@ -1,49 +1,94 @@
-use std::path::PathBuf;
+use std::fmt;
+use std::ops::Add;
+use std::path::Path;
+use std::str::FromStr;
 
 use anyhow::Result;
+use chrono::NaiveDate;
+use itertools::Itertools;
 use ordered_float::OrderedFloat;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
-use crate::external::gtfs::agency::Agency;
+use crate::external::gtfs::agency::{Agency, AgencyId};
 use crate::external::gtfs::agency_jp::AgencyJp;
-use crate::external::gtfs::calendar::Calendar;
+use crate::external::gtfs::areas::Area;
+use crate::external::gtfs::attributions::Attribution;
+use crate::external::gtfs::booking_rules::BookingRule;
+use crate::external::gtfs::calendar::{Calendar, ServiceId};
 use crate::external::gtfs::calendar_dates::CalendarDate;
-use crate::external::gtfs::extended::nodes::Node;
+use crate::external::gtfs::extended::agency_details::AgencyDetail;
+use crate::external::gtfs::extended::block_trips::BlockTrip;
+use crate::external::gtfs::extended::extras::{ExtraColumns, ExtraRecord};
+use crate::external::gtfs::extended::leg_fares::LegFare;
+use crate::external::gtfs::extended::nearby_stops::NearbyStop;
+use crate::external::gtfs::extended::node_details::NodeDeparture;
+use crate::external::gtfs::extended::nodes::{Node, NodeId, NodeMapping, Stop2Node};
+use crate::external::gtfs::extended::office_details::OfficeDetail;
+use crate::external::gtfs::extended::records::{Record, RecordQuery};
+use crate::external::gtfs::extended::route_details::RouteDetail;
+use crate::external::gtfs::extended::service_route_details::{
+    ServiceRouteSummary, ServiceRouteTrip,
+};
 use crate::external::gtfs::extended::service_route_identity::ServiceRouteIdentity;
-use crate::external::gtfs::extended::service_routes::ServiceRoute;
+use crate::external::gtfs::extended::service_route_merges::ServiceRouteMerge;
+use crate::external::gtfs::extended::service_route_problems::ServiceRouteProblem;
+use crate::external::gtfs::extended::service_routes::{ServiceRoute, ServiceRouteId};
 use crate::external::gtfs::extended::stop_details::StopDetail;
 use crate::external::gtfs::extended::stop_time_details::StopTimeDetail;
 use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
+use crate::external::gtfs::extended::walking_transfers::WalkingTransfer;
 use crate::external::gtfs::fare_attributes::FareAttribute;
+use crate::external::gtfs::fare_leg_rules::FareLegRule;
+use crate::external::gtfs::fare_media::FareMedia;
+use crate::external::gtfs::fare_products::FareProduct;
 use crate::external::gtfs::fare_rules::FareRule;
+use crate::external::gtfs::fare_transfer_rules::FareTransferRule;
 use crate::external::gtfs::feed_info::Feed;
 use crate::external::gtfs::frequencies::Frequency;
 use crate::external::gtfs::legacy_translations::LegacyTranslation;
-use crate::external::gtfs::office_jp::OfficeJp;
-use crate::external::gtfs::routes::Route;
+use crate::external::gtfs::levels::{Level, LevelId};
+use crate::external::gtfs::networks::Network;
+use crate::external::gtfs::office_jp::{JpOfficeId, OfficeJp};
+use crate::external::gtfs::pathways::Pathway;
+use crate::external::gtfs::route_networks::RouteNetwork;
+use crate::external::gtfs::routes::{Route, RouteId, RouteType};
 use crate::external::gtfs::routes_jp::RouteJp;
 use crate::external::gtfs::shapes::Shape;
+use crate::external::gtfs::stop_areas::StopArea;
 use crate::external::gtfs::stop_times::StopTime;
 use crate::external::gtfs::stops::{Stop, StopId};
 use crate::external::gtfs::transfers::Transfer;
-use crate::external::gtfs::translations::Translation;
-use crate::external::gtfs::trips::{Trip, TripId};
+use crate::external::gtfs::translations::{TranslatableTableName, Translation};
+use crate::external::gtfs::trips::{Trip, TripId, TripQuery};
+use crate::io::RawRecord;
 
 pub mod agency;
 pub mod agency_jp;
+pub mod areas;
+pub mod attributions;
+pub mod booking_rules;
 pub mod calendar;
 pub mod calendar_dates;
 pub mod extended;
 pub mod fare_attributes;
+pub mod fare_leg_rules;
+pub mod fare_media;
+pub mod fare_products;
 pub mod fare_rules;
+pub mod fare_transfer_rules;
 pub mod feed_info;
 pub mod frequencies;
 pub mod legacy_translations;
+pub mod levels;
+pub mod networks;
 pub mod office_jp;
+pub mod pathways;
+pub mod route_networks;
 pub mod routes;
 pub mod routes_jp;
 pub mod shapes;
+pub mod stop_areas;
 pub mod stop_times;
 pub mod stops;
 pub mod transfers;
@@ -52,10 +97,8 @@ pub mod trips;
 
 /// 色. 00FFFF など 6 桁の 16 進数
 pub type Color = String;
-/// メートル
-pub type Meter = u32;
-/// HH:mm:ss形式で28時などの表現も許容
-pub type UnlimitedTime = String;
+/// メートル (ex: 1250.5)
+pub type Meter = OrderedFloat<f64>;
 /// YYYY-MM-DD形式の年月日 (ex: 2017-01-06)
 pub type DateString = String;
 /// YYYYMMDD形式の年月日 (ex: 20170106)(Option<NaiveDate>がDeserializeできないため仕方なく..)
@@ -79,6 +122,174 @@ pub type Latitude = OrderedFloat<f32>;
 /// 経度 (degree)
 pub type Longitude = OrderedFloat<f32>;
 
+/// 事業者・営業所による絞り込み条件 (未指定の項目では絞り込まない)
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct OperatorScope {
+    /// 事業者ID
+    pub agency_id: Option<AgencyId>,
+    /// 営業所ID
+    pub office_id: Option<JpOfficeId>,
+}
+
+/// 並び替えられる項目名と、その項目を求めるSQLの式の組
+pub type SortColumns = &'static [(&'static str, &'static str)];
+
+/// 並び替える項目
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SortKey {
+    /// SortColumnsのSQLの式
+    pub column: &'static str,
+    pub descending: bool,
+}
+
+/// 検索結果のうち取得する範囲 (SQLのLIMIT・OFFSET). limitを指定しない場合はoffset以降すべて
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct Paging {
+    /// 取得する件数の上限
+    pub limit: Option<u32>,
+    /// 読み飛ばす件数
+    pub offset: u32,
+    /// 検索ごとの既定の順序より優先する並び替え
+    pub sort: Vec<SortKey>,
+}
+
+impl Paging {
+    /// `LIMIT :limit`に渡す値 (上限なしは-1)
+    pub fn sql_limit(&self) -> i64 {
+        self.limit.map(|x| x as i64).unwrap_or(-1)
+    }
+
+    /// ORDER BY句の式. sortの項目で並び替えてから既定の順序defaultで並べる
+    /// nullは昇順・降順ともに最後にまとめる
+    pub fn order_by(&self, default: &str) -> String {
+        self.sort
+            .iter()
+            .map(|x| {
+                format!(
+                    "{column} IS NULL, {column}{direction}",
+                    column = x.column,
+                    direction = if x.descending { " DESC" } else { "" }
+                )
+            })
+            .chain(std::iter::once(default.to_string()))
+            .join(", ")
+    }
+}
+
+/// 2地点間の大円距離 (メートル)
+pub fn great_circle_distance(from: (Latitude, Longitude), to: (Latitude, Longitude)) -> f64 {
+    const EARTH_RADIUS: f64 = 6_371_000.0;
+    let (lat1, lon1) = (
+        (from.0 .0 as f64).to_radians(),
+        (from.1 .0 as f64).to_radians(),
+    );
+    let (lat2, lon2) = ((to.0 .0 as f64).to_radians(), (to.1 .0 as f64).to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+/// GTFSの時刻. サービス日の「正午の12時間前」からの経過秒数で表す
+/// H:MM:SS または HH:MM:SS 形式で、25:10:00のような24時以降の表現も許容する
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Hash)]
+pub struct GtfsTime(Second);
+
+impl GtfsTime {
+    pub fn from_seconds(seconds: Second) -> Self {
+        GtfsTime(seconds)
+    }
+
+    /// サービス日の「正午の12時間前」からの経過秒数
+    pub fn seconds(&self) -> Second {
+        self.0
+    }
+
+    /// otherからの経過秒数 (otherの方が遅い場合は負)
+    pub fn seconds_since(&self, other: &GtfsTime) -> i64 {
+        self.0 as i64 - other.0 as i64
+    }
+}
+
+impl Add<Second> for GtfsTime {
+    type Output = GtfsTime;
+
+    fn add(self, seconds: Second) -> GtfsTime {
+        GtfsTime(self.0 + seconds)
+    }
+}
+
+impl fmt::Display for GtfsTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}:{:02}",
+            self.0 / 3600,
+            self.0 % 3600 / 60,
+            self.0 % 60
+        )
+    }
+}
+
+impl FromStr for GtfsTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("{} は H:MM:SS または HH:MM:SS 形式の時刻ではありません", s);
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        match parts.as_slice() {
+            [h, m, s] if !h.is_empty() && h.len() <= 2 && m.len() == 2 && s.len() == 2 => {
+                let h: Second = h.parse().map_err(|_| invalid())?;
+                let m: Second = m.parse().map_err(|_| invalid())?;
+                let s: Second = s.parse().map_err(|_| invalid())?;
+                if m >= 60 || s >= 60 {
+                    return Err(invalid());
+                }
+                Ok(GtfsTime(h * 3600 + m * 60 + s))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Serialize for GtfsTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for GtfsTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// GTFSの時刻を格納したtextカラムから経過秒数を求めるSQL式 (MM:SSは常に末尾5文字)
+#[macro_export]
+macro_rules! gtfs_time_seconds_sql {
+    ($column:literal) => {
+        concat!(
+            "CAST(substr(",
+            $column,
+            ", 1, length(",
+            $column,
+            ") - 6) AS integer) * 3600 + ",
+            "CAST(substr(",
+            $column,
+            ", -5, 2) AS integer) * 60 + ",
+            "CAST(substr(",
+            $column,
+            ", -2, 2) AS integer)"
+        )
+    };
+}
+
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
 pub enum Timezone {
     /// 日本語
@@ -118,6 +329,18 @@ pub enum DirectionId {
     Inbound = 1,
 }
 
+impl FromStr for DirectionId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(DirectionId::Outbound),
+            "1" => Ok(DirectionId::Inbound),
+            _ => Err(format!("{} は上下区分(0または1)ではありません", s)),
+        }
+    }
+}
+
 /// GTFSのCSVファイルを扱うインタフェース
 pub trait GtfsCsvTrait {
     fn load_agencies(&mut self) -> Result<Vec<Agency>>;
@@ -144,11 +367,46 @@ pub trait GtfsCsvTrait {
     fn has_frequencies(&mut self) -> bool;
     fn load_transfers(&mut self) -> Result<Vec<Transfer>>;
     fn has_transfers(&mut self) -> bool;
+    fn load_levels(&mut self) -> Result<Vec<Level>>;
+    fn has_levels(&mut self) -> bool;
+    fn load_pathways(&mut self) -> Result<Vec<Pathway>>;
+    fn has_pathways(&mut self) -> bool;
+    fn load_attributions(&mut self) -> Result<Vec<Attribution>>;
+    fn has_attributions(&mut self) -> bool;
+    fn load_fare_media(&mut self) -> Result<Vec<FareMedia>>;
+    fn has_fare_media(&mut self) -> bool;
+    fn load_fare_products(&mut self) -> Result<Vec<FareProduct>>;
+    fn has_fare_products(&mut self) -> bool;
+    fn load_fare_leg_rules(&mut self) -> Result<Vec<FareLegRule>>;
+    fn has_fare_leg_rules(&mut self) -> bool;
+    fn load_fare_transfer_rules(&mut self) -> Result<Vec<FareTransferRule>>;
+    fn has_fare_transfer_rules(&mut self) -> bool;
+    fn load_areas(&mut self) -> Result<Vec<Area>>;
+    fn has_areas(&mut self) -> bool;
+    fn load_stop_areas(&mut self) -> Result<Vec<StopArea>>;
+    fn has_stop_areas(&mut self) -> bool;
+    fn load_networks(&mut self) -> Result<Vec<Network>>;
+    fn has_networks(&mut self) -> bool;
+    fn load_route_networks(&mut self) -> Result<Vec<RouteNetwork>>;
+    fn has_route_networks(&mut self) -> bool;
+    fn load_booking_rules(&mut self) -> Result<Vec<BookingRule>>;
+    fn has_booking_rules(&mut self) -> bool;
     fn load_feeds(&mut self) -> Result<Vec<Feed>>;
     fn load_translations(&mut self) -> Result<Vec<Translation>>;
     fn load_legacy_translations(&mut self) -> Result<Vec<LegacyTranslation>>;
+    /// translations.txtが古い仕様(trans_id, lang, translation)かどうか
+    fn is_legacy_translations(&mut self) -> Result<bool>;
+    /// 任意のファイルが存在するか
+    fn has_file(&mut self, file_name: &str) -> bool;
+    /// 任意のファイルのヘッダを取得する
+    fn load_headers(&mut self, file_name: &str) -> Result<Vec<String>>;
+    /// 任意のファイルを型を定義せずに読みこむ
+    fn load_raw(&mut self, file_name: &str) -> Result<Vec<RawRecord>>;
+    /// ディレクトリにある.txtファイルの名前一覧
+    fn list_file_names(&mut self) -> Result<Vec<String>>;
     // --- extended ---
-    fn load_service_route_identity(&self, path: &PathBuf) -> Result<Vec<ServiceRouteIdentity>>;
+    fn load_service_route_identity(&self, path: &Path) -> Result<Vec<ServiceRouteIdentity>>;
+    fn load_node_mappings(&self, path: &Path) -> Result<Vec<NodeMapping>>;
 }
 
 /// GTFSのDBを扱うインタフェース
@@ -158,24 +416,65 @@ pub trait GtfsDbTrait {
     fn insert_agencies(&mut self, agencies: &[Agency]) -> Result<()>;
     fn insert_agencies_jp(&mut self, agencies: &[AgencyJp]) -> Result<()>;
     fn insert_stops(&mut self, stops: &[Stop]) -> Result<()>;
-    fn select_stops(&mut self, word: String) -> Result<Vec<Stop>>;
+    fn select_stops(&mut self, word: String, paging: &Paging) -> Result<Vec<Stop>>;
+    /// select_stopsでページングしない場合の件数
+    fn count_stops(&mut self, word: String) -> Result<u32>;
+    fn select_stop(&mut self, stop_id: StopId) -> Result<Option<Stop>>;
+    fn select_stops_by_parents(&mut self, parent_stations: Vec<StopId>) -> Result<Vec<Stop>>;
     fn insert_routes(&mut self, routes: &[Route]) -> Result<()>;
-    fn select_routes(&mut self) -> Result<Vec<Route>>;
+    fn select_routes(&mut self, scope: &OperatorScope) -> Result<Vec<Route>>;
+    /// routeで使われている経路タイプを取得する
+    fn select_route_types(&mut self) -> Result<Vec<RouteType>>;
+    /// stopを通るrouteの経路タイプを、stopと経路タイプの組で取得する
+    fn select_route_types_by_stop(&mut self) -> Result<Vec<(StopId, RouteType)>>;
     fn insert_routes_jp(&mut self, routes: &[RouteJp]) -> Result<()>;
     fn insert_trips(&mut self, trips: &[Trip]) -> Result<()>;
-    fn select_trips(&mut self, stop_id: StopId) -> Result<Vec<Trip>>;
+    /// stop_timesが1つもないtripのIDを取得する
+    fn select_trip_ids_without_stop_times(&mut self) -> Result<Vec<TripId>>;
+    /// 返却結果のソートは 始発の出発時刻, trip_id を保証する
+    fn select_trips(&mut self, query: &TripQuery, paging: &Paging) -> Result<Vec<Trip>>;
+    /// select_tripsでページングしない場合の件数
+    fn count_trips(&mut self, query: &TripQuery) -> Result<u32>;
     fn insert_offices_jp(&mut self, offices: &[OfficeJp]) -> Result<()>;
     fn insert_stop_times(&mut self, stop_times: &[StopTime]) -> Result<()>;
+    /// tripのいずれかに属するstop_timeを取得する
+    fn select_stop_times(&mut self, trip_ids: Vec<TripId>) -> Result<Vec<StopTime>>;
     fn insert_calendars(&mut self, calendars: &[Calendar]) -> Result<()>;
     fn insert_calendar_dates(&mut self, calendar_dates: &[CalendarDate]) -> Result<()>;
+    /// 指定日に運行するservice_idを取得する
+    fn select_service_ids(&mut self, date: &NaiveDate) -> Result<Vec<ServiceId>>;
     fn insert_fare_attributes(&mut self, fare_attributes: &[FareAttribute]) -> Result<()>;
     fn insert_fare_rules(&mut self, fare_rules: &[FareRule]) -> Result<()>;
     fn insert_shapes(&mut self, shapes: &[Shape]) -> Result<()>;
     fn insert_frequencies(&mut self, frequencies: &[Frequency]) -> Result<()>;
     fn insert_transfers(&mut self, transfers: &[Transfer]) -> Result<()>;
+    fn select_transfers(&mut self) -> Result<Vec<Transfer>>;
+    fn insert_levels(&mut self, levels: &[Level]) -> Result<()>;
+    fn select_levels(&mut self, level_ids: Vec<LevelId>) -> Result<Vec<Level>>;
+    fn insert_pathways(&mut self, pathways: &[Pathway]) -> Result<()>;
+    fn select_pathways(&mut self, stop_ids: Vec<StopId>) -> Result<Vec<Pathway>>;
+    fn insert_attributions(&mut self, attributions: &[Attribution]) -> Result<()>;
+    fn insert_fare_media(&mut self, fare_media: &[FareMedia]) -> Result<()>;
+    fn insert_fare_products(&mut self, fare_products: &[FareProduct]) -> Result<()>;
+    fn insert_fare_leg_rules(&mut self, fare_leg_rules: &[FareLegRule]) -> Result<()>;
+    fn insert_fare_transfer_rules(
+        &mut self,
+        fare_transfer_rules: &[FareTransferRule],
+    ) -> Result<()>;
+    fn insert_areas(&mut self, areas: &[Area]) -> Result<()>;
+    fn insert_stop_areas(&mut self, stop_areas: &[StopArea]) -> Result<()>;
+    fn insert_networks(&mut self, networks: &[Network]) -> Result<()>;
+    fn insert_route_networks(&mut self, route_networks: &[RouteNetwork]) -> Result<()>;
+    fn insert_booking_rules(&mut self, booking_rules: &[BookingRule]) -> Result<()>;
     fn insert_feeds(&mut self, feeds: &[Feed]) -> Result<()>;
     fn insert_translations(&mut self, translations: &[Translation]) -> Result<()>;
     fn insert_legacy_translations(&mut self, translations: &[LegacyTranslation]) -> Result<()>;
+    /// 翻訳対象となりうるフィールドの値を重複なく取得する
+    fn select_translatable_values(
+        &mut self,
+        table_name: &TranslatableTableName,
+        field_name: &str,
+    ) -> Result<Vec<String>>;
 
     /// -------------- extended -----------------------
     /// 返却結果のソートは trip_id, stop_sequence を保証する
@@ -183,10 +482,151 @@ pub trait GtfsDbTrait {
         &mut self,
         trip_ids: Option<Vec<TripId>>,
         stop_name_prefix: Option<String>,
+        scope: &OperatorScope,
+        paging: &Paging,
     ) -> Result<Vec<StopTimeDetail>>;
 
+    /// select_stop_time_detailsでページングしない場合の件数
+    fn count_stop_time_details(
+        &mut self,
+        trip_ids: Option<Vec<TripId>>,
+        stop_name_prefix: Option<String>,
+        scope: &OperatorScope,
+    ) -> Result<u32>;
+
     fn select_service_route_identity(&mut self) -> Result<Vec<ServiceRouteIdentity>>;
 
+    /// 返却結果のソートは paging.sort, service_route_id, direction_id を保証する
+    fn select_service_route_summaries(
+        &mut self,
+        service_route_id: Option<ServiceRouteId>,
+        route_id: Option<RouteId>,
+        stop_id: Option<StopId>,
+        direction_id: Option<DirectionId>,
+        paging: &Paging,
+    ) -> Result<Vec<ServiceRouteSummary>>;
+
+    /// select_service_route_summariesでページングしない場合の件数
+    fn count_service_route_summaries(
+        &mut self,
+        route_id: Option<RouteId>,
+        stop_id: Option<StopId>,
+        direction_id: Option<DirectionId>,
+    ) -> Result<u32>;
+
+    /// 返却結果のソートは 出発時刻, trip_id を保証する
+    fn select_service_route_trips(
+        &mut self,
+        service_route_id: ServiceRouteId,
+        direction_id: DirectionId,
+    ) -> Result<Vec<ServiceRouteTrip>>;
+
+    fn select_agency_details(&mut self, paging: &Paging) -> Result<Vec<AgencyDetail>>;
+
+    /// select_agency_detailsでページングしない場合の件数
+    fn count_agency_details(&mut self) -> Result<u32>;
+
+    fn select_office_details(&mut self, paging: &Paging) -> Result<Vec<OfficeDetail>>;
+
+    /// select_office_detailsでページングしない場合の件数
+    fn count_office_details(&mut self) -> Result<u32>;
+
+    /// stopsの座標から空間インデックスを作成する. 作成したレコード数を返す
+    fn insert_stops_rtree(&mut self) -> Result<usize>;
+
+    /// 中心からおよそradiusメートル以内にあるstopを近い順に取得する (距離は未計算)
+    fn select_stops_in_bounds(
+        &mut self,
+        lat: f64,
+        lon: f64,
+        radius: f64,
+        paging: &Paging,
+    ) -> Result<Vec<NearbyStop>>;
+
+    /// select_stops_in_boundsでページングしない場合の件数
+    fn count_stops_in_bounds(&mut self, lat: f64, lon: f64, radius: f64) -> Result<u32>;
+
+    fn select_nodes(&mut self, word: Option<String>, paging: &Paging) -> Result<Vec<Node>>;
+
+    /// select_nodesでページングしない場合の件数
+    fn count_nodes(&mut self, word: Option<String>) -> Result<u32>;
+
+    fn select_node(&mut self, node_id: NodeId) -> Result<Option<Node>>;
+
+    fn select_node_stops(&mut self, node_id: NodeId) -> Result<Vec<Stop>>;
+
+    fn select_node_routes(&mut self, node_id: NodeId) -> Result<Vec<Route>>;
+
+    fn select_node_service_routes(&mut self, node_id: NodeId) -> Result<Vec<ServiceRoute>>;
+
+    /// 返却結果のソートは 出発時刻, trip_id を保証する
+    fn select_node_departures(
+        &mut self,
+        node_id: NodeId,
+        service_ids: Option<Vec<ServiceId>>,
+        from: Option<&GtfsTime>,
+        scope: &OperatorScope,
+        paging: &Paging,
+    ) -> Result<Vec<NodeDeparture>>;
+
+    /// select_node_departuresでページングしない場合の件数
+    fn count_node_departures(
+        &mut self,
+        node_id: NodeId,
+        service_ids: Option<Vec<ServiceId>>,
+        from: Option<&GtfsTime>,
+        scope: &OperatorScope,
+    ) -> Result<u32>;
+
+    /// 返却結果のソートはquery.order_byを保証する
+    fn select_records(&mut self, query: &RecordQuery) -> Result<Vec<Record>>;
+
+    /// 返却結果のソートは block_id, 出発時刻 を保証する
+    fn select_block_trips(
+        &mut self,
+        service_ids: Vec<ServiceId>,
+        block_id: Option<String>,
+    ) -> Result<Vec<BlockTrip>>;
+
+    /// 返却結果のソートは paging.sort, route_id を保証する
+    fn select_route_details(
+        &mut self,
+        route_id: Option<RouteId>,
+        word: Option<String>,
+        route_types: Option<Vec<RouteType>>,
+        scope: &OperatorScope,
+        paging: &Paging,
+    ) -> Result<Vec<RouteDetail>>;
+
+    /// select_route_detailsでページングしない場合の件数
+    fn count_route_details(
+        &mut self,
+        word: Option<String>,
+        route_types: Option<Vec<RouteType>>,
+        scope: &OperatorScope,
+    ) -> Result<u32>;
+
+    fn select_route_stops(&mut self, route_id: RouteId) -> Result<Vec<Stop>>;
+
+    fn select_route_service_routes(&mut self, route_id: RouteId) -> Result<Vec<ServiceRoute>>;
+
+    /// 返却結果のソートは paging.sort, fare_product_id, fare_media_id を保証する
+    fn select_leg_fares(
+        &mut self,
+        from_stop_id: StopId,
+        to_stop_id: StopId,
+        route_id: Option<RouteId>,
+        paging: &Paging,
+    ) -> Result<Vec<LegFare>>;
+
+    /// select_leg_faresでページングしない場合の件数
+    fn count_leg_fares(
+        &mut self,
+        from_stop_id: StopId,
+        to_stop_id: StopId,
+        route_id: Option<RouteId>,
+    ) -> Result<u32>;
+
     fn insert_trips2service_routes(
         &mut self,
         trip2service_routes: &[Trip2ServiceRoute],
@@ -194,7 +634,20 @@ pub trait GtfsDbTrait {
 
     fn insert_service_routes(&mut self, service_routes: &[ServiceRoute]) -> Result<()>;
 
+    fn insert_service_route_merges(&mut self, merges: &[ServiceRouteMerge]) -> Result<()>;
+
+    fn insert_walking_transfers(&mut self, walking_transfers: &[WalkingTransfer]) -> Result<()>;
+
+    fn insert_service_route_problems(&mut self, problems: &[ServiceRouteProblem]) -> Result<()>;
+
     fn select_stop_details(&mut self) -> Result<Vec<StopDetail>>;
 
     fn insert_nodes(&mut self, nodes: &[Node]) -> Result<()>;
+    fn insert_stops2nodes(&mut self, stops2nodes: &[Stop2Node]) -> Result<()>;
+    fn select_stops2nodes(&mut self) -> Result<Vec<Stop2Node>>;
+
+    /// テーブルに仕様外カラムを保持するextrasカラムを追加し、値を更新する
+    fn update_extras(&mut self, table_name: &str, extras: &[ExtraColumns]) -> Result<()>;
+
+    fn insert_extra_records(&mut self, records: &[ExtraRecord]) -> Result<()>;
 }
@@ -54,6 +54,10 @@ impl Table for Agency {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["agency_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         agency_id text primary key,
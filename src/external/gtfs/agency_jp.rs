@@ -45,6 +45,10 @@ impl Table for AgencyJp {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["agency_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         agency_id text primary key,
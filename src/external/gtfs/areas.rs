@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+
+/// エリアID (ex: A_1)
+pub type AreaId = String;
+
+/// エリア情報 (Fares v2)
+/// https://developers.google.com/transit/gtfs/reference#areastxt
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct Area {
+    /// エリアID
+    pub area_id: AreaId,
+    /// エリア名 (ex: 都区内)
+    pub area_name: Option<String>,
+}
+
+impl GTFSFile for Area {
+    fn file_name() -> &'static str {
+        "areas.txt"
+    }
+}
+
+impl Table for Area {
+    fn table_name() -> &'static str {
+        "areas"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["area_id", "area_name"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["area_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        area_id text primary key,
+        area_name text
+        "
+    }
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::external::gtfs::agency::AgencyId;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{MailAddress, TelephoneNumber, Url};
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+
+/// 帰属ID
+pub type AttributionId = String;
+
+/// 役割の有無
+#[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
+#[repr(u8)]
+pub enum AttributionRole {
+    /// 役割を持たない
+    No = 0,
+    /// 役割を持つ
+    Yes = 1,
+}
+
+/// データ帰属情報
+/// https://developers.google.com/transit/gtfs/reference#attributionstxt
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct Attribution {
+    /// 帰属ID
+    pub attribution_id: Option<AttributionId>,
+    /// 事業者ID (agency_id, route_id, trip_idのうち最大1つを指定)
+    pub agency_id: Option<AgencyId>,
+    /// 経路ID
+    pub route_id: Option<RouteId>,
+    /// 便ID
+    pub trip_id: Option<TripId>,
+    /// 組織名 (ex: 株式会社みみぞう)
+    pub organization_name: String,
+    /// データ作成者か
+    pub is_producer: Option<AttributionRole>,
+    /// 運行者か
+    pub is_operator: Option<AttributionRole>,
+    /// 管轄者か
+    pub is_authority: Option<AttributionRole>,
+    /// 組織URL
+    pub attribution_url: Option<Url>,
+    /// 組織Eメール
+    pub attribution_email: Option<MailAddress>,
+    /// 組織電話番号
+    pub attribution_phone: Option<TelephoneNumber>,
+}
+
+impl GTFSFile for Attribution {
+    fn file_name() -> &'static str {
+        "attributions.txt"
+    }
+}
+
+impl Table for Attribution {
+    fn table_name() -> &'static str {
+        "attributions"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &[
+            "attribution_id",
+            "agency_id",
+            "route_id",
+            "trip_id",
+            "organization_name",
+            "is_producer",
+            "is_operator",
+            "is_authority",
+            "attribution_url",
+            "attribution_email",
+            "attribution_phone",
+        ]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        attribution_id text,
+        agency_id text,
+        route_id text,
+        trip_id text,
+        organization_name text not null,
+        is_producer int,
+        is_operator int,
+        is_authority int,
+        attribution_url text,
+        attribution_email text,
+        attribution_phone text
+        "
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::{GtfsTime, TelephoneNumber, Url};
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+
+/// 予約ルールID (ex: BR_1)
+pub type BookingRuleId = String;
+
+/// 予約タイプ
+#[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
+#[repr(u8)]
+pub enum BookingType {
+    /// 直前まで予約可能
+    RealTime = 0,
+    /// 当日の事前予約が必要
+    SameDay = 1,
+    /// 前日以前の予約が必要
+    PriorDays = 2,
+}
+
+/// 予約ルール情報 (GTFS-Flex)
+/// https://github.com/MobilityData/gtfs-flex/blob/master/spec/reference.md#booking_rulestxt-file-added
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct BookingRule {
+    /// 予約ルールID
+    pub booking_rule_id: BookingRuleId,
+    /// 予約タイプ
+    pub booking_type: BookingType,
+    /// 予約締切の最小時間 (分)
+    pub prior_notice_duration_min: Option<u32>,
+    /// 予約受付の最大時間 (分)
+    pub prior_notice_duration_max: Option<u32>,
+    /// 予約締切日 (何日前か)
+    pub prior_notice_last_day: Option<u32>,
+    /// 予約締切時刻
+    pub prior_notice_last_time: Option<GtfsTime>,
+    /// 予約受付開始日 (何日前か)
+    pub prior_notice_start_day: Option<u32>,
+    /// 予約受付開始時刻
+    pub prior_notice_start_time: Option<GtfsTime>,
+    /// 予約日の数え方に使う運行日ID
+    pub prior_notice_service_id: Option<ServiceId>,
+    /// 案内メッセージ
+    pub message: Option<String>,
+    /// 乗車時の案内メッセージ
+    pub pickup_message: Option<String>,
+    /// 降車時の案内メッセージ
+    pub drop_off_message: Option<String>,
+    /// 予約電話番号
+    pub phone_number: Option<TelephoneNumber>,
+    /// 案内URL
+    pub info_url: Option<Url>,
+    /// 予約URL
+    pub booking_url: Option<Url>,
+}
+
+impl GTFSFile for BookingRule {
+    fn file_name() -> &'static str {
+        "booking_rules.txt"
+    }
+}
+
+impl Table for BookingRule {
+    fn table_name() -> &'static str {
+        "booking_rules"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &[
+            "booking_rule_id",
+            "booking_type",
+            "prior_notice_duration_min",
+            "prior_notice_duration_max",
+            "prior_notice_last_day",
+            "prior_notice_last_time",
+            "prior_notice_start_day",
+            "prior_notice_start_time",
+            "prior_notice_service_id",
+            "message",
+            "pickup_message",
+            "drop_off_message",
+            "phone_number",
+            "info_url",
+            "booking_url",
+        ]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["booking_rule_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        booking_rule_id text primary key,
+        booking_type int not null,
+        prior_notice_duration_min int,
+        prior_notice_duration_max int,
+        prior_notice_last_day int,
+        prior_notice_last_time text,
+        prior_notice_start_day int,
+        prior_notice_start_time text,
+        prior_notice_service_id text,
+        message text,
+        pickup_message text,
+        drop_off_message text,
+        phone_number text,
+        info_url text,
+        booking_url text
+        "
+    }
+}
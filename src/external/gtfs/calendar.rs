@@ -1,7 +1,9 @@
+use crate::external::gtfs::calendar_dates::CalendarDate;
 use crate::external::gtfscsv::GTFSFile;
 use crate::external::gtfsdb::Table;
 use crate::serde_chrono_custom::yyyymmdd;
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate, Weekday};
+use rusqlite::{named_params, Connection};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
@@ -72,6 +74,10 @@ impl Table for Calendar {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["service_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         service_id text primary key,
@@ -87,3 +93,47 @@ impl Table for Calendar {
         "
     }
 }
+
+/// 指定日に運行するservice_idを取得する
+/// calendarの曜日・期間に該当するものにcalendar_datesの適用(1)を加え、非適用(2)を除く
+pub fn select_service_ids_by_date(
+    conn: &mut Connection,
+    date: &NaiveDate,
+) -> rusqlite::Result<Vec<ServiceId>> {
+    let weekday_column = match date.weekday() {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    };
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT service_id FROM {calendar}
+WHERE {weekday} == 1 AND start_date <= :date AND end_date >= :date
+UNION
+SELECT service_id FROM {calendar_dates}
+WHERE date == :date AND exception_type == 1
+EXCEPT
+SELECT service_id FROM {calendar_dates}
+WHERE date == :date AND exception_type == 2
+ORDER BY service_id
+",
+            calendar = Calendar::table_name(),
+            calendar_dates = CalendarDate::table_name(),
+            weekday = weekday_column,
+        )
+        .as_str(),
+    )?;
+
+    let rows = stmt.query_map_named(
+        named_params! {
+            ":date": yyyymmdd::format(date),
+        },
+        |row| row.get(0),
+    )?;
+    rows.collect()
+}
@@ -46,6 +46,10 @@ impl Table for CalendarDate {
         &["service_id", "date", "exception_type"]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["service_id", "date"]
+    }
+
     fn create_sql() -> &'static str {
         "
         service_id text,
@@ -1,6 +1,19 @@
+pub mod agency_details;
+pub mod block_trips;
+pub mod extras;
+pub mod leg_fares;
+pub mod nearby_stops;
+pub mod node_details;
 pub mod nodes;
+pub mod office_details;
+pub mod records;
+pub mod route_details;
+pub mod service_route_details;
 pub mod service_route_identity;
+pub mod service_route_merges;
+pub mod service_route_problems;
 pub mod service_routes;
 pub mod stop_details;
 pub mod stop_time_details;
 pub mod trips2service_routes;
+pub mod walking_transfers;
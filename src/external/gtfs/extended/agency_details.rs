@@ -0,0 +1,87 @@
+use rusqlite::{named_params, Connection, NO_PARAMS};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::from_rows;
+
+use crate::external::gtfs::agency::{Agency, AgencyId};
+use crate::external::gtfs::agency_jp::AgencyJp;
+use crate::external::gtfs::routes::Route;
+use crate::external::gtfs::trips::Trip;
+use crate::external::gtfs::{Paging, SortColumns, Url};
+use crate::external::gtfsdb::Table;
+
+/// 事業者と、その事業者が運行するrouteとtripの数
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct AgencyDetail {
+    /// 事業者ID
+    pub agency_id: AgencyId,
+    /// 事業者名称 (ex: 都営バス)
+    pub agency_name: String,
+    /// 事業者正式名称 (ex: 東京都交通局)
+    pub agency_official_name: Option<String>,
+    /// 事業者URL (ex: http://www.kotsu.metro.tokyo.jp/bus/)
+    pub agency_url: Url,
+    /// route数
+    pub route_count: u32,
+    /// trip数
+    pub trip_count: u32,
+}
+
+/// select_agency_detailsで並び替えられる項目
+pub const AGENCY_DETAIL_SORT_COLUMNS: SortColumns = &[
+    ("agency_id", "a.agency_id"),
+    ("agency_name", "a.agency_name"),
+    ("agency_official_name", "aj.agency_official_name"),
+    ("route_count", "route_count"),
+    ("trip_count", "trip_count"),
+];
+
+/// 事業者の詳細をpagingの範囲だけ取得する
+pub fn select_agency_details(
+    conn: &mut Connection,
+    paging: &Paging,
+) -> serde_rusqlite::Result<Vec<AgencyDetail>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  a.agency_id,
+  a.agency_name,
+  aj.agency_official_name,
+  a.agency_url,
+  (SELECT count(*) FROM {routes} r WHERE r.agency_id == a.agency_id) AS route_count,
+  (SELECT count(*)
+   FROM {trips} t INNER JOIN {routes} r ON t.route_id == r.route_id
+   WHERE r.agency_id == a.agency_id) AS trip_count
+FROM
+  {agency} a
+    LEFT JOIN {agency_jp} aj
+    ON a.agency_id == aj.agency_id
+ORDER BY
+  {order}
+LIMIT :limit OFFSET :offset
+",
+            agency = Agency::table_name(),
+            agency_jp = AgencyJp::table_name(),
+            routes = Route::table_name(),
+            trips = Trip::table_name(),
+            order = paging.order_by("a.agency_id"),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":limit": paging.sql_limit(),
+        ":offset": paging.offset,
+    })?)
+    .collect();
+    result
+}
+
+/// select_agency_detailsでページングしない場合の件数
+pub fn count_agency_details(conn: &mut Connection) -> rusqlite::Result<u32> {
+    conn.query_row(
+        format!("SELECT count(*) FROM {}", Agency::table_name()).as_str(),
+        NO_PARAMS,
+        |row| row.get(0),
+    )
+}
@@ -0,0 +1,113 @@
+use std::rc::Rc;
+
+use itertools::Itertools;
+use rusqlite::{named_params, types::Value, Connection};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::from_rows;
+
+use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::{Stop, StopId};
+use crate::external::gtfs::trips::{Trip, TripId};
+use crate::external::gtfs::{GtfsTime, Latitude, Longitude};
+use crate::external::gtfsdb::Table;
+
+/// 運用を組み立てるための便の始点・終点情報
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct BlockTrip {
+    /// 便結合区分 (運用ID)
+    pub block_id: Option<String>,
+    /// 便ID
+    pub trip_id: TripId,
+    /// 経路ID
+    pub route_id: RouteId,
+    /// 運行日ID
+    pub service_id: ServiceId,
+    /// 始点の標柱ID
+    pub first_stop_id: StopId,
+    /// 始点の停留所名
+    pub first_stop_name: String,
+    /// 始点の親駅
+    pub first_parent_station: Option<StopId>,
+    /// 始点の緯度
+    pub first_stop_lat: Latitude,
+    /// 始点の経度
+    pub first_stop_lon: Longitude,
+    /// 始点の出発時刻
+    pub departure_time: GtfsTime,
+    /// 終点の標柱ID
+    pub last_stop_id: StopId,
+    /// 終点の停留所名
+    pub last_stop_name: String,
+    /// 終点の親駅
+    pub last_parent_station: Option<StopId>,
+    /// 終点の緯度
+    pub last_stop_lat: Latitude,
+    /// 終点の経度
+    pub last_stop_lon: Longitude,
+    /// 終点の到着時刻
+    pub arrival_time: GtfsTime,
+}
+
+/// service_idsのいずれかで運行する便の始点・終点を取得する
+/// block_idを指定した場合はその運用に属する便のみ取得する
+/// 返却結果のソートは block_id, 出発時刻 を保証する
+pub fn select_block_trips(
+    conn: &mut Connection,
+    service_ids: Vec<ServiceId>,
+    block_id: Option<String>,
+) -> serde_rusqlite::Result<Vec<BlockTrip>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  t.block_id,
+  t.trip_id,
+  t.route_id,
+  t.service_id,
+  fst.stop_id AS first_stop_id,
+  fs.stop_name AS first_stop_name,
+  fs.parent_station AS first_parent_station,
+  fs.stop_lat AS first_stop_lat,
+  fs.stop_lon AS first_stop_lon,
+  fst.departure_time,
+  lst.stop_id AS last_stop_id,
+  ls.stop_name AS last_stop_name,
+  ls.parent_station AS last_parent_station,
+  ls.stop_lat AS last_stop_lat,
+  ls.stop_lon AS last_stop_lon,
+  lst.arrival_time
+FROM
+  {trips} t
+    INNER JOIN {stop_times} fst
+    ON t.trip_id == fst.trip_id
+      AND fst.stop_sequence == (SELECT min(stop_sequence) FROM {stop_times} WHERE trip_id == t.trip_id)
+    INNER JOIN {stops} fs
+    ON fst.stop_id == fs.stop_id
+    INNER JOIN {stop_times} lst
+    ON t.trip_id == lst.trip_id
+      AND lst.stop_sequence == (SELECT max(stop_sequence) FROM {stop_times} WHERE trip_id == t.trip_id)
+    INNER JOIN {stops} ls
+    ON lst.stop_id == ls.stop_id
+WHERE
+  t.service_id in rarray(:service_ids)
+  AND (:block_id IS NULL OR t.block_id == :block_id)
+ORDER BY
+  t.block_id, fst.departure_time_seconds, t.trip_id
+",
+            trips = Trip::table_name(),
+            stop_times = StopTime::table_name(),
+            stops = Stop::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let service_ids = Rc::new(service_ids.into_iter().map(Value::from).collect_vec());
+    let result = from_rows(stmt.query_named(named_params! {
+        ":service_ids": service_ids,
+        ":block_id": block_id,
+    })?)
+    .collect();
+    result
+}
@@ -0,0 +1,165 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::types::{ToSqlOutput, Value};
+use rusqlite::{Connection, ToSql, NO_PARAMS};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::to_params_named;
+
+use crate::external::gtfsdb::Table;
+use crate::io::RawRecord;
+
+/// 仕様外のカラムを保持するカラム名
+pub const EXTRAS_COLUMN: &str = "extras";
+
+/// 既知のテーブルに含まれる仕様外カラムの値
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExtraColumns {
+    /// レコードを特定するためのカラム名と値 (主キー、主キーがないテーブルはrowid)
+    pub keys: Vec<(String, Value)>,
+    /// 仕様外カラムの名前と値をまとめたJSON (ex: {"vendor_code":"A1"})
+    pub extras: String,
+}
+
+impl ExtraColumns {
+    pub fn new(
+        keys: Vec<(String, Value)>,
+        record: &[(String, String)],
+        extra_columns: &[String],
+    ) -> Self {
+        let extras: serde_json::Map<String, serde_json::Value> = record
+            .iter()
+            .filter(|(h, _)| extra_columns.contains(h))
+            .map(|(h, v)| (h.clone(), serde_json::Value::String(v.clone())))
+            .collect();
+        ExtraColumns {
+            keys,
+            extras: serde_json::Value::Object(extras).to_string(),
+        }
+    }
+}
+
+/// レコードをテーブルに挿入するときと同じ形式で、key_columnsの値を取り出す
+/// (ex: 時刻の7:05:30はテーブルと同じ07:05:30になる)
+pub fn key_values<T>(record: &T, key_columns: &[&str]) -> Result<Vec<(String, Value)>>
+where
+    T: Serialize,
+{
+    let params = to_params_named(record)?;
+    let params = params.to_slice();
+    key_columns
+        .iter()
+        .map(|column| {
+            let name = format!(":{}", column);
+            let (_, param) = params
+                .iter()
+                .find(|(n, _)| *n == name)
+                .with_context(|| format!("{}はレコードに含まれないカラムです", column))?;
+            let value = match param.to_sql()? {
+                ToSqlOutput::Borrowed(v) => v.into(),
+                ToSqlOutput::Owned(v) => v,
+                _ => Value::Null,
+            };
+            Ok((column.to_string(), value))
+        })
+        .collect()
+}
+
+/// 仕様外ファイルのレコード (1つの値を1行とするkey/value形式)
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct ExtraRecord {
+    /// ファイル名 (ex: vendor_info.txt)
+    pub file_name: String,
+    /// ファイル中の行番号 (ヘッダを除き1始まり)
+    pub row_number: u32,
+    /// カラム名
+    pub field_name: String,
+    /// 値
+    pub field_value: String,
+}
+
+impl ExtraRecord {
+    pub fn from_raw(file_name: &str, records: &[RawRecord]) -> Vec<ExtraRecord> {
+        records
+            .iter()
+            .enumerate()
+            .flat_map(|(i, record)| {
+                record.iter().map(move |(h, v)| ExtraRecord {
+                    file_name: file_name.to_string(),
+                    row_number: i as u32 + 1,
+                    field_name: h.clone(),
+                    field_value: v.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Table for ExtraRecord {
+    fn table_name() -> &'static str {
+        "extra_records"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["file_name", "row_number", "field_name", "field_value"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["file_name", "row_number", "field_name"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        file_name text not null,
+        row_number int not null,
+        field_name text not null,
+        field_value text,
+        PRIMARY KEY(file_name, row_number, field_name)
+        "
+    }
+}
+
+/// テーブルに仕様外カラムを保持するextrasカラムを追加する
+pub fn add_extras_column(conn: &mut Connection, table_name: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        format!(
+            "ALTER TABLE {} ADD COLUMN {} text",
+            table_name, EXTRAS_COLUMN
+        )
+        .as_str(),
+        NO_PARAMS,
+    )?;
+    Ok(())
+}
+
+/// keysが一致するレコードのextrasカラムを更新する. 一致するレコードがない場合は失敗する
+pub fn update_extras(
+    conn: &mut Connection,
+    table_name: &str,
+    extras: &[ExtraColumns],
+) -> Result<()> {
+    let tx = conn.transaction()?;
+    for extra in extras {
+        let conditions = extra
+            .keys
+            .iter()
+            .enumerate()
+            .map(|(i, (k, _))| format!("{} IS ?{}", k, i + 2))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let sql = format!(
+            "UPDATE {} SET {} = ?1 WHERE {}",
+            table_name, EXTRAS_COLUMN, conditions
+        );
+        let params = vec![Value::Text(extra.extras.clone())]
+            .into_iter()
+            .chain(extra.keys.iter().map(|(_, v)| v.clone()));
+        let updated = tx.prepare_cached(sql.as_str())?.execute(params)?;
+        if updated == 0 {
+            bail!(
+                "仕様外カラムを格納するレコードが見つかりませんでした: {:?}",
+                extra.keys
+            );
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
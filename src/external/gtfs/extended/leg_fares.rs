@@ -0,0 +1,167 @@
+use rusqlite::{named_params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::from_rows;
+
+use crate::external::gtfs::areas::AreaId;
+use crate::external::gtfs::fare_attributes::CurrencyType;
+use crate::external::gtfs::fare_leg_rules::{FareLegRule, LegGroupId};
+use crate::external::gtfs::fare_media::FareMediaId;
+use crate::external::gtfs::fare_products::{FareProduct, FareProductId};
+use crate::external::gtfs::networks::NetworkId;
+use crate::external::gtfs::route_networks::RouteNetwork;
+use crate::external::gtfs::routes::{Route, RouteId};
+use crate::external::gtfs::stop_areas::StopArea;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::{Paging, SortColumns};
+use crate::external::gtfsdb::Table;
+use ordered_float::OrderedFloat;
+
+/// 1区間の乗車に適用される運賃 (Fares v2)
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct LegFare {
+    /// 区間グループID
+    pub leg_group_id: Option<LegGroupId>,
+    /// ネットワークID
+    pub network_id: Option<NetworkId>,
+    /// 乗車エリアID
+    pub from_area_id: Option<AreaId>,
+    /// 降車エリアID
+    pub to_area_id: Option<AreaId>,
+    /// 優先度
+    pub rule_priority: Option<u32>,
+    /// 運賃商品ID
+    pub fare_product_id: FareProductId,
+    /// 運賃商品名 (ex: 大人普通運賃)
+    pub fare_product_name: Option<String>,
+    /// 運賃媒体ID
+    pub fare_media_id: Option<FareMediaId>,
+    /// 金額 (ex: 210)
+    pub amount: OrderedFloat<f64>,
+    /// 通貨 (ex: JPY)
+    pub currency: CurrencyType,
+}
+
+/// select_leg_faresで並び替えられる項目
+pub const LEG_FARE_SORT_COLUMNS: SortColumns = &[
+    ("leg_group_id", "flr.leg_group_id"),
+    ("network_id", "flr.network_id"),
+    ("from_area_id", "flr.from_area_id"),
+    ("to_area_id", "flr.to_area_id"),
+    ("rule_priority", "flr.rule_priority"),
+    ("fare_product_id", "fp.fare_product_id"),
+    ("fare_product_name", "fp.fare_product_name"),
+    ("fare_media_id", "fp.fare_media_id"),
+    ("amount", "fp.amount"),
+    ("currency", "fp.currency"),
+];
+
+/// select_leg_faresとcount_leg_faresで共通の、columnsを取得するSELECT文 (ORDER BY句より前)
+fn leg_fares_sql(columns: &str) -> String {
+    format!(
+        "
+WITH
+  route_network_ids AS (
+    SELECT network_id FROM {route_networks} WHERE route_id == :route_id
+    UNION
+    SELECT network_id FROM {routes} WHERE route_id == :route_id AND network_id IS NOT NULL
+  ),
+  from_area_ids AS (
+    SELECT area_id FROM {stop_areas} WHERE stop_id == :from_stop_id
+  ),
+  to_area_ids AS (
+    SELECT area_id FROM {stop_areas} WHERE stop_id == :to_stop_id
+  ),
+  matched_rules AS (
+    SELECT *
+    FROM {fare_leg_rules} flr
+    WHERE
+      (:route_id IS NULL OR
+       flr.network_id IN route_network_ids OR
+       (flr.network_id IS NULL AND
+        NOT EXISTS (SELECT 1 FROM {fare_leg_rules} WHERE network_id IN route_network_ids)))
+      AND (flr.from_area_id IN from_area_ids OR
+           (flr.from_area_id IS NULL AND
+            NOT EXISTS (SELECT 1 FROM {fare_leg_rules} WHERE from_area_id IN from_area_ids)))
+      AND (flr.to_area_id IN to_area_ids OR
+           (flr.to_area_id IS NULL AND
+            NOT EXISTS (SELECT 1 FROM {fare_leg_rules} WHERE to_area_id IN to_area_ids)))
+  )
+SELECT
+  {columns}
+FROM
+  matched_rules flr
+    INNER JOIN {fare_products} fp
+    ON flr.fare_product_id == fp.fare_product_id
+WHERE
+  coalesce(flr.rule_priority, 0) == (SELECT max(coalesce(rule_priority, 0)) FROM matched_rules)
+",
+        columns = columns,
+        fare_leg_rules = FareLegRule::table_name(),
+        fare_products = FareProduct::table_name(),
+        route_networks = RouteNetwork::table_name(),
+        routes = Route::table_name(),
+        stop_areas = StopArea::table_name(),
+    )
+}
+
+/// 乗車stop、降車stop、経路に該当するfare_leg_rulesと運賃商品を検索する
+/// network_id, from_area_id, to_area_idが未指定のルールは、その項目に該当する指定のルールがない場合にのみ該当する
+/// 経路のネットワークはroute_networksとroutesのnetwork_idの両方から求め、経路を指定しない場合はnetwork_idで絞り込まない
+/// 該当したルールのうち、rule_priorityが最も高いもの (未指定は0) のみを返す. pagingの範囲だけを返す
+pub fn select_leg_fares(
+    conn: &mut Connection,
+    from_stop_id: StopId,
+    to_stop_id: StopId,
+    route_id: Option<RouteId>,
+    paging: &Paging,
+) -> serde_rusqlite::Result<Vec<LegFare>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "{}ORDER BY\n  {}\nLIMIT :limit OFFSET :offset",
+            leg_fares_sql(LEG_FARE_COLUMNS),
+            paging.order_by("fp.fare_product_id, fp.fare_media_id"),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":from_stop_id": from_stop_id,
+        ":to_stop_id": to_stop_id,
+        ":route_id": route_id,
+        ":limit": paging.sql_limit(),
+        ":offset": paging.offset,
+    })?)
+    .collect();
+    result
+}
+
+/// select_leg_faresでページングしない場合の件数
+pub fn count_leg_fares(
+    conn: &mut Connection,
+    from_stop_id: StopId,
+    to_stop_id: StopId,
+    route_id: Option<RouteId>,
+) -> rusqlite::Result<u32> {
+    let mut stmt = conn.prepare(leg_fares_sql("count(*)").as_str())?;
+    stmt.query_row_named(
+        named_params! {
+            ":from_stop_id": from_stop_id,
+            ":to_stop_id": to_stop_id,
+            ":route_id": route_id,
+        },
+        |row| row.get(0),
+    )
+}
+
+/// select_leg_faresで取得するカラム
+const LEG_FARE_COLUMNS: &str = "
+  flr.leg_group_id,
+  flr.network_id,
+  flr.from_area_id,
+  flr.to_area_id,
+  flr.rule_priority,
+  fp.fare_product_id,
+  fp.fare_product_name,
+  fp.fare_media_id,
+  fp.amount,
+  fp.currency";
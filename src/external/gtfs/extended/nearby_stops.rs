@@ -0,0 +1,194 @@
+use rusqlite::{named_params, Connection, NO_PARAMS};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::from_rows;
+
+use crate::external::gtfs::stops::{LocationType, PlatformCode, Stop, StopId};
+use crate::external::gtfs::{Latitude, Longitude, Paging, SortColumns};
+use crate::external::gtfsdb::Table;
+
+/// stopの位置を検索するR*Treeの仮想テーブル (idはstopsのrowid)
+pub const STOPS_RTREE_TABLE_NAME: &str = "stops_rtree";
+
+/// 緯度1度あたりの距離 (メートル)
+const METERS_PER_LATITUDE: f64 = 111_320.0;
+
+/// 座標から近いstop
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct NearbyStop {
+    /// 停留所・標柱ID
+    pub stop_id: StopId,
+    /// 停留所・標柱名称
+    pub stop_name: String,
+    /// 緯度
+    pub stop_lat: Latitude,
+    /// 経度
+    pub stop_lon: Longitude,
+    /// 停留所・標柱区分
+    pub location_type: Option<LocationType>,
+    /// 親駅情報
+    pub parent_station: Option<StopId>,
+    /// のりば情報
+    pub platform_code: Option<PlatformCode>,
+    /// 指定座標からの距離 (メートル)
+    #[serde(default)]
+    pub distance: f64,
+}
+
+pub fn create_stops_rtree(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS {} USING rtree(id, min_lat, max_lat, min_lon, max_lon)",
+            STOPS_RTREE_TABLE_NAME
+        )
+        .as_str(),
+        NO_PARAMS,
+    )?;
+    Ok(())
+}
+
+pub fn drop_stops_rtree(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        format!("DROP TABLE IF EXISTS {}", STOPS_RTREE_TABLE_NAME).as_str(),
+        NO_PARAMS,
+    )?;
+    Ok(())
+}
+
+/// stopsの座標からR*Treeを作成する. 作成したレコード数を返す
+pub fn insert_stops_rtree(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute(
+        format!(
+            "
+INSERT INTO {rtree}
+SELECT rowid, stop_lat, stop_lat, stop_lon, stop_lon
+FROM {stops}
+WHERE stop_lat IS NOT NULL AND stop_lon IS NOT NULL
+",
+            rtree = STOPS_RTREE_TABLE_NAME,
+            stops = Stop::table_name(),
+        )
+        .as_str(),
+        NO_PARAMS,
+    )
+}
+
+/// 中心からradiusメートルの円を含む緯度経度の範囲をR*Treeで絞り込み、
+/// 緯度経度の差を距離に換算した近似距離がradius以内のstopに限るFROM句とWHERE句
+fn stops_in_bounds_from_sql() -> String {
+    format!(
+        "
+FROM
+  {rtree} r
+    INNER JOIN {stops} s
+    ON r.id == s.rowid
+WHERE
+  r.max_lat >= :min_lat AND r.min_lat <= :max_lat
+  AND r.max_lon >= :min_lon AND r.min_lon <= :max_lon
+  AND {distance} <= :radius * :radius
+",
+        rtree = STOPS_RTREE_TABLE_NAME,
+        stops = Stop::table_name(),
+        distance = APPROXIMATE_SQUARED_DISTANCE,
+    )
+}
+
+/// 中心からの近似距離 (メートル) の2乗. 狭い範囲では大円距離とほぼ一致する
+const APPROXIMATE_SQUARED_DISTANCE: &str = "
+  ((s.stop_lat - :lat) * :lat_scale) * ((s.stop_lat - :lat) * :lat_scale)
+  + ((s.stop_lon - :lon) * :lon_scale) * ((s.stop_lon - :lon) * :lon_scale)";
+
+/// select_stops_in_boundsで並び替えられる項目. distanceは近似距離で並べる
+pub const NEARBY_STOP_SORT_COLUMNS: SortColumns = &[
+    ("stop_id", "s.stop_id"),
+    ("stop_name", "s.stop_name"),
+    ("stop_lat", "s.stop_lat"),
+    ("stop_lon", "s.stop_lon"),
+    ("location_type", "s.location_type"),
+    ("parent_station", "s.parent_station"),
+    ("platform_code", "s.platform_code"),
+    ("distance", APPROXIMATE_SQUARED_DISTANCE),
+];
+
+/// 緯度・経度1度あたりの距離 (メートル)
+fn scales_of(lat: f64) -> (f64, f64) {
+    (
+        METERS_PER_LATITUDE,
+        METERS_PER_LATITUDE * lat.to_radians().cos().max(f64::EPSILON),
+    )
+}
+
+/// 中心からおよそradiusメートル以内にあるstopを近い順 (paging.sortを指定した場合はその順) に取得する
+/// pagingを指定した場合はその範囲のみ
+/// 距離は緯度経度の差からの近似で判定するため、正確な距離は呼び出し側で計算すること
+pub fn select_stops_in_bounds(
+    conn: &mut Connection,
+    lat: f64,
+    lon: f64,
+    radius: f64,
+    paging: &Paging,
+) -> serde_rusqlite::Result<Vec<NearbyStop>> {
+    let (lat_scale, lon_scale) = scales_of(lat);
+    let mut stmt = conn.prepare_cached(
+        format!(
+            "
+SELECT
+  s.stop_id,
+  s.stop_name,
+  s.stop_lat,
+  s.stop_lon,
+  s.location_type,
+  s.parent_station,
+  s.platform_code
+{from}
+ORDER BY
+  {order}
+LIMIT :limit OFFSET :offset
+",
+            from = stops_in_bounds_from_sql(),
+            order = paging.order_by(&format!("{}, s.stop_id", APPROXIMATE_SQUARED_DISTANCE)),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":lat": lat,
+        ":lon": lon,
+        ":lat_scale": lat_scale,
+        ":lon_scale": lon_scale,
+        ":radius": radius,
+        ":min_lat": lat - radius / lat_scale,
+        ":max_lat": lat + radius / lat_scale,
+        ":min_lon": lon - radius / lon_scale,
+        ":max_lon": lon + radius / lon_scale,
+        ":limit": paging.sql_limit(),
+        ":offset": paging.offset,
+    })?)
+    .collect();
+    result
+}
+
+/// select_stops_in_boundsでページングしない場合の件数
+pub fn count_stops_in_bounds(
+    conn: &mut Connection,
+    lat: f64,
+    lon: f64,
+    radius: f64,
+) -> rusqlite::Result<u32> {
+    let (lat_scale, lon_scale) = scales_of(lat);
+    let mut stmt =
+        conn.prepare_cached(format!("SELECT count(*) {}", stops_in_bounds_from_sql()).as_str())?;
+    stmt.query_row_named(
+        named_params! {
+            ":lat": lat,
+            ":lon": lon,
+            ":lat_scale": lat_scale,
+            ":lon_scale": lon_scale,
+            ":radius": radius,
+            ":min_lat": lat - radius / lat_scale,
+            ":max_lat": lat + radius / lat_scale,
+            ":min_lon": lon - radius / lon_scale,
+            ":max_lon": lon + radius / lon_scale,
+        },
+        |row| row.get(0),
+    )
+}
@@ -0,0 +1,375 @@
+use std::rc::Rc;
+
+use itertools::Itertools;
+use rusqlite::{named_params, types::Value, Connection};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::from_rows;
+
+use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::extended::nodes::{Node, NodeId, Stop2Node};
+use crate::external::gtfs::extended::service_routes::{ServiceRoute, ServiceRouteId};
+use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
+use crate::external::gtfs::routes::{Route, RouteId};
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::{Stop, StopId};
+use crate::external::gtfs::trips::{Trip, TripId};
+use crate::external::gtfs::{DirectionId, GtfsTime, OperatorScope, Paging, Second, SortColumns};
+use crate::external::gtfsdb::Table;
+
+/// ノードに属するstopからの出発
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct NodeDeparture {
+    /// 出発する標柱ID
+    pub stop_id: StopId,
+    /// 出発する標柱名称
+    pub stop_name: String,
+    /// のりば番号
+    pub platform_code: Option<String>,
+    /// 便ID
+    pub trip_id: TripId,
+    /// 経路ID
+    pub route_id: RouteId,
+    /// 運行日ID
+    pub service_id: ServiceId,
+    /// 便行き先
+    pub trip_headsign: Option<String>,
+    /// 停留所行き先
+    pub stop_headsign: Option<String>,
+    /// サービスルートID
+    pub service_route_id: Option<ServiceRouteId>,
+    /// サービスルートの上下区分
+    pub service_route_direction_id: Option<DirectionId>,
+    /// 出発時刻
+    pub departure_time: GtfsTime,
+}
+
+/// select_nodes_by_wordで並び替えられる項目
+pub const NODE_SORT_COLUMNS: SortColumns = &[
+    ("node_id", "node_id"),
+    ("node_name", "node_name"),
+    ("node_ruby", "node_ruby"),
+];
+
+/// select_nodes_by_wordとcount_nodes_by_wordで共通のFROM句とWHERE句
+fn nodes_from_sql() -> String {
+    format!(
+        "
+FROM
+  {nodes}
+WHERE
+  :word IS NULL OR node_name LIKE :word OR node_ruby LIKE :word
+",
+        nodes = Node::table_name(),
+    )
+}
+
+/// node_nameまたはnode_rubyが部分一致するノードを取得する. pagingの範囲だけを返す
+pub fn select_nodes_by_word(
+    conn: &mut Connection,
+    word: Option<String>,
+    paging: &Paging,
+) -> serde_rusqlite::Result<Vec<Node>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  node_id,
+  node_name,
+  node_ruby
+{from}
+ORDER BY
+  {order}
+LIMIT :limit OFFSET :offset
+",
+            from = nodes_from_sql(),
+            order = paging.order_by("node_id"),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":word": word.map(|x| format!("%{}%", x)),
+        ":limit": paging.sql_limit(),
+        ":offset": paging.offset,
+    })?)
+    .collect();
+    result
+}
+
+/// select_nodes_by_wordでページングしない場合の件数
+pub fn count_nodes_by_word(conn: &mut Connection, word: Option<String>) -> rusqlite::Result<u32> {
+    let mut stmt = conn.prepare(format!("SELECT count(*) {}", nodes_from_sql()).as_str())?;
+    stmt.query_row_named(
+        named_params! {":word": word.map(|x| format!("%{}%", x))},
+        |row| row.get(0),
+    )
+}
+
+pub fn select_node(conn: &mut Connection, node_id: NodeId) -> serde_rusqlite::Result<Option<Node>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "SELECT node_id, node_name, node_ruby FROM {} WHERE node_id == :node_id",
+            Node::table_name()
+        )
+        .as_str(),
+    )?;
+    let result = from_rows(stmt.query_named(named_params! {":node_id": node_id})?)
+        .next()
+        .transpose();
+    result
+}
+
+/// ノードに属するstopを取得する
+pub fn select_node_stops(
+    conn: &mut Connection,
+    node_id: NodeId,
+) -> serde_rusqlite::Result<Vec<Stop>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  s.*
+FROM
+  {stops} s
+    INNER JOIN {stops2nodes} s2n
+    ON s.stop_id == s2n.stop_id
+WHERE
+  s2n.node_id == :node_id
+ORDER BY
+  s.stop_id
+",
+            stops = Stop::table_name(),
+            stops2nodes = Stop2Node::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {":node_id": node_id})?).collect();
+    result
+}
+
+/// ノードに属するstopを通るrouteを取得する
+pub fn select_node_routes(
+    conn: &mut Connection,
+    node_id: NodeId,
+) -> serde_rusqlite::Result<Vec<Route>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  r.*
+FROM
+  {routes} r
+WHERE
+  r.route_id IN (
+    SELECT t.route_id
+    FROM
+      {trips} t
+        INNER JOIN {stop_times} st
+        ON t.trip_id == st.trip_id
+        INNER JOIN {stops2nodes} s2n
+        ON st.stop_id == s2n.stop_id
+    WHERE s2n.node_id == :node_id
+  )
+ORDER BY
+  r.route_id
+",
+            routes = Route::table_name(),
+            trips = Trip::table_name(),
+            stop_times = StopTime::table_name(),
+            stops2nodes = Stop2Node::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {":node_id": node_id})?).collect();
+    result
+}
+
+/// ノードに属するstopを通るサービスルートを取得する
+pub fn select_node_service_routes(
+    conn: &mut Connection,
+    node_id: NodeId,
+) -> serde_rusqlite::Result<Vec<ServiceRoute>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  sr.service_route_id,
+  sr.service_route_name,
+  sr.direction_id
+FROM
+  {service_routes} sr
+WHERE
+  EXISTS (
+    SELECT 1
+    FROM
+      {trips2service_routes} t2sr
+        INNER JOIN {stop_times} st
+        ON t2sr.trip_id == st.trip_id
+        INNER JOIN {stops2nodes} s2n
+        ON st.stop_id == s2n.stop_id
+    WHERE t2sr.service_route_id == sr.service_route_id
+      AND t2sr.service_route_direction_id == sr.direction_id
+      AND s2n.node_id == :node_id
+  )
+ORDER BY
+  sr.service_route_id, sr.direction_id
+",
+            service_routes = ServiceRoute::table_name(),
+            trips2service_routes = Trip2ServiceRoute::table_name(),
+            stop_times = StopTime::table_name(),
+            stops2nodes = Stop2Node::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {":node_id": node_id})?).collect();
+    result
+}
+
+/// ノードに属するstopからの出発の絞り込み条件
+pub struct NodeDepartureQuery<'a> {
+    pub node_id: NodeId,
+    /// 指定した場合はそのいずれかで運行するtripのみ
+    pub service_ids: Option<Vec<ServiceId>>,
+    /// 指定した場合はその時刻以降のみ
+    pub from_seconds: Option<Second>,
+    pub scope: &'a OperatorScope,
+}
+
+/// select_node_departuresで並び替えられる項目
+pub const NODE_DEPARTURE_SORT_COLUMNS: SortColumns = &[
+    ("stop_id", "s.stop_id"),
+    ("stop_name", "s.stop_name"),
+    ("platform_code", "s.platform_code"),
+    ("trip_id", "t.trip_id"),
+    ("route_id", "t.route_id"),
+    ("service_id", "t.service_id"),
+    ("trip_headsign", "t.trip_headsign"),
+    ("stop_headsign", "st.stop_headsign"),
+    ("service_route_id", "t2sr.service_route_id"),
+    (
+        "service_route_direction_id",
+        "t2sr.service_route_direction_id",
+    ),
+    ("departure_time", "st.departure_time_seconds"),
+];
+
+/// select_node_departuresとcount_node_departuresで共通のFROM句とWHERE句
+fn node_departures_from_sql() -> String {
+    format!(
+        "
+FROM
+  {stop_times} st
+    INNER JOIN {stops2nodes} s2n
+    ON st.stop_id == s2n.stop_id
+    INNER JOIN {stops} s
+    ON st.stop_id == s.stop_id
+    INNER JOIN {trips} t
+    ON st.trip_id == t.trip_id
+    INNER JOIN {routes} r
+    ON t.route_id == r.route_id
+    LEFT JOIN {trips2service_routes} t2sr
+    ON st.trip_id == t2sr.trip_id
+WHERE
+  s2n.node_id == :node_id
+  AND st.departure_time_seconds IS NOT NULL
+  AND st.stop_sequence < (SELECT max(stop_sequence) FROM {stop_times} WHERE trip_id == st.trip_id)
+  AND (:all_services OR t.service_id IN rarray(:service_ids))
+  AND (:from_seconds IS NULL OR st.departure_time_seconds >= :from_seconds)
+  AND (:agency_id IS NULL OR r.agency_id == :agency_id)
+  AND (:office_id IS NULL OR t.jp_office_id == :office_id)
+",
+        stop_times = StopTime::table_name(),
+        stops2nodes = Stop2Node::table_name(),
+        stops = Stop::table_name(),
+        trips = Trip::table_name(),
+        routes = Route::table_name(),
+        trips2service_routes = Trip2ServiceRoute::table_name(),
+    )
+}
+
+/// ノードに属するすべてのstopからの出発を取得する (終点での到着は含まない)
+/// pagingを指定した場合はその範囲のみ
+/// 返却結果のソートは paging.sort, 出発時刻, trip_id を保証する
+pub fn select_node_departures(
+    conn: &mut Connection,
+    query: &NodeDepartureQuery,
+    paging: &Paging,
+) -> serde_rusqlite::Result<Vec<NodeDeparture>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  s.stop_id,
+  s.stop_name,
+  s.platform_code,
+  t.trip_id,
+  t.route_id,
+  t.service_id,
+  t.trip_headsign,
+  st.stop_headsign,
+  t2sr.service_route_id,
+  t2sr.service_route_direction_id,
+  st.departure_time
+{from}
+ORDER BY
+  {order}
+LIMIT :limit OFFSET :offset
+",
+            from = node_departures_from_sql(),
+            order = paging.order_by("st.departure_time_seconds, t.trip_id"),
+        )
+        .as_str(),
+    )?;
+
+    let all_services = query.service_ids.is_none();
+    let service_ids = service_ids_of(query);
+    let result = from_rows(stmt.query_named(named_params! {
+        ":node_id": query.node_id,
+        ":all_services": all_services,
+        ":service_ids": service_ids,
+        ":from_seconds": query.from_seconds,
+        ":agency_id": query.scope.agency_id,
+        ":office_id": query.scope.office_id,
+        ":limit": paging.sql_limit(),
+        ":offset": paging.offset,
+    })?)
+    .collect();
+    result
+}
+
+/// select_node_departuresで取得できる出発の件数 (ページングしない場合)
+pub fn count_node_departures(
+    conn: &mut Connection,
+    query: &NodeDepartureQuery,
+) -> rusqlite::Result<u32> {
+    let mut stmt =
+        conn.prepare(format!("SELECT count(*) {}", node_departures_from_sql()).as_str())?;
+    let all_services = query.service_ids.is_none();
+    let service_ids = service_ids_of(query);
+    stmt.query_row_named(
+        named_params! {
+            ":node_id": query.node_id,
+            ":all_services": all_services,
+            ":service_ids": service_ids,
+            ":from_seconds": query.from_seconds,
+            ":agency_id": query.scope.agency_id,
+            ":office_id": query.scope.office_id,
+        },
+        |row| row.get(0),
+    )
+}
+
+fn service_ids_of(query: &NodeDepartureQuery) -> Rc<Vec<Value>> {
+    Rc::new(
+        query
+            .service_ids
+            .iter()
+            .flatten()
+            .cloned()
+            .map(Value::from)
+            .collect_vec(),
+    )
+}
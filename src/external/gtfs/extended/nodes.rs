@@ -1,5 +1,12 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use strum_macros::{EnumString, EnumVariantNames};
 
+use crate::external::gtfs::extended::stop_details::StopDetail;
+use crate::external::gtfs::great_circle_distance;
+use crate::external::gtfs::stops::StopId;
 use crate::external::gtfsdb::Table;
 
 /// ノードID (ex: 1)
@@ -24,6 +31,10 @@ impl Table for Node {
         &["node_id", "node_name", "node_ruby"]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["node_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         node_id int,
@@ -33,3 +44,223 @@ impl Table for Node {
         "
     }
 }
+
+/// stopとノードの対応
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct Stop2Node {
+    /// 停留所・標柱ID
+    pub stop_id: StopId,
+    /// ノードID
+    pub node_id: NodeId,
+}
+
+impl Table for Stop2Node {
+    fn table_name() -> &'static str {
+        "stops2nodes"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["stop_id", "node_id"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["stop_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        stop_id text,
+        node_id int,
+        PRIMARY KEY(stop_id)
+        "
+    }
+}
+
+/// ノード対応ファイルの1行. 同じnode_keyのstopは1つのノードにまとめる
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct NodeMapping {
+    /// 停留所・標柱ID
+    pub stop_id: StopId,
+    /// ノードを識別する任意の文字列
+    pub node_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, EnumString, EnumVariantNames)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum NodeStrategy {
+    /// 親駅ごと (親駅のない標柱はそれぞれ別ノード)
+    ParentStation,
+    /// 親駅ごとに加え、一定距離以内にある同名の標柱・親駅をまとめる
+    SameName,
+    /// ノード対応ファイルの指定ごと (指定のないstopは親駅ごと)
+    Mapping,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+enum GroupKey {
+    /// 代表となる親駅・標柱のID
+    Stop(StopId),
+    /// ノード対応ファイルのnode_key
+    Mapped(String),
+}
+
+/// 親駅を辿った最上位のstop_id
+fn root_of<'a>(stop_id: &'a str, by_stop_id: &HashMap<&'a str, &'a StopDetail>) -> &'a str {
+    let mut current = stop_id;
+    // 親駅の循環参照で止まらないよう、辿る回数を制限する
+    for _ in 0..by_stop_id.len() {
+        match by_stop_id
+            .get(current)
+            .and_then(|x| x.parent_station.as_deref())
+            .filter(|x| by_stop_id.contains_key(x))
+        {
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+    current
+}
+
+/// ノードIDの元になる文字列のハッシュ (FNV-1a) を1以上のノードIDにする
+/// Rustのバージョンやプロセスによらず同じ値になるよう、標準のHasherは使わない
+fn hash_node_key(key: &str) -> NodeId {
+    let hash = key.bytes().fold(0x811c_9dc5u32, |hash, b| {
+        (hash ^ b as u32).wrapping_mul(0x0100_0193)
+    });
+    (hash % NodeId::MAX as u32) as NodeId + 1
+}
+
+/// 戦略に従ってstopをノードにまとめる
+/// ノードIDは代表stop (親駅を持たないstopを優先) のstop_id、またはノード対応ファイルのnode_keyのハッシュから求めるため、
+/// 他のstopの追加・削除やstopの並び順に依存しない
+pub fn generate_nodes(
+    stop_details: &[StopDetail],
+    strategy: &NodeStrategy,
+    distance: f64,
+    mappings: &[NodeMapping],
+) -> (Vec<Node>, Vec<Stop2Node>) {
+    let by_stop_id: HashMap<&str, &StopDetail> = stop_details
+        .iter()
+        .map(|x| (x.stop_id.as_str(), x))
+        .collect();
+    let root_by_stop_id: HashMap<&str, &str> = stop_details
+        .iter()
+        .map(|x| (x.stop_id.as_str(), root_of(&x.stop_id, &by_stop_id)))
+        .collect();
+
+    let mut key_by_root: HashMap<&str, GroupKey> = HashMap::new();
+    if let NodeStrategy::SameName = strategy {
+        let roots = stop_details
+            .iter()
+            .filter(|x| root_by_stop_id[x.stop_id.as_str()] == x.stop_id)
+            .sorted_by(|a, b| a.stop_id.cmp(&b.stop_id))
+            .collect_vec();
+        // 一定距離以内の同名stopを辿ってつながるものを同じクラスタにする (union-find)
+        let mut parents = (0..roots.len()).collect_vec();
+        fn find(parents: &mut [usize], i: usize) -> usize {
+            let mut root = i;
+            while parents[root] != root {
+                root = parents[root];
+            }
+            let mut current = i;
+            while parents[current] != root {
+                let next = parents[current];
+                parents[current] = root;
+                current = next;
+            }
+            root
+        }
+        let position = |x: &StopDetail| (x.stop_lat, x.stop_lon);
+        for (_, same_names) in &roots
+            .iter()
+            .enumerate()
+            .sorted_by(|(_, a), (_, b)| a.stop_name.cmp(&b.stop_name))
+            .group_by(|(_, x)| x.stop_name.as_str())
+        {
+            let same_names = same_names.collect_vec();
+            for (i, (a, x)) in same_names.iter().enumerate() {
+                for (b, y) in &same_names[i + 1..] {
+                    if great_circle_distance(position(x), position(y)) <= distance {
+                        let (ra, rb) = (find(&mut parents, *a), find(&mut parents, *b));
+                        // stop_idの小さい方を代表とする
+                        parents[ra.max(rb)] = ra.min(rb);
+                    }
+                }
+            }
+        }
+        for i in 0..roots.len() {
+            let representative = roots[find(&mut parents, i)];
+            key_by_root.insert(
+                &roots[i].stop_id,
+                GroupKey::Stop(representative.stop_id.clone()),
+            );
+        }
+    }
+    let key_by_mapping: HashMap<&str, &str> = match strategy {
+        NodeStrategy::Mapping => mappings
+            .iter()
+            .map(|x| (x.stop_id.as_str(), x.node_key.as_str()))
+            .collect(),
+        _ => HashMap::new(),
+    };
+
+    let groups = stop_details
+        .iter()
+        .map(|x| {
+            let root = root_by_stop_id[x.stop_id.as_str()];
+            let key = key_by_mapping
+                .get(x.stop_id.as_str())
+                .or_else(|| key_by_mapping.get(root))
+                .map(|k| GroupKey::Mapped(k.to_string()))
+                .or_else(|| key_by_root.get(root).cloned())
+                .unwrap_or_else(|| GroupKey::Stop(root.to_string()));
+            (key, x)
+        })
+        .into_group_map();
+
+    // 各グループは親駅を持たないstopを優先してstop_id順に並べ、先頭を代表とする
+    let groups = groups
+        .into_iter()
+        .map(|(key, members)| {
+            let members = members
+                .into_iter()
+                .sorted_by(|a, b| {
+                    a.parent_station
+                        .is_some()
+                        .cmp(&b.parent_station.is_some())
+                        .then(a.stop_id.cmp(&b.stop_id))
+                })
+                .collect_vec();
+            let id_key = match key {
+                GroupKey::Mapped(node_key) => format!("node_key:{}", node_key),
+                GroupKey::Stop(_) => format!("stop_id:{}", members[0].stop_id),
+            };
+            (id_key, members)
+        })
+        .sorted_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut nodes = vec![];
+    let mut stops2nodes = vec![];
+    let mut used = HashSet::new();
+    for (id_key, members) in groups {
+        // ハッシュが衝突した場合は空いているIDまでずらす
+        let mut node_id = hash_node_key(&id_key);
+        while !used.insert(node_id) {
+            node_id = node_id % NodeId::MAX + 1;
+        }
+        nodes.push(Node {
+            node_id,
+            node_name: members[0].stop_name.clone(),
+            node_ruby: members[0].stop_ruby.clone(),
+        });
+        stops2nodes.extend(members.into_iter().map(|x| Stop2Node {
+            stop_id: x.stop_id.clone(),
+            node_id,
+        }));
+    }
+    nodes.sort_by_key(|x| x.node_id);
+    stops2nodes.sort_by(|a, b| a.stop_id.cmp(&b.stop_id));
+
+    (nodes, stops2nodes)
+}
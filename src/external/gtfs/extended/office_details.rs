@@ -0,0 +1,78 @@
+use rusqlite::{named_params, Connection, NO_PARAMS};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::from_rows;
+
+use crate::external::gtfs::office_jp::{JpOfficeId, OfficeJp};
+use crate::external::gtfs::trips::Trip;
+use crate::external::gtfs::{Paging, SortColumns, TelephoneNumber, Url};
+use crate::external::gtfsdb::Table;
+
+/// 営業所と、その営業所が担当するrouteとtripの数
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct OfficeDetail {
+    /// 営業所ID
+    pub office_id: JpOfficeId,
+    /// 営業所名 (ex: 深川営業所)
+    pub office_name: String,
+    /// 営業所URL
+    pub office_url: Option<Url>,
+    /// 営業所電話番号
+    pub office_phone: Option<TelephoneNumber>,
+    /// route数 (担当するtripが1つでもあるroute)
+    pub route_count: u32,
+    /// trip数
+    pub trip_count: u32,
+}
+
+/// select_office_detailsで並び替えられる項目
+pub const OFFICE_DETAIL_SORT_COLUMNS: SortColumns = &[
+    ("office_id", "o.office_id"),
+    ("office_name", "o.office_name"),
+    ("route_count", "route_count"),
+    ("trip_count", "trip_count"),
+];
+
+/// 営業所の詳細をpagingの範囲だけ取得する
+pub fn select_office_details(
+    conn: &mut Connection,
+    paging: &Paging,
+) -> serde_rusqlite::Result<Vec<OfficeDetail>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  o.office_id,
+  o.office_name,
+  o.office_url,
+  o.office_phone,
+  (SELECT count(DISTINCT route_id) FROM {trips} WHERE jp_office_id == o.office_id) AS route_count,
+  (SELECT count(*) FROM {trips} WHERE jp_office_id == o.office_id) AS trip_count
+FROM
+  {office_jp} o
+ORDER BY
+  {order}
+LIMIT :limit OFFSET :offset
+",
+            office_jp = OfficeJp::table_name(),
+            trips = Trip::table_name(),
+            order = paging.order_by("o.office_id"),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":limit": paging.sql_limit(),
+        ":offset": paging.offset,
+    })?)
+    .collect();
+    result
+}
+
+/// select_office_detailsでページングしない場合の件数
+pub fn count_office_details(conn: &mut Connection) -> rusqlite::Result<u32> {
+    conn.query_row(
+        format!("SELECT count(*) FROM {}", OfficeJp::table_name()).as_str(),
+        NO_PARAMS,
+        |row| row.get(0),
+    )
+}
@@ -0,0 +1,152 @@
+use std::rc::Rc;
+
+use itertools::Itertools;
+use rusqlite::types::{ToSql, Value};
+use rusqlite::Connection;
+
+/// カラム名 => 値 (SQLiteの型をそのままJSONの型にしたもの)
+pub type Record = serde_json::Map<String, serde_json::Value>;
+
+/// レコードの絞込条件
+#[derive(Debug, Clone)]
+pub enum Condition {
+    /// カラムの値がいずれかに一致する
+    In(&'static str, Vec<Value>),
+    /// いずれかのカラムが部分一致する
+    Like(&'static [&'static str], String),
+    /// カラムの値が、別のテーブルで条件に一致するレコードのカラムの値のいずれかに一致する
+    /// (ex: stop_timesのtrip_idが、service_idが一致するtripsのtrip_idに含まれる)
+    InSelect {
+        column: &'static str,
+        table: &'static str,
+        select: &'static str,
+        condition: Box<Condition>,
+    },
+}
+
+impl Condition {
+    fn to_sql(&self, params: &mut Vec<Box<dyn ToSql>>) -> String {
+        match self {
+            Condition::In(column, values) => {
+                params.push(Box::new(Rc::new(values.clone())));
+                format!("{} IN rarray(?)", column)
+            }
+            Condition::Like(columns, word) => {
+                let sql = columns
+                    .iter()
+                    .map(|x| {
+                        params.push(Box::new(format!("%{}%", word)));
+                        format!("{} LIKE ?", x)
+                    })
+                    .join(" OR ");
+                format!("({})", sql)
+            }
+            Condition::InSelect {
+                column,
+                table,
+                select,
+                condition,
+            } => format!(
+                "{} IN (SELECT {} FROM {} WHERE {})",
+                column,
+                select,
+                table,
+                condition.to_sql(params)
+            ),
+        }
+    }
+}
+
+/// テーブル・カラム名はプログラムで定義したもののみ指定する (利用者の入力は値としてのみ扱う)
+#[derive(Debug, Clone)]
+pub struct RecordQuery {
+    pub table: &'static str,
+    pub conditions: Vec<Condition>,
+    /// 並び順のSQL式
+    pub order_by: &'static [&'static str],
+    /// 指定した場合、limitとoffsetはこのカラムの値の組ごとに適用する
+    pub partition_by: Vec<&'static str>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// partition_byを指定した場合に、組ごとの順位を入れるカラム (結果には含めない)
+const ROW_NUMBER_COLUMN: &str = "__row_number";
+
+/// 条件に一致するレコードをすべてのカラムについて取得する
+pub fn select_records(conn: &mut Connection, query: &RecordQuery) -> rusqlite::Result<Vec<Record>> {
+    let mut params: Vec<Box<dyn ToSql>> = vec![];
+    let order_by = if query.order_by.is_empty() {
+        String::new()
+    } else {
+        format!(" ORDER BY {}", query.order_by.join(", "))
+    };
+    let mut sql = if query.partition_by.is_empty() {
+        format!("SELECT * FROM {}", query.table)
+    } else {
+        format!(
+            "SELECT *, ROW_NUMBER() OVER (PARTITION BY {}{}) AS {} FROM {}",
+            query.partition_by.join(", "),
+            order_by,
+            ROW_NUMBER_COLUMN,
+            query.table
+        )
+    };
+    if !query.conditions.is_empty() {
+        sql += &format!(
+            " WHERE {}",
+            query
+                .conditions
+                .iter()
+                .map(|x| x.to_sql(&mut params))
+                .join(" AND ")
+        );
+    }
+    if !query.partition_by.is_empty() {
+        let offset = query.offset.unwrap_or(0);
+        params.push(Box::new(offset));
+        sql = format!("SELECT * FROM ({}) WHERE {} > ?", sql, ROW_NUMBER_COLUMN);
+        if let Some(limit) = query.limit {
+            params.push(Box::new(i64::from(offset) + i64::from(limit)));
+            sql += &format!(" AND {} <= ?", ROW_NUMBER_COLUMN);
+        }
+        sql += &order_by;
+    } else if !order_by.is_empty() {
+        sql += &order_by;
+    }
+    // SQLiteはOFFSETのみを指定できないため、LIMIT -1 (上限なし) とする
+    if query.partition_by.is_empty() && (query.limit.is_some() || query.offset.is_some()) {
+        params.push(Box::new(query.limit.map(i64::from).unwrap_or(-1)));
+        params.push(Box::new(query.offset.unwrap_or(0)));
+        sql += " LIMIT ? OFFSET ?";
+    }
+
+    let mut stmt = conn.prepare(&sql)?;
+    let names = stmt
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect_vec();
+    let mut rows = stmt.query(params.iter().map(|x| x.as_ref()))?;
+    let mut records = vec![];
+    while let Some(row) = rows.next()? {
+        let mut record = Record::new();
+        for (i, name) in names.iter().enumerate() {
+            if name != ROW_NUMBER_COLUMN {
+                record.insert(name.clone(), to_json(row.get::<_, Value>(i)?));
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn to_json(value: Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(x) => x.into(),
+        Value::Real(x) => x.into(),
+        Value::Text(x) => x.into(),
+        Value::Blob(_) => serde_json::Value::Null,
+    }
+}
@@ -0,0 +1,297 @@
+use std::rc::Rc;
+
+use itertools::Itertools;
+use rusqlite::{named_params, types::Value, Connection, ToSql};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::from_rows;
+
+use crate::external::gtfs::agency::{Agency, AgencyId};
+use crate::external::gtfs::extended::service_routes::ServiceRoute;
+use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
+use crate::external::gtfs::routes::{Route, RouteId, RouteType};
+use crate::external::gtfs::routes_jp::RouteJp;
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::trips::Trip;
+use crate::external::gtfs::{Color, OperatorScope, OptionalDateString, Paging, SortColumns, Url};
+use crate::external::gtfsdb::Table;
+
+/// routeと、その事業者名・routes_jpの情報、停車するstop・サービスルート・tripの数
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct RouteDetail {
+    /// 経路ID
+    pub route_id: RouteId,
+    /// 事業者ID
+    pub agency_id: AgencyId,
+    /// 事業者名称 (ex: 都営バス)
+    pub agency_name: Option<String>,
+    /// 経路略称 (ex: 東16)
+    pub route_short_name: Option<String>,
+    /// 経路名 (ex: 東京駅八重洲口～月島駅前～東京ビ ッグサイト)
+    pub route_long_name: Option<String>,
+    /// 経路情報
+    pub route_desc: Option<String>,
+    /// 経路タイプ
+    pub route_type: RouteType,
+    /// 経路URL
+    pub route_url: Option<Url>,
+    /// 経路色 (ex: FFD700)
+    pub route_color: Option<Color>,
+    /// 経路文字色 (ex: 000000)
+    pub route_text_color: Option<Color>,
+    /// 路線ID
+    pub jp_parent_route_id: Option<String>,
+    /// ダイヤ改正日
+    pub route_update_date: OptionalDateString,
+    /// 起点 (ex: 東京駅八重洲口)
+    pub origin_stop: Option<String>,
+    /// 経過地 (ex: 月島駅)
+    pub via_stop: Option<String>,
+    /// 終点 (ex: 東京ビッグサイト)
+    pub destination_stop: Option<String>,
+    /// 停車するstop数
+    pub stop_count: u32,
+    /// サービスルート数 (上下別)
+    pub service_route_count: u32,
+    /// trip数
+    pub trip_count: u32,
+}
+
+/// select_route_detailsで並び替えられる項目
+pub const ROUTE_DETAIL_SORT_COLUMNS: SortColumns = &[
+    ("route_id", "r.route_id"),
+    ("agency_id", "r.agency_id"),
+    ("agency_name", "a.agency_name"),
+    ("route_short_name", "r.route_short_name"),
+    ("route_long_name", "r.route_long_name"),
+    ("route_type", "r.route_type"),
+    ("jp_parent_route_id", "r.jp_parent_route_id"),
+    ("route_update_date", "rj.route_update_date"),
+    ("origin_stop", "rj.origin_stop"),
+    ("via_stop", "rj.via_stop"),
+    ("destination_stop", "rj.destination_stop"),
+    ("stop_count", "stop_count"),
+    ("service_route_count", "service_route_count"),
+    ("trip_count", "trip_count"),
+];
+
+/// select_route_detailsとcount_route_detailsで共通のFROM句とWHERE句
+fn route_details_from_sql() -> String {
+    format!(
+        "
+FROM
+  {routes} r
+    LEFT JOIN {agency} a
+    ON r.agency_id == a.agency_id
+    LEFT JOIN {routes_jp} rj
+    ON r.route_id == rj.route_id
+WHERE
+  (:route_id IS NULL OR r.route_id == :route_id)
+  AND (:word IS NULL OR r.route_short_name LIKE :word OR r.route_long_name LIKE :word)
+  AND (:all_route_types OR r.route_type IN rarray(:route_types))
+  AND (:agency_id IS NULL OR r.agency_id == :agency_id)
+  AND (:office_id IS NULL OR
+       r.route_id IN (SELECT route_id FROM {trips} WHERE jp_office_id == :office_id))
+",
+        routes = Route::table_name(),
+        agency = Agency::table_name(),
+        routes_jp = RouteJp::table_name(),
+        trips = Trip::table_name(),
+    )
+}
+
+/// route_details_from_sqlに渡すパラメータでfを呼び出す
+fn with_route_detail_params<F, T>(
+    route_id: Option<RouteId>,
+    word: Option<String>,
+    route_types: Option<Vec<RouteType>>,
+    scope: &OperatorScope,
+    f: F,
+) -> T
+where
+    F: FnOnce(&[(&str, &dyn ToSql)]) -> T,
+{
+    let all_route_types = route_types.is_none();
+    let route_types = Rc::new(
+        route_types
+            .iter()
+            .flatten()
+            .map(|x| Value::from(x.code() as i64))
+            .collect_vec(),
+    );
+    f(named_params! {
+        ":route_id": route_id,
+        ":word": word.map(|x| format!("%{}%", x)),
+        ":all_route_types": all_route_types,
+        ":route_types": route_types,
+        ":agency_id": scope.agency_id,
+        ":office_id": scope.office_id,
+    })
+}
+
+/// routeの詳細を検索する
+/// route_idを指定した場合はそのrouteのみ、wordを指定した場合はroute_short_nameまたはroute_long_nameに含むrouteのみ、
+/// route_typesを指定した場合はいずれかの経路タイプのrouteのみ、
+/// scopeを指定した場合はその事業者・営業所のrouteのみ (営業所はそのrouteに属するtripの営業所で判定する)
+/// 各数は絞り込んだrouteごとに数える. pagingの範囲だけを返す
+pub fn select_route_details(
+    conn: &mut Connection,
+    route_id: Option<RouteId>,
+    word: Option<String>,
+    route_types: Option<Vec<RouteType>>,
+    scope: &OperatorScope,
+    paging: &Paging,
+) -> serde_rusqlite::Result<Vec<RouteDetail>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  r.route_id,
+  r.agency_id,
+  a.agency_name,
+  r.route_short_name,
+  r.route_long_name,
+  r.route_desc,
+  r.route_type,
+  r.route_url,
+  r.route_color,
+  r.route_text_color,
+  r.jp_parent_route_id,
+  rj.route_update_date,
+  rj.origin_stop,
+  rj.via_stop,
+  rj.destination_stop,
+  (
+    SELECT count(DISTINCT st.stop_id)
+    FROM
+      {trips} t
+        INNER JOIN {stop_times} st
+        ON t.trip_id == st.trip_id
+    WHERE t.route_id == r.route_id
+  ) AS stop_count,
+  (
+    SELECT count(*)
+    FROM (
+      SELECT DISTINCT t2sr.service_route_id, t2sr.service_route_direction_id
+      FROM
+        {trips} t
+          INNER JOIN {trips2service_routes} t2sr
+          ON t.trip_id == t2sr.trip_id
+      WHERE t.route_id == r.route_id
+    )
+  ) AS service_route_count,
+  (
+    SELECT count(*)
+    FROM {trips} t
+    WHERE t.route_id == r.route_id
+  ) AS trip_count
+{from}
+ORDER BY
+  {order}
+LIMIT :limit OFFSET :offset
+",
+            from = route_details_from_sql(),
+            order = paging.order_by("r.route_id"),
+            trips = Trip::table_name(),
+            stop_times = StopTime::table_name(),
+            trips2service_routes = Trip2ServiceRoute::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let (limit, offset) = (paging.sql_limit(), paging.offset);
+    with_route_detail_params(route_id, word, route_types, scope, |params| {
+        let mut params = params.to_vec();
+        params.push((":limit", &limit));
+        params.push((":offset", &offset));
+        from_rows(stmt.query_named(&params)?).collect()
+    })
+}
+
+/// select_route_detailsでページングしない場合の件数
+pub fn count_route_details(
+    conn: &mut Connection,
+    word: Option<String>,
+    route_types: Option<Vec<RouteType>>,
+    scope: &OperatorScope,
+) -> rusqlite::Result<u32> {
+    let mut stmt =
+        conn.prepare(format!("SELECT count(*) {}", route_details_from_sql()).as_str())?;
+    with_route_detail_params(None, word, route_types, scope, |params| {
+        stmt.query_row_named(params, |row| row.get(0))
+    })
+}
+
+/// routeのtripが停車するstopを取得する
+pub fn select_route_stops(
+    conn: &mut Connection,
+    route_id: RouteId,
+) -> serde_rusqlite::Result<Vec<Stop>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  s.*
+FROM
+  {stops} s
+WHERE
+  s.stop_id IN (
+    SELECT st.stop_id
+    FROM
+      {trips} t
+        INNER JOIN {stop_times} st
+        ON t.trip_id == st.trip_id
+    WHERE t.route_id == :route_id
+  )
+ORDER BY
+  s.stop_id
+",
+            stops = Stop::table_name(),
+            trips = Trip::table_name(),
+            stop_times = StopTime::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {":route_id": route_id})?).collect();
+    result
+}
+
+/// routeのtripが属するサービスルートを取得する
+pub fn select_route_service_routes(
+    conn: &mut Connection,
+    route_id: RouteId,
+) -> serde_rusqlite::Result<Vec<ServiceRoute>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  sr.service_route_id,
+  sr.service_route_name,
+  sr.direction_id
+FROM
+  {service_routes} sr
+WHERE
+  EXISTS (
+    SELECT 1
+    FROM
+      {trips2service_routes} t2sr
+        INNER JOIN {trips} t
+        ON t2sr.trip_id == t.trip_id
+    WHERE t2sr.service_route_id == sr.service_route_id
+      AND t2sr.service_route_direction_id == sr.direction_id
+      AND t.route_id == :route_id
+  )
+ORDER BY
+  sr.service_route_id, sr.direction_id
+",
+            service_routes = ServiceRoute::table_name(),
+            trips2service_routes = Trip2ServiceRoute::table_name(),
+            trips = Trip::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {":route_id": route_id})?).collect();
+    result
+}
@@ -0,0 +1,221 @@
+use rusqlite::{named_params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::from_rows;
+
+use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::extended::service_routes::{ServiceRoute, ServiceRouteId};
+use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trips::{Trip, TripId};
+use crate::external::gtfs::{DirectionId, GtfsTime, Paging, Second, SortColumns};
+use crate::external::gtfsdb::Table;
+
+/// サービスルートに属するtripの集計
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct ServiceRouteSummary {
+    /// サービスルートID
+    pub service_route_id: ServiceRouteId,
+    /// 上下区分
+    pub direction_id: DirectionId,
+    /// サービスルート名
+    pub service_route_name: String,
+    /// 停車順の代表とするtrip (正規パターンとなる停車数が最多のtripのうち、trip_idが最小のもの)
+    pub representative_trip_id: TripId,
+    /// trip数
+    pub trip_count: u32,
+    /// 始発の出発時刻 (秒)
+    pub first_departure_seconds: Second,
+    /// 最終の出発時刻 (秒)
+    pub last_departure_seconds: Second,
+}
+
+/// サービスルートに属するtrip
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct ServiceRouteTrip {
+    /// 便ID
+    pub trip_id: TripId,
+    /// 経路ID
+    pub route_id: RouteId,
+    /// 運行日ID
+    pub service_id: ServiceId,
+    /// 便行き先 (ex: 東京ビッグサイト（月島駅経由）)
+    pub trip_headsign: Option<String>,
+    /// 始点の出発時刻
+    pub departure_time: GtfsTime,
+    /// 終点の到着時刻
+    pub arrival_time: GtfsTime,
+}
+
+/// select_service_route_summariesで並び替えられる項目
+pub const SERVICE_ROUTE_SORT_COLUMNS: SortColumns = &[
+    ("service_route_id", "sr.service_route_id"),
+    ("direction_id", "sr.direction_id"),
+    ("service_route_name", "sr.service_route_name"),
+    ("trip_count", "trip_count"),
+    ("first_departure_time", "first_departure_seconds"),
+    ("last_departure_time", "last_departure_seconds"),
+];
+
+/// select_service_route_summariesとcount_service_route_summariesで共通のFROM句からGROUP BY句まで
+fn service_route_summaries_from_sql() -> String {
+    format!(
+        "
+FROM
+  {service_routes} sr
+    INNER JOIN {trips2service_routes} t2sr
+    ON sr.service_route_id == t2sr.service_route_id
+      AND sr.direction_id == t2sr.service_route_direction_id
+    INNER JOIN {trips} t
+    ON t2sr.trip_id == t.trip_id
+    INNER JOIN {stop_times} fst
+    ON t2sr.trip_id == fst.trip_id
+      AND fst.stop_sequence == (SELECT min(stop_sequence) FROM {stop_times} WHERE trip_id == t2sr.trip_id)
+WHERE
+  (:service_route_id IS NULL OR sr.service_route_id == :service_route_id)
+  AND (:route_id IS NULL OR t.route_id == :route_id)
+  AND (:direction_id IS NULL OR sr.direction_id == :direction_id)
+  AND (:stop_id IS NULL OR EXISTS (
+    SELECT 1
+    FROM {trips2service_routes} x INNER JOIN {stop_times} xst ON x.trip_id == xst.trip_id
+    WHERE x.service_route_id == sr.service_route_id
+      AND x.service_route_direction_id == sr.direction_id
+      AND xst.stop_id == :stop_id
+  ))
+GROUP BY
+  sr.service_route_id, sr.direction_id
+",
+        service_routes = ServiceRoute::table_name(),
+        trips2service_routes = Trip2ServiceRoute::table_name(),
+        trips = Trip::table_name(),
+        stop_times = StopTime::table_name(),
+    )
+}
+
+/// サービスルートごとにtripを集計する
+/// service_route_idはそのサービスルートのみ、route_idはそのrouteのtripのみ、
+/// stop_idはそのstopを通るtripを含むサービスルートのみに絞り込む. pagingの範囲だけを返す
+pub fn select_service_route_summaries(
+    conn: &mut Connection,
+    service_route_id: Option<ServiceRouteId>,
+    route_id: Option<RouteId>,
+    stop_id: Option<StopId>,
+    direction_id: Option<DirectionId>,
+    paging: &Paging,
+) -> serde_rusqlite::Result<Vec<ServiceRouteSummary>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  sr.service_route_id,
+  sr.direction_id,
+  sr.service_route_name,
+  (
+    SELECT x.trip_id
+    FROM {trips2service_routes} x
+    WHERE x.service_route_id == sr.service_route_id
+      AND x.service_route_direction_id == sr.direction_id
+    ORDER BY (SELECT count(*) FROM {stop_times} WHERE trip_id == x.trip_id) DESC, x.trip_id
+    LIMIT 1
+  ) AS representative_trip_id,
+  count(*) AS trip_count,
+  min(fst.departure_time_seconds) AS first_departure_seconds,
+  max(fst.departure_time_seconds) AS last_departure_seconds
+{from}
+ORDER BY
+  {order}
+LIMIT :limit OFFSET :offset
+",
+            trips2service_routes = Trip2ServiceRoute::table_name(),
+            stop_times = StopTime::table_name(),
+            from = service_route_summaries_from_sql(),
+            order = paging.order_by("sr.service_route_id, sr.direction_id"),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":service_route_id": service_route_id,
+        ":route_id": route_id,
+        ":stop_id": stop_id,
+        ":direction_id": direction_id.map(|x| x as u8),
+        ":limit": paging.sql_limit(),
+        ":offset": paging.offset,
+    })?)
+    .collect();
+    result
+}
+
+/// select_service_route_summariesでページングしない場合の件数
+pub fn count_service_route_summaries(
+    conn: &mut Connection,
+    route_id: Option<RouteId>,
+    stop_id: Option<StopId>,
+    direction_id: Option<DirectionId>,
+) -> rusqlite::Result<u32> {
+    let mut stmt = conn.prepare(
+        format!(
+            "SELECT count(*) FROM (SELECT 1 {})",
+            service_route_summaries_from_sql()
+        )
+        .as_str(),
+    )?;
+    stmt.query_row_named(
+        named_params! {
+            ":service_route_id": None::<ServiceRouteId>,
+            ":route_id": route_id,
+            ":stop_id": stop_id,
+            ":direction_id": direction_id.map(|x| x as u8),
+        },
+        |row| row.get(0),
+    )
+}
+
+/// サービスルートに属するtripを取得する
+/// 返却結果のソートは 出発時刻, trip_id を保証する
+pub fn select_service_route_trips(
+    conn: &mut Connection,
+    service_route_id: ServiceRouteId,
+    direction_id: DirectionId,
+) -> serde_rusqlite::Result<Vec<ServiceRouteTrip>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  t.trip_id,
+  t.route_id,
+  t.service_id,
+  t.trip_headsign,
+  fst.departure_time,
+  lst.arrival_time
+FROM
+  {trips2service_routes} t2sr
+    INNER JOIN {trips} t
+    ON t2sr.trip_id == t.trip_id
+    INNER JOIN {stop_times} fst
+    ON t.trip_id == fst.trip_id
+      AND fst.stop_sequence == (SELECT min(stop_sequence) FROM {stop_times} WHERE trip_id == t.trip_id)
+    INNER JOIN {stop_times} lst
+    ON t.trip_id == lst.trip_id
+      AND lst.stop_sequence == (SELECT max(stop_sequence) FROM {stop_times} WHERE trip_id == t.trip_id)
+WHERE
+  t2sr.service_route_id == :service_route_id
+  AND t2sr.service_route_direction_id == :direction_id
+ORDER BY
+  fst.departure_time_seconds, t.trip_id
+",
+            trips2service_routes = Trip2ServiceRoute::table_name(),
+            trips = Trip::table_name(),
+            stop_times = StopTime::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":service_route_id": service_route_id,
+        ":direction_id": direction_id as u8,
+    })?)
+    .collect();
+    result
+}
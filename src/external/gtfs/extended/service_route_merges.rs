@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+use crate::external::gtfs::extended::service_routes::ServiceRouteId;
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::DirectionId;
+use crate::external::gtfsdb::Table;
+
+/// 停車パターンが異なるtripを同じサービスルートにまとめた理由
+#[derive(Debug, Deserialize, Serialize, Display, Eq, PartialEq, Clone, Hash)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum MergeReason {
+    /// 停留所名の並びが同じ
+    SameStopNames,
+    /// 経路・上下区分・停留所名の並びが同じ
+    SameRouteAndStopNames,
+    /// 親駅の並びが同じ (のりばのみ異なる)
+    SameStations,
+    /// ノードの並びが同じ (同じノードの標柱のみ異なる)
+    SameNodes,
+    /// 描画IDが同じ
+    SameShape,
+    /// 正規パターンの部分列である (停留所の通過)
+    Subsequence,
+    /// service_route識別ファイルで指定された
+    IdentityFile,
+}
+
+/// 代表tripと停車パターン(stop_ids)が異なるのに同じサービスルートへまとめたtrip
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct ServiceRouteMerge {
+    /// 便ID
+    pub trip_id: TripId,
+    /// サービスルートID
+    pub service_route_id: ServiceRouteId,
+    /// サービスルートの上下区分
+    pub service_route_direction_id: DirectionId,
+    /// tripの標柱IDのカンマ区切り
+    pub stop_ids: String,
+    /// サービスルートの代表tripの標柱IDのカンマ区切り
+    pub base_stop_ids: String,
+    /// まとめた理由
+    pub reason: MergeReason,
+}
+
+impl Table for ServiceRouteMerge {
+    fn table_name() -> &'static str {
+        "service_route_merges"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &[
+            "trip_id",
+            "service_route_id",
+            "service_route_direction_id",
+            "stop_ids",
+            "base_stop_ids",
+            "reason",
+        ]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["trip_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        trip_id text primary key,
+        service_route_id int not null,
+        service_route_direction_id int not null,
+        stop_ids text not null,
+        base_stop_ids text not null,
+        reason text not null
+        "
+    }
+}
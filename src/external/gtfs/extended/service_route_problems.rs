@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+use thiserror::Error;
+
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfsdb::Table;
+
+/// データベース作成時に見つかったtripの問題の種類
+#[derive(Debug, Deserialize, Serialize, Display, Eq, PartialEq, Clone, Hash)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ProblemKind {
+    /// tripにstop_timesが1つもない
+    NoStopTimes,
+    /// routeにroute_short_nameとroute_long_nameのどちらもない
+    MissingRouteName,
+    /// 停車パターンを識別できない
+    Unidentifiable,
+    /// 始点または終点に時刻がなく、通過時刻を補間できない
+    Uninterpolatable,
+}
+
+/// データベース作成時に見つかったtripの問題
+/// lenientモードでは問題のあるtripを除外し、このテーブルに隔離する
+#[derive(Debug, Deserialize, Serialize, Error, Eq, PartialEq, Clone, Hash)]
+#[error("[{kind}] trip_id={trip_id}: {message}")]
+pub struct ServiceRouteProblem {
+    /// 便ID
+    pub trip_id: TripId,
+    /// 問題の種類
+    pub kind: ProblemKind,
+    /// 問題の詳細
+    pub message: String,
+}
+
+impl ServiceRouteProblem {
+    pub fn new(trip_id: &str, kind: ProblemKind, message: impl Into<String>) -> Self {
+        Self {
+            trip_id: trip_id.to_string(),
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl Table for ServiceRouteProblem {
+    fn table_name() -> &'static str {
+        "service_route_problems"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["trip_id", "kind", "message"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["trip_id", "kind"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        trip_id text not null,
+        kind text not null,
+        message text not null,
+        PRIMARY KEY(trip_id, kind)
+        "
+    }
+}
@@ -1,12 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::{Context, Result};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use strum_macros::{EnumString, EnumVariantNames};
 
+use crate::external::gtfs::extended::nodes::{NodeId, Stop2Node};
 use crate::external::gtfs::extended::service_route_identity::ServiceRouteIdentity;
+use crate::external::gtfs::extended::service_route_merges::{MergeReason, ServiceRouteMerge};
+use crate::external::gtfs::extended::service_route_problems::{ProblemKind, ServiceRouteProblem};
 use crate::external::gtfs::extended::stop_time_details::StopTimeDetail;
+use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trips::TripId;
 use crate::external::gtfs::DirectionId;
 use crate::external::gtfsdb::Table;
 
@@ -44,6 +51,10 @@ impl Table for ServiceRoute {
         &["service_route_id", "service_route_name", "direction_id"]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["service_route_id", "direction_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         service_route_id int,
@@ -57,116 +68,368 @@ impl Table for ServiceRoute {
 /// 同一性
 type Identifier = String;
 
-#[derive(Debug, Clone, EnumString, EnumVariantNames)]
+#[derive(Debug, Clone, Deserialize, Serialize, EnumString, EnumVariantNames)]
+#[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum IdentifyStrategy {
+    /// 標柱IDの並び
     StopIds,
+    /// 停留所名の並び
     StopNames,
+    /// 経路ID・上下区分・停留所名の並び
+    RouteStopNames,
+    /// 親駅(なければ標柱)IDの並び
+    Stations,
+    /// ノード(なければ標柱)IDの並び
+    Nodes,
+    /// 描画ID (描画IDのないtripは標柱IDの並び)
+    ShapeId,
+    /// 経路ID・上下区分ごとに、他の停車パターンの部分列となるパターンを正規パターンにまとめる
+    Subsequence,
+}
+
+impl IdentifyStrategy {
+    /// Identityファイルからidentifierを求める
+    /// Identityファイルの情報だけでは求められない戦略ではNone (trip_idで対応付ける)
+    pub fn identifier_of(&self, identity: &ServiceRouteIdentity) -> Option<Identifier> {
+        match self {
+            IdentifyStrategy::StopIds => Some(identity.stop_ids.clone()),
+            IdentifyStrategy::StopNames => Some(identity.stop_names.clone()),
+            _ => None,
+        }
+    }
+
+    /// 代表tripと停車パターンが異なるtripをまとめた理由
+    fn merge_reason(&self) -> MergeReason {
+        match self {
+            IdentifyStrategy::StopIds | IdentifyStrategy::StopNames => MergeReason::SameStopNames,
+            IdentifyStrategy::RouteStopNames => MergeReason::SameRouteAndStopNames,
+            IdentifyStrategy::Stations => MergeReason::SameStations,
+            IdentifyStrategy::Nodes => MergeReason::SameNodes,
+            IdentifyStrategy::ShapeId => MergeReason::SameShape,
+            IdentifyStrategy::Subsequence => MergeReason::Subsequence,
+        }
+    }
+}
+
+fn stop_ids_of(stop_time_details: &[StopTimeDetail]) -> String {
+    stop_time_details.iter().map(|x| &x.stop_id).join(",")
+}
+
+/// 経路ID・上下区分と停留所名の並び
+fn route_stops_of(stop_time_details: &[StopTimeDetail]) -> Result<((RouteId, u8), Vec<String>)> {
+    let first = stop_time_details
+        .first()
+        .context("stop_time_detailsが存在しません")?;
+    Ok((
+        (
+            first.route_id.clone(),
+            first.direction_id.clone().unwrap_or(DirectionId::Outbound) as u8,
+        ),
+        stop_time_details
+            .iter()
+            .map(|x| x.stop_name.clone())
+            .collect_vec(),
+    ))
+}
+
+/// 停留所名に区切り文字が含まれても衝突しないよう、JSONの配列をidentifierとする
+fn route_stop_names_of(stop_time_details: &[StopTimeDetail]) -> Result<Identifier> {
+    let ((route_id, direction_id), stop_names) = route_stops_of(stop_time_details)?;
+    Ok(serde_json::json!([route_id, direction_id, stop_names]).to_string())
+}
+
+/// subがsupの部分列であるか (順序を保ったまま要素を間引いたものか)
+fn is_subsequence(sub: &[String], sup: &[String]) -> bool {
+    let mut sup_iter = sup.iter();
+    sub.iter().all(|x| sup_iter.any(|y| y == x))
 }
 
 pub struct ServiceRouteGenerator {
     service_route_id: ServiceRouteId,
     pub service_route_by_identify: HashMap<Identifier, ServiceRoute>,
     identify_strategy: IdentifyStrategy,
+    /// service_route識別ファイルで指定されたtripのサービスルート
+    service_route_by_trip_id: HashMap<TripId, ServiceRoute>,
+    /// service_route識別ファイルから求めたidentifier
+    identity_identifiers: HashSet<Identifier>,
+    /// Subsequence戦略で、パターンごとにまとめる先の正規パターン
+    canonical_by_pattern: HashMap<Identifier, Identifier>,
+    /// Nodes戦略で使う標柱IDごとのノードID
+    node_id_by_stop_id: HashMap<StopId, NodeId>,
+    /// サービスルートごとの代表tripの標柱IDの並び
+    base_stop_ids: HashMap<(ServiceRouteId, u8), String>,
+    merges: Vec<ServiceRouteMerge>,
 }
 
+/// パターン => (停留所名の並び, trip数)
+type PatternCounts = HashMap<Identifier, (Vec<String>, usize)>;
+
 impl ServiceRouteGenerator {
     pub fn new(
         strategy: &IdentifyStrategy,
         identities_or: Option<&Vec<ServiceRouteIdentity>>,
     ) -> Self {
-        match identities_or {
-            Some(identities) => {
-                let max_id = identities
-                    .iter()
-                    .map(|i| i.service_route_id)
-                    .max()
-                    .unwrap_or(0);
-
-                // 先にインスタンスを作成
-                let mut ins = ServiceRouteGenerator {
-                    service_route_id: max_id,
-                    service_route_by_identify: HashMap::new(),
-                    identify_strategy: strategy.clone(),
-                };
+        let mut ins = ServiceRouteGenerator {
+            service_route_id: 0,
+            service_route_by_identify: HashMap::new(),
+            identify_strategy: strategy.clone(),
+            service_route_by_trip_id: HashMap::new(),
+            identity_identifiers: HashSet::new(),
+            canonical_by_pattern: HashMap::new(),
+            node_id_by_stop_id: HashMap::new(),
+            base_stop_ids: HashMap::new(),
+            merges: vec![],
+        };
 
-                for identity in identities {
-                    let service_route = ServiceRoute {
-                        service_route_id: identity.service_route_id,
-                        service_route_name: identity.service_route_name.clone(),
-                        direction_id: identity.service_route_direction_id.clone(),
-                    };
+        if let Some(identities) = identities_or {
+            ins.service_route_id = identities
+                .iter()
+                .map(|i| i.service_route_id)
+                .max()
+                .unwrap_or(0);
+
+            for identity in identities {
+                let service_route = ServiceRoute {
+                    service_route_id: identity.service_route_id,
+                    service_route_name: identity.service_route_name.clone(),
+                    direction_id: identity.service_route_direction_id.clone(),
+                };
+                if let Some(identifier) = ins.identify_strategy.identifier_of(identity) {
+                    ins.identity_identifiers.insert(identifier.clone());
                     ins.service_route_by_identify
-                        .insert(ins.pick_identifier(&identity), service_route);
+                        .insert(identifier, service_route.clone());
+                }
+                for trip_id in identity.trip_ids.split(',') {
+                    ins.service_route_by_trip_id
+                        .insert(trip_id.trim().to_string(), service_route.clone());
                 }
-
-                ins
             }
-            None => ServiceRouteGenerator {
-                service_route_id: 0,
-                service_route_by_identify: HashMap::new(),
-                identify_strategy: strategy.clone(),
-            },
         }
+
+        ins
     }
 
-    /// Identityファイルからidentifierを求める
-    fn pick_identifier(&self, identity: &ServiceRouteIdentity) -> Identifier {
-        match self.identify_strategy {
-            IdentifyStrategy::StopIds => identity.stop_ids.clone(),
-            IdentifyStrategy::StopNames => identity.stop_names.clone(),
+    /// Nodes戦略で使う標柱とノードの対応を設定する
+    pub fn with_nodes(mut self, stops2nodes: &[Stop2Node]) -> Self {
+        self.node_id_by_stop_id = stops2nodes
+            .iter()
+            .map(|x| (x.stop_id.clone(), x.node_id))
+            .collect();
+        self
+    }
+
+    /// Subsequence戦略のために全tripの停車パターンから正規パターンを求める
+    /// 他の戦略では何もしない
+    pub fn learn<'a, I>(&mut self, trips: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a [StopTimeDetail]>,
+    {
+        if !matches!(self.identify_strategy, IdentifyStrategy::Subsequence) {
+            return Ok(());
+        }
+
+        // (経路ID, 上下区分) => パターン => (停留所名の並び, trip数)
+        let mut patterns: HashMap<(RouteId, u8), PatternCounts> = HashMap::new();
+        for details in trips.into_iter().filter(|x| !x.is_empty()) {
+            let (group, stop_names) = route_stops_of(details)?;
+            patterns
+                .entry(group)
+                .or_default()
+                .entry(route_stop_names_of(details)?)
+                .or_insert((stop_names, 0))
+                .1 += 1;
         }
+
+        for (_, counts) in patterns {
+            // 長い順、trip数の多い順に正規パターンの候補とする
+            let candidates = counts
+                .iter()
+                .sorted_by(|(a, (a_stops, ac)), (b, (b_stops, bc))| {
+                    b_stops
+                        .len()
+                        .cmp(&a_stops.len())
+                        .then(bc.cmp(ac))
+                        .then(a.cmp(b))
+                })
+                .collect_vec();
+            for (pattern, (stops, _)) in &candidates {
+                let canonical = candidates
+                    .iter()
+                    .find(|(_, (sup, _))| sup.len() > stops.len() && is_subsequence(stops, sup))
+                    .map(|(c, _)| *c)
+                    .unwrap_or(pattern);
+                self.canonical_by_pattern
+                    .insert((*pattern).clone(), canonical.clone());
+            }
+        }
+        Ok(())
     }
 
     /// stop_time_detailsからidentifierを求める
     fn to_identifier(&self, stop_time_details: &[StopTimeDetail]) -> Result<Identifier> {
         match self.identify_strategy {
-            IdentifyStrategy::StopIds => Ok(stop_time_details.iter().map(|x| &x.stop_id).join(",")),
+            IdentifyStrategy::StopIds => Ok(stop_ids_of(stop_time_details)),
             IdentifyStrategy::StopNames => {
                 Ok(stop_time_details.iter().map(|x| &x.stop_name).join(","))
             }
+            IdentifyStrategy::RouteStopNames => route_stop_names_of(stop_time_details),
+            IdentifyStrategy::Stations => Ok(stop_time_details
+                .iter()
+                .map(|x| x.parent_station.as_ref().unwrap_or(&x.stop_id))
+                .join(",")),
+            IdentifyStrategy::Nodes => Ok(serde_json::Value::from(
+                stop_time_details
+                    .iter()
+                    .map(|x| match self.node_id_by_stop_id.get(&x.stop_id) {
+                        Some(node_id) => serde_json::Value::from(*node_id),
+                        None => serde_json::Value::from(x.stop_id.as_str()),
+                    })
+                    .collect_vec(),
+            )
+            .to_string()),
+            IdentifyStrategy::ShapeId => {
+                let shape_id = stop_time_details.first().and_then(|x| x.shape_id.as_ref());
+                Ok(match shape_id {
+                    Some(shape_id) => format!("shape:{}", shape_id),
+                    None => format!("stops:{}", stop_ids_of(stop_time_details)),
+                })
+            }
+            IdentifyStrategy::Subsequence => {
+                let pattern = route_stop_names_of(stop_time_details)?;
+                Ok(self
+                    .canonical_by_pattern
+                    .get(&pattern)
+                    .cloned()
+                    .unwrap_or(pattern))
+            }
         }
     }
 
     /// stop_time_detailsは 1つのtripに対し、sequence昇順
-    pub fn generate(&mut self, stop_time_details: &[StopTimeDetail]) -> Result<ServiceRoute> {
-        let identify = self
-            .to_identifier(stop_time_details)
-            .with_context(|| "service_routeのidentifyに失敗しました")?;
-        match self.service_route_by_identify.get(&identify) {
-            Some(c) => Ok(c.clone()),
-            None => {
-                let first_detail = stop_time_details
-                    .first()
-                    .expect("stop_time_detailsが存在しません。予期せぬGTFSデータです。");
-                let last_detail = stop_time_details
-                    .last()
-                    .expect("stop_time_detailsが存在しません。予期せぬGTFSデータです。");
-
-                // service_route_nameは参考程度で表示に使う想定はしていない
-                let service_route_name = format!(
-                    "{}({}～{})",
-                    first_detail.route_name(),
-                    first_detail.stop_name,
-                    last_detail.stop_name,
-                );
-                let service_route = ServiceRoute {
-                    service_route_id: self.service_route_id + 1,
-                    service_route_name,
-                    direction_id: first_detail
-                        .direction_id
-                        .clone()
-                        .unwrap_or(DirectionId::Outbound),
-                };
-                self.service_route_id += 1;
-                self.service_route_by_identify
-                    .insert(identify, service_route.clone());
-                Ok(service_route)
+    /// service_routeを決められないデータの場合は問題を返す
+    pub fn generate(
+        &mut self,
+        trip_id: &str,
+        stop_time_details: &[StopTimeDetail],
+    ) -> Result<ServiceRoute, ServiceRouteProblem> {
+        let (first_detail, last_detail) =
+            match (stop_time_details.first(), stop_time_details.last()) {
+                (Some(first), Some(last)) => (first, last),
+                _ => {
+                    return Err(ServiceRouteProblem::new(
+                        trip_id,
+                        ProblemKind::NoStopTimes,
+                        "stop_timesが存在しません",
+                    ))
+                }
+            };
+        let identify = self.to_identifier(stop_time_details).map_err(|e| {
+            ServiceRouteProblem::new(trip_id, ProblemKind::Unidentifiable, format!("{:#}", e))
+        })?;
+
+        let (service_route, reason) = match self.service_route_by_identify.get(&identify) {
+            Some(c) if self.identity_identifiers.contains(&identify) => {
+                (c.clone(), MergeReason::IdentityFile)
             }
+            Some(c) => (c.clone(), self.identify_strategy.merge_reason()),
+            None => match self.service_route_by_trip_id.get(trip_id) {
+                Some(c) => {
+                    let c = c.clone();
+                    self.service_route_by_identify.insert(identify, c.clone());
+                    (c, MergeReason::IdentityFile)
+                }
+                None => {
+                    let route_name = first_detail.route_name().ok_or_else(|| {
+                        ServiceRouteProblem::new(
+                            trip_id,
+                            ProblemKind::MissingRouteName,
+                            format!(
+                                "route_id={} にroute_short_nameとroute_long_nameのどちらもありません",
+                                first_detail.route_id
+                            ),
+                        )
+                    })?;
+                    // service_route_nameは参考程度で表示に使う想定はしていない
+                    let service_route_name = format!(
+                        "{}({}～{})",
+                        route_name, first_detail.stop_name, last_detail.stop_name,
+                    );
+                    let service_route = ServiceRoute {
+                        service_route_id: self.service_route_id + 1,
+                        service_route_name,
+                        direction_id: first_detail
+                            .direction_id
+                            .clone()
+                            .unwrap_or(DirectionId::Outbound),
+                    };
+                    self.service_route_id += 1;
+                    self.service_route_by_identify
+                        .insert(identify, service_route.clone());
+                    (service_route, self.identify_strategy.merge_reason())
+                }
+            },
+        };
+
+        let stop_ids = stop_ids_of(stop_time_details);
+        let base_stop_ids = self
+            .base_stop_ids
+            .entry((
+                service_route.service_route_id,
+                service_route.direction_id.clone() as u8,
+            ))
+            .or_insert_with(|| stop_ids.clone())
+            .clone();
+        if base_stop_ids != stop_ids {
+            self.merges.push(ServiceRouteMerge {
+                trip_id: trip_id.to_string(),
+                service_route_id: service_route.service_route_id,
+                service_route_direction_id: service_route.direction_id.clone(),
+                stop_ids,
+                base_stop_ids,
+                reason,
+            });
         }
+
+        Ok(service_route)
+    }
+
+    /// tripごとのstop_time_details (sequence昇順) からtripとservice_routeの対応を求める
+    /// service_routeを決められないtripは対応の代わりに問題として返す
+    pub fn generate_all(
+        &mut self,
+        stop_time_details_by_trip_id: Vec<(TripId, Vec<StopTimeDetail>)>,
+    ) -> Result<(Vec<Trip2ServiceRoute>, Vec<ServiceRouteProblem>)> {
+        self.learn(
+            stop_time_details_by_trip_id
+                .iter()
+                .map(|(_, details)| details.as_slice()),
+        )?;
+
+        let mut trip2service_routes = vec![];
+        let mut problems = vec![];
+        for (trip_id, details) in stop_time_details_by_trip_id {
+            match self.generate(&trip_id, &details) {
+                Ok(service_route) => trip2service_routes.push(Trip2ServiceRoute {
+                    trip_id,
+                    service_route_id: service_route.service_route_id,
+                    service_route_direction_id: service_route.direction_id,
+                }),
+                Err(problem) => problems.push(problem),
+            }
+        }
+        Ok((trip2service_routes, problems))
     }
 
     pub fn all(&self) -> Vec<&ServiceRoute> {
-        self.service_route_by_identify.values().collect_vec()
+        self.service_route_by_identify
+            .values()
+            .unique_by(|x| (x.service_route_id, x.direction_id.clone() as u8))
+            .collect_vec()
+    }
+
+    /// 代表tripと停車パターンが異なるのに同じサービスルートにまとめたtrip
+    pub fn merges(&self) -> &[ServiceRouteMerge] {
+        &self.merges
     }
 }
@@ -2,7 +2,7 @@ use rusqlite::{Connection, NO_PARAMS};
 use serde::{Deserialize, Serialize};
 use serde_rusqlite::from_rows;
 
-use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::stops::{LocationType, StopId};
 use crate::external::gtfs::{Latitude, Longitude};
 
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
@@ -23,6 +23,15 @@ pub struct StopDetail {
     ///   - 1だと利用不可
     ///   - 2～4だと必須
     pub parent_station: Option<StopId>,
+    /// 停留所・標柱区分
+    pub location_type: Option<LocationType>,
+}
+
+impl StopDetail {
+    /// 乗降できる標柱か
+    pub fn is_pole(&self) -> bool {
+        matches!(self.location_type, None | Some(LocationType::Pole))
+    }
 }
 
 pub fn select_stop_details(conn: &mut Connection) -> serde_rusqlite::Result<Vec<StopDetail>> {
@@ -34,7 +43,8 @@ SELECT
   t.translation as stop_ruby,
   st.stop_lat,
   st.stop_lon,
-  st.parent_station
+  st.parent_station,
+  st.location_type
 FROM
   stops st
     LEFT JOIN translations t
@@ -1,10 +1,12 @@
-use rusqlite::{named_params, types::Value, Connection, NO_PARAMS};
+use rusqlite::{named_params, types::Value, Connection, ToSql};
 use serde::{Deserialize, Serialize};
 
 use crate::external::gtfs::stop_times::StopTime;
 use crate::external::gtfs::stops::{Stop, StopId};
 use crate::external::gtfs::trips::{Trip, TripId};
-use crate::external::gtfs::{DirectionId, Latitude, Longitude, Sequence};
+use crate::external::gtfs::{
+    DirectionId, GtfsTime, Latitude, Longitude, OperatorScope, Paging, Sequence, SortColumns,
+};
 
 use crate::external::gtfs::routes::{Route, RouteId};
 use crate::external::gtfsdb::Table;
@@ -26,6 +28,8 @@ pub struct StopTimeDetail {
     pub stop_headsign: Option<String>,
     /// 標柱ID
     pub stop_id: StopId,
+    /// 親駅ID
+    pub parent_station: Option<StopId>,
     /// 停留所・標柱名称 (ex: ①東京駅八重洲口 ②東京駅八重洲口)
     pub stop_name: String,
     /// 緯度 (ex: ①35.680515 ※ターミナル中心 ②35.679752 ※標柱位置)
@@ -33,9 +37,13 @@ pub struct StopTimeDetail {
     /// 経度 (ex: ①139.764698 ※ターミナル中心）②139.768330 ※標柱位置)
     pub stop_lon: Longitude,
     /// 到着時刻 (ex: 7:00:00)
-    pub arrival_time: String,
+    pub arrival_time: GtfsTime,
     /// 出発時刻 (ex: 7:00:00)
-    pub departure_time: String,
+    pub departure_time: GtfsTime,
+    /// 時刻が補間されたものか
+    pub interpolated: bool,
+    /// 描画ID (ex: S_1001)
+    pub shape_id: Option<String>,
     /// 経路ID
     pub route_id: RouteId,
     /// 経路略称 (ex: 東16)
@@ -45,35 +53,37 @@ pub struct StopTimeDetail {
 }
 
 impl StopTimeDetail {
-    pub fn route_name(&self) -> &String {
-        match &self.route_long_name {
-            Some(n) => n,
-            _none => self.route_short_name.as_ref().unwrap(), // long_nameとshort_nameどちらかは値がある
-        }
+    /// 経路名 (なければ経路略称). どちらもないデータではNone
+    pub fn route_name(&self) -> Option<&String> {
+        self.route_long_name
+            .as_ref()
+            .or_else(|| self.route_short_name.as_ref())
     }
 }
 
-pub fn select_stop_time_details(
-    conn: &mut Connection,
-) -> serde_rusqlite::Result<Vec<StopTimeDetail>> {
-    let mut stmt = conn.prepare(
-        format!(
-            "
-SELECT
-  stt.trip_id,
-  t.direction_id,
-  t.trip_headsign,
-  stt.stop_sequence,
-  stt.stop_headsign,
-  st.stop_id,
-  st.stop_name,
-  st.stop_lat,
-  st.stop_lon,
-  stt.arrival_time,
-  stt.departure_time,
-  r.route_id,
-  r.route_short_name,
-  r.route_long_name
+/// select_stop_time_detailsで並び替えられる項目
+pub const STOP_TIME_DETAIL_SORT_COLUMNS: SortColumns = &[
+    ("trip_id", "stt.trip_id"),
+    ("direction_id", "t.direction_id"),
+    ("trip_headsign", "t.trip_headsign"),
+    ("stop_sequence", "stt.stop_sequence"),
+    ("stop_headsign", "stt.stop_headsign"),
+    ("stop_id", "st.stop_id"),
+    ("parent_station", "st.parent_station"),
+    ("stop_name", "st.stop_name"),
+    ("arrival_time", "stt.arrival_time_seconds"),
+    ("departure_time", "stt.departure_time_seconds"),
+    ("interpolated", "stt.interpolated"),
+    ("shape_id", "t.shape_id"),
+    ("route_id", "r.route_id"),
+    ("route_short_name", "r.route_short_name"),
+    ("route_long_name", "r.route_long_name"),
+];
+
+/// select_stop_time_detailsとcount_stop_time_detailsで共通のFROM句とWHERE句
+fn stop_time_details_from_sql() -> String {
+    format!(
+        "
 FROM
   {} stt
     INNER JOIN {} t
@@ -82,24 +92,53 @@ FROM
     ON stt.stop_id == st.stop_id
     INNER JOIN {} r
     ON t.route_id == r.route_id
-ORDER BY
-  stt.trip_id, stt.stop_sequence
+WHERE (:all_trips OR stt.trip_id IN rarray(:trip_ids))
+  AND (:stop_name_prefix IS NULL OR st.stop_name LIKE :stop_name_prefix)
+  AND (:agency_id IS NULL OR r.agency_id == :agency_id)
+  AND (:office_id IS NULL OR t.jp_office_id == :office_id)
 ",
-            StopTime::table_name(),
-            Trip::table_name(),
-            Stop::table_name(),
-            Route::table_name(),
-        )
-        .as_str(),
-    )?;
+        StopTime::table_name(),
+        Trip::table_name(),
+        Stop::table_name(),
+        Route::table_name(),
+    )
+}
 
-    let result = from_rows(stmt.query(NO_PARAMS)?).collect();
-    result
+/// stop_time_details_from_sqlに渡すパラメータでfを呼び出す
+fn with_stop_time_detail_params<F, T>(
+    trip_ids: Option<Vec<TripId>>,
+    stop_name_prefix: Option<String>,
+    scope: &OperatorScope,
+    f: F,
+) -> T
+where
+    F: FnOnce(&[(&str, &dyn ToSql)]) -> T,
+{
+    let all_trips = trip_ids.is_none();
+    let ids = Rc::new(
+        trip_ids
+            .into_iter()
+            .flatten()
+            .map(Value::from)
+            .collect_vec(),
+    );
+    f(named_params! {
+        ":all_trips": all_trips,
+        ":trip_ids": ids,
+        ":stop_name_prefix": stop_name_prefix.map(|x| format!("{}%", x)),
+        ":agency_id": scope.agency_id,
+        ":office_id": scope.office_id,
+    })
 }
 
-pub fn select_stop_time_details_by_ids(
+/// trip_ids, 停留所名の前方一致で絞り込んだstop_timeを取得する
+/// 返却結果のソートは paging.sort, trip_id, stop_sequence を保証する
+pub fn select_stop_time_details(
     conn: &mut Connection,
-    trip_ids: Vec<TripId>,
+    trip_ids: Option<Vec<TripId>>,
+    stop_name_prefix: Option<String>,
+    scope: &OperatorScope,
+    paging: &Paging,
 ) -> serde_rusqlite::Result<Vec<StopTimeDetail>> {
     let mut stmt = conn.prepare(
         format!(
@@ -111,87 +150,47 @@ SELECT
   stt.stop_sequence,
   stt.stop_headsign,
   st.stop_id,
+  st.parent_station,
   st.stop_name,
   st.stop_lat,
   st.stop_lon,
   stt.arrival_time,
   stt.departure_time,
+  stt.interpolated,
+  t.shape_id,
   r.route_id,
   r.route_short_name,
   r.route_long_name
-FROM
-  {} stt
-    INNER JOIN {} t
-    ON stt.trip_id == t.trip_id
-    INNER JOIN {} st
-    ON stt.stop_id == st.stop_id
-    INNER JOIN {} r
-    ON t.route_id == r.route_id
-WHERE stt.trip_id in rarray(:trip_ids)
+{}
 ORDER BY
-  stt.trip_id, stt.stop_sequence
+  {}
+LIMIT :limit OFFSET :offset
 ",
-            StopTime::table_name(),
-            Trip::table_name(),
-            Stop::table_name(),
-            Route::table_name(),
+            stop_time_details_from_sql(),
+            paging.order_by("stt.trip_id, stt.stop_sequence"),
         )
         .as_str(),
     )?;
 
-    let ids = Rc::new(trip_ids.into_iter().map(Value::from).collect_vec());
-    let result = from_rows(stmt.query_named(named_params! {
-        ":trip_ids": ids,
-    })?)
-    .collect();
-    result
+    let (limit, offset) = (paging.sql_limit(), paging.offset);
+    with_stop_time_detail_params(trip_ids, stop_name_prefix, scope, |params| {
+        let mut params = params.to_vec();
+        params.push((":limit", &limit));
+        params.push((":offset", &offset));
+        from_rows(stmt.query_named(&params)?).collect()
+    })
 }
 
-pub fn select_stop_time_details_by_name(
+/// select_stop_time_detailsでページングしない場合の件数
+pub fn count_stop_time_details(
     conn: &mut Connection,
-    stop_name_prefix: String,
-) -> serde_rusqlite::Result<Vec<StopTimeDetail>> {
-    let mut stmt = conn.prepare(
-        format!(
-            "
-SELECT
-  stt.trip_id,
-  t.direction_id,
-  t.trip_headsign,
-  stt.stop_sequence,
-  stt.stop_headsign,
-  st.stop_id,
-  st.stop_name,
-  st.stop_lat,
-  st.stop_lon,
-  stt.arrival_time,
-  stt.departure_time,
-  r.route_id,
-  r.route_short_name,
-  r.route_long_name
-FROM
-  {} stt
-    INNER JOIN {} t
-    ON stt.trip_id == t.trip_id
-    INNER JOIN {} st
-    ON stt.stop_id == st.stop_id
-    INNER JOIN {} r
-    ON t.route_id == r.route_id
-WHERE st.stop_name like :stop_name_prefix
-ORDER BY
-  stt.trip_id, stt.stop_sequence
-",
-            StopTime::table_name(),
-            Trip::table_name(),
-            Stop::table_name(),
-            Route::table_name(),
-        )
-        .as_str(),
-    )?;
-
-    let result = from_rows(stmt.query_named(named_params! {
-        ":stop_name_prefix": format!("{}%", stop_name_prefix)
-    })?)
-    .collect();
-    result
+    trip_ids: Option<Vec<TripId>>,
+    stop_name_prefix: Option<String>,
+    scope: &OperatorScope,
+) -> rusqlite::Result<u32> {
+    let mut stmt =
+        conn.prepare(format!("SELECT count(*) {}", stop_time_details_from_sql()).as_str())?;
+    with_stop_time_detail_params(trip_ids, stop_name_prefix, scope, |params| {
+        stmt.query_row_named(params, |row| row.get(0))
+    })
 }
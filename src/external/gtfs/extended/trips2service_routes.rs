@@ -25,6 +25,10 @@ impl Table for Trip2ServiceRoute {
         &["trip_id", "service_route_id", "service_route_direction_id"]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["trip_id", "service_route_id", "service_route_direction_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         trip_id text,
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::Meter;
+use crate::external::gtfsdb::Table;
+
+/// 近くの標柱間の徒歩乗換としてtransfersに生成した組
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct WalkingTransfer {
+    /// 乗換元標柱ID
+    pub from_stop_id: StopId,
+    /// 乗換先標柱ID
+    pub to_stop_id: StopId,
+    /// 標柱間の大円距離
+    pub distance: Meter,
+}
+
+impl Table for WalkingTransfer {
+    fn table_name() -> &'static str {
+        "walking_transfers"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["from_stop_id", "to_stop_id", "distance"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["from_stop_id", "to_stop_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        from_stop_id text not null,
+        to_stop_id text not null,
+        distance double not null,
+        PRIMARY KEY(from_stop_id, to_stop_id)
+        "
+    }
+}
@@ -71,6 +71,10 @@ impl Table for FareAttribute {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["fare_id", "currency_type"]
+    }
+
     fn create_sql() -> &'static str {
         "
         fare_id text,
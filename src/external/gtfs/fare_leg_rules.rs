@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::areas::AreaId;
+use crate::external::gtfs::fare_products::FareProductId;
+use crate::external::gtfs::networks::NetworkId;
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+
+/// 区間グループID (ex: leg_1)
+pub type LegGroupId = String;
+
+/// 区間運賃ルール情報 (Fares v2)
+/// https://developers.google.com/transit/gtfs/reference#fare_leg_rulestxt
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct FareLegRule {
+    /// 区間グループID
+    pub leg_group_id: Option<LegGroupId>,
+    /// ネットワークID (未指定の場合はすべてのネットワーク)
+    pub network_id: Option<NetworkId>,
+    /// 乗車エリアID (未指定の場合はすべてのエリア)
+    pub from_area_id: Option<AreaId>,
+    /// 降車エリアID (未指定の場合はすべてのエリア)
+    pub to_area_id: Option<AreaId>,
+    /// 乗車時間帯グループID
+    pub from_timeframe_group_id: Option<String>,
+    /// 降車時間帯グループID
+    pub to_timeframe_group_id: Option<String>,
+    /// 運賃商品ID
+    pub fare_product_id: FareProductId,
+    /// 優先度 (大きいほど優先)
+    pub rule_priority: Option<u32>,
+}
+
+impl GTFSFile for FareLegRule {
+    fn file_name() -> &'static str {
+        "fare_leg_rules.txt"
+    }
+}
+
+impl Table for FareLegRule {
+    fn table_name() -> &'static str {
+        "fare_leg_rules"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &[
+            "leg_group_id",
+            "network_id",
+            "from_area_id",
+            "to_area_id",
+            "from_timeframe_group_id",
+            "to_timeframe_group_id",
+            "fare_product_id",
+            "rule_priority",
+        ]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        leg_group_id text,
+        network_id text,
+        from_area_id text,
+        to_area_id text,
+        from_timeframe_group_id text,
+        to_timeframe_group_id text,
+        fare_product_id text not null,
+        rule_priority int
+        "
+    }
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+
+/// 運賃媒体ID (ex: suica)
+pub type FareMediaId = String;
+
+/// 運賃媒体タイプ
+#[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
+#[repr(u8)]
+pub enum FareMediaType {
+    /// 媒体なし (現金払いなど)
+    None = 0,
+    /// 紙の乗車券
+    PaperTicket = 1,
+    /// 交通系ICカード
+    TransitCard = 2,
+    /// クレジットカードなどのタッチ決済 (cEMV)
+    Cemv = 3,
+    /// モバイルアプリ
+    MobileApp = 4,
+}
+
+/// 運賃媒体情報 (Fares v2)
+/// https://developers.google.com/transit/gtfs/reference#fare_mediatxt
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct FareMedia {
+    /// 運賃媒体ID
+    pub fare_media_id: FareMediaId,
+    /// 運賃媒体名 (ex: Suica)
+    pub fare_media_name: Option<String>,
+    /// 運賃媒体タイプ
+    pub fare_media_type: FareMediaType,
+}
+
+impl GTFSFile for FareMedia {
+    fn file_name() -> &'static str {
+        "fare_media.txt"
+    }
+}
+
+impl Table for FareMedia {
+    fn table_name() -> &'static str {
+        "fare_media"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["fare_media_id", "fare_media_name", "fare_media_type"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["fare_media_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        fare_media_id text primary key,
+        fare_media_name text,
+        fare_media_type int not null
+        "
+    }
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::fare_attributes::CurrencyType;
+use crate::external::gtfs::fare_media::FareMediaId;
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+use ordered_float::OrderedFloat;
+
+/// 運賃商品ID (ex: regular_adult)
+pub type FareProductId = String;
+
+/// 運賃商品情報 (Fares v2)
+/// https://developers.google.com/transit/gtfs/reference#fare_productstxt
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct FareProduct {
+    /// 運賃商品ID
+    pub fare_product_id: FareProductId,
+    /// 運賃商品名 (ex: 大人普通運賃)
+    pub fare_product_name: Option<String>,
+    /// 運賃媒体ID (未指定の場合は媒体を問わない)
+    pub fare_media_id: Option<FareMediaId>,
+    /// 金額 (ex: 210)
+    pub amount: OrderedFloat<f64>,
+    /// 通貨 (ex: JPY)
+    pub currency: CurrencyType,
+}
+
+impl GTFSFile for FareProduct {
+    fn file_name() -> &'static str {
+        "fare_products.txt"
+    }
+}
+
+impl Table for FareProduct {
+    fn table_name() -> &'static str {
+        "fare_products"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &[
+            "fare_product_id",
+            "fare_product_name",
+            "fare_media_id",
+            "amount",
+            "currency",
+        ]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["fare_product_id", "fare_media_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        fare_product_id text not null,
+        fare_product_name text,
+        fare_media_id text,
+        amount double not null,
+        currency text not null,
+        PRIMARY KEY(fare_product_id, fare_media_id)
+        "
+    }
+}
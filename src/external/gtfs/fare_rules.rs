@@ -43,6 +43,10 @@ impl Table for FareRule {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["fare_id", "route_id", "origin_id", "destination_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         fare_id text not null,
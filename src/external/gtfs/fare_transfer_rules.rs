@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::external::gtfs::fare_leg_rules::LegGroupId;
+use crate::external::gtfs::fare_products::FareProductId;
+use crate::external::gtfs::Second;
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+
+/// 乗継有効時間の起点と終点
+#[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
+#[repr(u8)]
+pub enum DurationLimitType {
+    /// 前の区間の出発から次の区間の到着まで
+    DepartureToArrival = 0,
+    /// 前の区間の出発から次の区間の出発まで
+    DepartureToDeparture = 1,
+    /// 前の区間の到着から次の区間の出発まで
+    ArrivalToDeparture = 2,
+    /// 前の区間の到着から次の区間の到着まで
+    ArrivalToArrival = 3,
+}
+
+/// 乗継運賃の計算方法
+#[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
+#[repr(u8)]
+pub enum FareTransferType {
+    /// 前の区間の運賃 + 乗継運賃
+    FromLegPlusTransfer = 0,
+    /// 前の区間の運賃 + 乗継運賃 + 次の区間の運賃
+    FromLegPlusTransferPlusToLeg = 1,
+    /// 乗継運賃のみ
+    TransferOnly = 2,
+}
+
+/// 乗継運賃ルール情報 (Fares v2)
+/// https://developers.google.com/transit/gtfs/reference#fare_transfer_rulestxt
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct FareTransferRule {
+    /// 乗継元の区間グループID
+    pub from_leg_group_id: Option<LegGroupId>,
+    /// 乗継先の区間グループID
+    pub to_leg_group_id: Option<LegGroupId>,
+    /// 乗継回数の上限 (-1は無制限)
+    pub transfer_count: Option<i32>,
+    /// 乗継有効時間
+    pub duration_limit: Option<Second>,
+    /// 乗継有効時間の起点と終点
+    pub duration_limit_type: Option<DurationLimitType>,
+    /// 乗継運賃の計算方法
+    pub fare_transfer_type: FareTransferType,
+    /// 乗継運賃の運賃商品ID (未指定の場合は乗継運賃なし)
+    pub fare_product_id: Option<FareProductId>,
+}
+
+impl GTFSFile for FareTransferRule {
+    fn file_name() -> &'static str {
+        "fare_transfer_rules.txt"
+    }
+}
+
+impl Table for FareTransferRule {
+    fn table_name() -> &'static str {
+        "fare_transfer_rules"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &[
+            "from_leg_group_id",
+            "to_leg_group_id",
+            "transfer_count",
+            "duration_limit",
+            "duration_limit_type",
+            "fare_transfer_type",
+            "fare_product_id",
+        ]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        from_leg_group_id text,
+        to_leg_group_id text,
+        transfer_count int,
+        duration_limit int,
+        duration_limit_type int,
+        fare_transfer_type int not null,
+        fare_product_id text
+        "
+    }
+}
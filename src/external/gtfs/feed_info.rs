@@ -19,7 +19,7 @@ pub struct Feed {
     /// 有効期間終了日
     feed_end_date: Option<DateString>,
     /// 提供データバージョン
-    feed_version: Option<String>,
+    pub feed_version: Option<String>,
 }
 
 impl GTFSFile for Feed {
@@ -44,6 +44,10 @@ impl Table for Feed {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["feed_publisher_name"]
+    }
+
     fn create_sql() -> &'static str {
         "
         feed_publisher_name text primary key,
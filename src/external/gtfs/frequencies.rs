@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::external::gtfs::trips::TripId;
-use crate::external::gtfs::{Second, UnlimitedTime};
+use crate::external::gtfs::{GtfsTime, Second};
 use crate::external::gtfscsv::GTFSFile;
 use crate::external::gtfsdb::Table;
 
@@ -23,9 +23,9 @@ pub struct Frequency {
     /// 便ID
     trip_id: TripId,
     /// 開始時刻
-    start_time: UnlimitedTime,
+    start_time: GtfsTime,
     /// 終了時刻
-    end_time: UnlimitedTime,
+    end_time: GtfsTime,
     /// 運行間隔
     headway_secs: Second,
     /// 案内精度
@@ -53,14 +53,26 @@ impl Table for Frequency {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["trip_id", "start_time"]
+    }
+
     fn create_sql() -> &'static str {
-        "
+        concat!(
+            "
         trip_id text not null,
         start_time text not null,
         end_time text not null,
+        start_time_seconds int GENERATED ALWAYS AS (",
+            crate::gtfs_time_seconds_sql!("start_time"),
+            ") STORED,
+        end_time_seconds int GENERATED ALWAYS AS (",
+            crate::gtfs_time_seconds_sql!("end_time"),
+            ") STORED,
         headway_secs int not null,
         exact_times int,
         PRIMARY KEY(trip_id, start_time)
         "
+        )
     }
 }
@@ -15,6 +15,11 @@ pub struct LegacyTranslation {
     pub translation: String,
 }
 
+impl LegacyTranslation {
+    /// 古い仕様にのみ存在するカラム名 (ヘッダによる仕様の判別に使う)
+    pub const IDENTIFIER_COLUMN: &'static str = "trans_id";
+}
+
 impl GTFSFile for LegacyTranslation {
     fn file_name() -> &'static str {
         "translations.txt"
@@ -30,6 +35,10 @@ impl Table for LegacyTranslation {
         &["table_id", "lang", "translation"]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["trans_id", "lang"]
+    }
+
     fn create_sql() -> &'static str {
         "
         trans_id text not null,
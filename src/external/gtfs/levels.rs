@@ -0,0 +1,77 @@
+use rusqlite::{named_params, types::Value, Connection};
+use serde::{Deserialize, Serialize};
+use serde_rusqlite::from_rows;
+use std::rc::Rc;
+
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+use itertools::Itertools;
+use ordered_float::OrderedFloat;
+
+/// 階層ID (ex: L_B1)
+pub type LevelId = String;
+
+/// 階層情報
+/// https://developers.google.com/transit/gtfs/reference#levelstxt
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Eq, Hash)]
+pub struct Level {
+    /// 階層ID
+    pub level_id: LevelId,
+    /// 階層インデックス (地上階を0とし、地下は負の値) (ex: -1)
+    pub level_index: OrderedFloat<f32>,
+    /// 階層名 (ex: 地下1階)
+    pub level_name: Option<String>,
+}
+
+impl GTFSFile for Level {
+    fn file_name() -> &'static str {
+        "levels.txt"
+    }
+}
+
+impl Table for Level {
+    fn table_name() -> &'static str {
+        "levels"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["level_id", "level_index", "level_name"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["level_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        level_id text primary key,
+        level_index double not null,
+        level_name text
+        "
+    }
+}
+
+/// level_idに一致するlevelを検索する
+pub fn select_levels_by_ids(
+    conn: &mut Connection,
+    level_ids: Vec<LevelId>,
+) -> serde_rusqlite::Result<Vec<Level>> {
+    let mut stmt = conn.prepare(
+        "
+SELECT
+  level_id,
+  level_index,
+  level_name
+FROM levels
+WHERE level_id in rarray(:level_ids)
+ORDER BY level_index
+",
+    )?;
+
+    let ids = Rc::new(level_ids.into_iter().map(Value::from).collect_vec());
+    let result = from_rows(stmt.query_named(named_params! {
+        ":level_ids": ids
+    })?)
+    .collect();
+    result
+}
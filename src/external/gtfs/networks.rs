@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+
+/// ネットワークID (ex: N_1)
+pub type NetworkId = String;
+
+/// ネットワーク情報 (Fares v2)
+/// https://developers.google.com/transit/gtfs/reference#networkstxt
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct Network {
+    /// ネットワークID
+    pub network_id: NetworkId,
+    /// ネットワーク名 (ex: 都バス)
+    pub network_name: Option<String>,
+}
+
+impl GTFSFile for Network {
+    fn file_name() -> &'static str {
+        "networks.txt"
+    }
+}
+
+impl Table for Network {
+    fn table_name() -> &'static str {
+        "networks"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["network_id", "network_name"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["network_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        network_id text primary key,
+        network_name text
+        "
+    }
+}
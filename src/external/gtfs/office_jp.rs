@@ -36,6 +36,10 @@ impl Table for OfficeJp {
         &["office_id", "office_name", "office_url", "office_phone"]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["office_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         office_id text primary key,
@@ -0,0 +1,160 @@
+use rusqlite::{named_params, types::Value, Connection};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use serde_rusqlite::from_rows;
+use std::rc::Rc;
+
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::Second;
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+use itertools::Itertools;
+use ordered_float::OrderedFloat;
+
+/// 通路ID (ex: P_1)
+pub type PathwayId = String;
+
+/// 通路区分
+#[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
+#[repr(u8)]
+pub enum PathwayMode {
+    /// 通路
+    Walkway = 1,
+    /// 階段
+    Stairs = 2,
+    /// 動く歩道
+    MovingSidewalk = 3,
+    /// エスカレーター
+    Escalator = 4,
+    /// エレベーター
+    Elevator = 5,
+    /// 改札 (入場)
+    FareGate = 6,
+    /// 改札 (出場)
+    ExitGate = 7,
+}
+
+/// 通行方向
+#[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
+#[repr(u8)]
+pub enum Bidirectional {
+    /// from_stop_idからto_stop_idへの一方通行
+    Unidirectional = 0,
+    /// 双方向
+    Bidirectional = 1,
+}
+
+/// 通路情報
+/// https://developers.google.com/transit/gtfs/reference#pathwaystxt
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Eq, Hash)]
+pub struct Pathway {
+    /// 通路ID
+    pub pathway_id: PathwayId,
+    /// 通路の始点 (location_typeが0,2,3,4のstop)
+    pub from_stop_id: StopId,
+    /// 通路の終点 (location_typeが0,2,3,4のstop)
+    pub to_stop_id: StopId,
+    /// 通路区分
+    pub pathway_mode: PathwayMode,
+    /// 通行方向
+    pub is_bidirectional: Bidirectional,
+    /// 長さ (メートル)
+    pub length: Option<OrderedFloat<f32>>,
+    /// 通過所要時間
+    pub traversal_time: Option<Second>,
+    /// 階段の段数 (上りは正、下りは負)
+    pub stair_count: Option<i32>,
+    /// 最大勾配 (ex: 0.083)
+    pub max_slope: Option<OrderedFloat<f32>>,
+    /// 最小幅 (メートル)
+    pub min_width: Option<OrderedFloat<f32>>,
+    /// 案内表示 (ex: 1番線)
+    pub signposted_as: Option<String>,
+    /// 逆方向の案内表示
+    pub reversed_signposted_as: Option<String>,
+}
+
+impl GTFSFile for Pathway {
+    fn file_name() -> &'static str {
+        "pathways.txt"
+    }
+}
+
+impl Table for Pathway {
+    fn table_name() -> &'static str {
+        "pathways"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &[
+            "pathway_id",
+            "from_stop_id",
+            "to_stop_id",
+            "pathway_mode",
+            "is_bidirectional",
+            "length",
+            "traversal_time",
+            "stair_count",
+            "max_slope",
+            "min_width",
+            "signposted_as",
+            "reversed_signposted_as",
+        ]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["pathway_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        pathway_id text primary key,
+        from_stop_id text not null,
+        to_stop_id text not null,
+        pathway_mode int not null,
+        is_bidirectional int not null,
+        length double,
+        traversal_time int,
+        stair_count int,
+        max_slope double,
+        min_width double,
+        signposted_as text,
+        reversed_signposted_as text
+        "
+    }
+}
+
+/// stopのいずれかを始点または終点とするpathwayを検索する
+pub fn select_pathways_by_stops(
+    conn: &mut Connection,
+    stop_ids: Vec<StopId>,
+) -> serde_rusqlite::Result<Vec<Pathway>> {
+    let mut stmt = conn.prepare(
+        "
+SELECT
+  pathway_id,
+  from_stop_id,
+  to_stop_id,
+  pathway_mode,
+  is_bidirectional,
+  length,
+  traversal_time,
+  stair_count,
+  max_slope,
+  min_width,
+  signposted_as,
+  reversed_signposted_as
+FROM pathways
+WHERE from_stop_id in rarray(:stop_ids)
+   OR to_stop_id in rarray(:stop_ids)
+ORDER BY pathway_id
+",
+    )?;
+
+    let ids = Rc::new(stop_ids.into_iter().map(Value::from).collect_vec());
+    let result = from_rows(stmt.query_named(named_params! {
+        ":stop_ids": ids
+    })?)
+    .collect();
+    result
+}
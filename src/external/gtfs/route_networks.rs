@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::networks::NetworkId;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+
+/// ネットワークに属する経路情報 (Fares v2)
+/// https://developers.google.com/transit/gtfs/reference#route_networkstxt
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct RouteNetwork {
+    /// ネットワークID
+    pub network_id: NetworkId,
+    /// 経路ID
+    pub route_id: RouteId,
+}
+
+impl GTFSFile for RouteNetwork {
+    fn file_name() -> &'static str {
+        "route_networks.txt"
+    }
+}
+
+impl Table for RouteNetwork {
+    fn table_name() -> &'static str {
+        "route_networks"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["network_id", "route_id"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["route_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        network_id text not null,
+        route_id text primary key
+        "
+    }
+}
@@ -1,20 +1,339 @@
-use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
+use rusqlite::{named_params, Connection, NO_PARAMS};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_rusqlite::from_rows;
+use strum_macros::{Display, EnumString, EnumVariantNames};
 
 use crate::external::gtfs::agency::AgencyId;
-use crate::external::gtfs::Color;
+use crate::external::gtfs::networks::NetworkId;
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trips::Trip;
+use crate::external::gtfs::{Color, OperatorScope, Second};
 use crate::external::gtfscsv::GTFSFile;
 use crate::external::gtfsdb::Table;
 
 /// 経路ID (ex: 1001)
 pub type RouteId = String;
 
-// GTFSの場合は他にも追加されるはず
-#[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
-#[repr(u8)]
-enum RouteType {
+/// 経路タイプの列挙子とroutes.txtでのコードの対応から、RouteTypeとコードとの変換を定義する
+macro_rules! route_types {
+    ($($(#[$meta:meta])* $name:ident = $code:literal,)*) => {
+        /// 経路タイプ
+        /// https://developers.google.com/transit/gtfs/reference#routestxt
+        /// https://developers.google.com/transit/gtfs/reference/extended-route-types
+        #[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+        pub enum RouteType {
+            $($(#[$meta])* $name,)*
+            /// 上記以外の経路タイプ (コードをそのまま保持する)
+            Unknown(u16),
+        }
+
+        impl RouteType {
+            /// 定義済みの経路タイプのコード
+            pub const CODES: &'static [u16] = &[$($code,)*];
+
+            /// routes.txtでのコード
+            pub fn code(&self) -> u16 {
+                match self {
+                    $(RouteType::$name => $code,)*
+                    RouteType::Unknown(code) => *code,
+                }
+            }
+
+            pub fn from_code(code: u16) -> RouteType {
+                match code {
+                    $($code => RouteType::$name,)*
+                    code => RouteType::Unknown(code),
+                }
+            }
+        }
+    };
+}
+
+route_types! {
+    // ---------- 基本 ----------
+    /// 路面電車・ライトレール
+    Tram = 0,
+    /// 地下鉄
+    Subway = 1,
+    /// 鉄道
+    Rail = 2,
+    /// バス
+    Bus = 3,
+    /// フェリー
+    Ferry = 4,
+    /// ケーブルトラム
+    CableTram = 5,
+    /// ロープウェイ・ゴンドラ
+    AerialLift = 6,
+    /// ケーブルカー
+    Funicular = 7,
+    /// トロリーバス
+    Trolleybus = 11,
+    /// モノレール
+    Monorail = 12,
+    // ---------- 拡張 (Hierarchical Vehicle Types) ----------
+    /// 鉄道サービス
+    RailwayService = 100,
+    /// 高速鉄道 (新幹線など)
+    HighSpeedRail = 101,
+    /// 長距離列車
+    LongDistanceTrains = 102,
+    /// 地域間鉄道
+    InterRegionalRail = 103,
+    /// 自動車輸送列車
+    CarTransportRail = 104,
+    /// 寝台列車
+    SleeperRail = 105,
+    /// 地域鉄道
+    RegionalRail = 106,
+    /// 観光鉄道
+    TouristRailway = 107,
+    /// 構内シャトル
+    RailShuttle = 108,
+    /// 近郊鉄道
+    SuburbanRailway = 109,
+    /// 代替鉄道
+    ReplacementRail = 110,
+    /// 特別列車
+    SpecialRail = 111,
+    /// トラック輸送列車
+    LorryTransportRail = 112,
+    /// すべての鉄道サービス
+    AllRailServices = 113,
+    /// クロスカントリー鉄道
+    CrossCountryRail = 114,
+    /// 車両輸送列車
+    VehicleTransportRail = 115,
+    /// ラック式鉄道
+    RackAndPinionRailway = 116,
+    /// その他の鉄道
+    AdditionalRail = 117,
+    /// 長距離バスサービス
+    CoachService = 200,
+    /// 国際長距離バス
+    InternationalCoach = 201,
+    /// 国内長距離バス
+    NationalCoach = 202,
+    /// シャトル長距離バス
+    ShuttleCoach = 203,
+    /// 地域間長距離バス
+    RegionalCoach = 204,
+    /// 特別長距離バス
+    SpecialCoach = 205,
+    /// 観光長距離バス
+    SightseeingCoach = 206,
+    /// ツアー長距離バス
+    TouristCoach = 207,
+    /// 通勤長距離バス
+    CommuterCoach = 208,
+    /// すべての長距離バスサービス
+    AllCoachServices = 209,
+    /// 都市鉄道サービス
+    UrbanRailwayService = 400,
+    /// メトロ
+    Metro = 401,
+    /// 地下鉄
+    Underground = 402,
+    /// 都市鉄道
+    UrbanRailway = 403,
+    /// すべての都市鉄道サービス
+    AllUrbanRailwayServices = 404,
+    /// モノレール
+    MonorailService = 405,
+    /// バスサービス
+    BusService = 700,
+    /// 地域間バス
+    RegionalBus = 701,
+    /// 急行バス
+    ExpressBus = 702,
+    /// 各停バス
+    StoppingBus = 703,
+    /// 路線バス
+    LocalBus = 704,
+    /// 深夜バス
+    NightBus = 705,
+    /// 郵便バス
+    PostBus = 706,
+    /// 福祉バス
+    SpecialNeedsBus = 707,
+    /// 移動支援バス
+    MobilityBus = 708,
+    /// 障がい者向け移動支援バス
+    MobilityBusForRegisteredDisabled = 709,
+    /// 観光バス
+    SightseeingBus = 710,
+    /// シャトルバス
+    ShuttleBus = 711,
+    /// スクールバス
+    SchoolBus = 712,
+    /// スクール兼路線バス
+    SchoolAndPublicServiceBus = 713,
+    /// 鉄道代行バス
+    RailReplacementBus = 714,
+    /// デマンドバス
+    DemandAndResponseBus = 715,
+    /// すべてのバスサービス
+    AllBusServices = 716,
+    /// トロリーバスサービス
+    TrolleybusService = 800,
+    /// 路面電車サービス
+    TramService = 900,
+    /// 市内路面電車
+    CityTram = 901,
+    /// ローカル路面電車
+    LocalTram = 902,
+    /// 地域間路面電車
+    RegionalTram = 903,
+    /// 観光路面電車
+    SightseeingTram = 904,
+    /// シャトル路面電車
+    ShuttleTram = 905,
+    /// すべての路面電車サービス
+    AllTramServices = 906,
+    /// 水上交通サービス
+    WaterTransportService = 1000,
+    /// 航空サービス
+    AirService = 1100,
+    /// フェリーサービス
+    FerryService = 1200,
+    /// 索道サービス
+    AerialLiftService = 1300,
+    /// テレキャビン
+    Telecabin = 1301,
+    /// ロープウェイ
+    CableCar = 1302,
+    /// エレベーター
+    Elevator = 1303,
+    /// チェアリフト
+    ChairLift = 1304,
+    /// ドラッグリフト
+    DragLift = 1305,
+    /// 小型テレキャビン
+    SmallTelecabin = 1306,
+    /// すべてのテレキャビンサービス
+    AllTelecabinServices = 1307,
+    /// ケーブルカーサービス
+    FunicularService = 1400,
+    /// タクシーサービス
+    TaxiService = 1500,
+    /// 乗合タクシー
+    CommunalTaxi = 1501,
+    /// 水上タクシー
+    WaterTaxi = 1502,
+    /// 鉄道タクシー
+    RailTaxi = 1503,
+    /// 自転車タクシー
+    BikeTaxi = 1504,
+    /// 認可タクシー
+    LicensedTaxi = 1505,
+    /// ハイヤー
+    PrivateHireService = 1506,
+    /// すべてのタクシーサービス
+    AllTaxiServices = 1507,
+    /// その他のサービス
+    MiscellaneousService = 1700,
+    /// 馬車
+    HorseDrawnCarriage = 1702,
+}
+
+impl Serialize for RouteType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for RouteType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u16::deserialize(deserializer).map(RouteType::from_code)
+    }
+}
+
+impl RouteType {
+    /// 経路タイプが属する交通モード. 未定義のコードは拡張経路タイプの百の位で判定する
+    pub fn mode(&self) -> TransitMode {
+        match self.code() {
+            12 | 405 => TransitMode::Monorail,
+            0 | 900..=999 => TransitMode::Tram,
+            1 | 400..=499 => TransitMode::Subway,
+            2 | 100..=199 => TransitMode::Rail,
+            3 | 700..=799 => TransitMode::Bus,
+            4 | 1000..=1099 | 1200..=1299 => TransitMode::Ferry,
+            5 => TransitMode::CableTram,
+            6 | 1300..=1399 => TransitMode::AerialLift,
+            7 | 1400..=1499 => TransitMode::Funicular,
+            11 | 800..=899 => TransitMode::Trolleybus,
+            200..=299 => TransitMode::Coach,
+            1100..=1199 => TransitMode::Air,
+            1500..=1599 => TransitMode::Taxi,
+            _ => TransitMode::Other,
+        }
+    }
+}
+
+/// 交通モード (拡張経路タイプを基本経路タイプ相当にまとめたもの)
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Eq,
+    PartialEq,
+    Clone,
+    Copy,
+    Hash,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TransitMode {
+    /// 路面電車・ライトレール
+    Tram,
+    /// 地下鉄
+    Subway,
+    /// 鉄道
+    Rail,
     /// バス
-    BUS = 3,
+    Bus,
+    /// フェリー・水上交通
+    Ferry,
+    /// ケーブルトラム
+    CableTram,
+    /// ロープウェイ・ゴンドラ
+    AerialLift,
+    /// ケーブルカー
+    Funicular,
+    /// トロリーバス
+    Trolleybus,
+    /// モノレール
+    Monorail,
+    /// 長距離バス
+    Coach,
+    /// 航空
+    Air,
+    /// タクシー
+    Taxi,
+    /// その他
+    Other,
+}
+
+impl TransitMode {
+    /// transfers.txtにmin_transfer_timeの指定がない場合に想定する乗換時間
+    pub fn default_min_transfer_time(&self) -> Second {
+        match self {
+            TransitMode::Bus
+            | TransitMode::Tram
+            | TransitMode::Trolleybus
+            | TransitMode::CableTram
+            | TransitMode::Taxi => 60,
+            TransitMode::Coach | TransitMode::AerialLift | TransitMode::Funicular => 120,
+            TransitMode::Subway | TransitMode::Monorail | TransitMode::Other => 180,
+            TransitMode::Rail => 300,
+            TransitMode::Ferry => 600,
+            TransitMode::Air => 3600,
+        }
+    }
 }
 
 /// 経路情報
@@ -22,9 +341,9 @@ enum RouteType {
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
 pub struct Route {
     /// 経路ID
-    route_id: RouteId,
+    pub route_id: RouteId,
     /// 事業者ID
-    agency_id: AgencyId,
+    pub agency_id: AgencyId,
     /// 経路略称 (ex: 東16)
     /// route_long_nameとどちらか1つは指定必須
     route_short_name: Option<String>,
@@ -34,7 +353,7 @@ pub struct Route {
     /// 経路情報
     route_desc: Option<String>,
     /// 経路タイプ
-    route_type: RouteType,
+    pub route_type: RouteType,
     /// 経路URL (ex: http://tobus.jp/blsys/navi?LCD=&VCD=cslrsi &ECD=picsroute&RTM CD=50)
     route_url: Option<String>,
     /// 経路色 (ex: FFD700)
@@ -43,6 +362,8 @@ pub struct Route {
     route_text_color: Option<Color>,
     /// 路線ID
     jp_parent_route_id: Option<String>,
+    /// ネットワークID (route_networks.txtを使わない場合)
+    pub network_id: Option<NetworkId>,
     // route_sort_order
     // continuous_pickup
     // continuous_drop_off
@@ -71,9 +392,14 @@ impl Table for Route {
             "route_color",
             "route_text_color",
             "jp_parent_route_id",
+            "network_id",
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["route_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         route_id text primary key,
@@ -85,7 +411,87 @@ impl Table for Route {
         route_url text,
         route_color text,
         route_text_color text,
-        jp_parent_route_id text
+        jp_parent_route_id text,
+        network_id text
         "
     }
 }
+
+impl Route {
+    /// 交通モード
+    pub fn mode(&self) -> TransitMode {
+        self.route_type.mode()
+    }
+}
+
+/// scopeの事業者・営業所に該当するrouteを検索する
+/// 営業所はそのrouteに属するtripの営業所で判定する
+pub fn select_routes_by_scope(
+    conn: &mut Connection,
+    scope: &OperatorScope,
+) -> serde_rusqlite::Result<Vec<Route>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT * FROM {routes} r
+WHERE (:agency_id IS NULL OR r.agency_id == :agency_id)
+  AND (:office_id IS NULL OR
+       r.route_id IN (SELECT route_id FROM {trips} WHERE jp_office_id == :office_id))
+",
+            routes = Route::table_name(),
+            trips = Trip::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":agency_id": scope.agency_id,
+        ":office_id": scope.office_id,
+    })?)
+    .collect();
+    result
+}
+
+/// routeで使われている経路タイプを重複なく検索する
+pub fn select_route_types(conn: &mut Connection) -> rusqlite::Result<Vec<RouteType>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "SELECT DISTINCT route_type FROM {routes} ORDER BY route_type",
+            routes = Route::table_name(),
+        )
+        .as_str(),
+    )?;
+    let rows = stmt.query_map(NO_PARAMS, |row| row.get(0).map(RouteType::from_code))?;
+    rows.collect()
+}
+
+/// stopを通るtripのrouteの経路タイプを、stopと経路タイプの組で重複なく検索する
+pub fn select_route_types_by_stop(
+    conn: &mut Connection,
+) -> rusqlite::Result<Vec<(StopId, RouteType)>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT DISTINCT
+  st.stop_id,
+  r.route_type
+FROM
+  {stop_times} st
+    INNER JOIN {trips} t
+    ON st.trip_id == t.trip_id
+    INNER JOIN {routes} r
+    ON t.route_id == r.route_id
+ORDER BY
+  st.stop_id, r.route_type
+",
+            stop_times = StopTime::table_name(),
+            trips = Trip::table_name(),
+            routes = Route::table_name(),
+        )
+        .as_str(),
+    )?;
+    let rows = stmt.query_map(NO_PARAMS, |row| {
+        Ok((row.get(0)?, RouteType::from_code(row.get(1)?)))
+    })?;
+    rows.collect()
+}
@@ -42,6 +42,10 @@ impl Table for RouteJp {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["route_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         route_id text primary key,
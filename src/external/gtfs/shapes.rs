@@ -45,6 +45,10 @@ impl Table for Shape {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["shape_id", "shape_pt_sequence"]
+    }
+
     fn create_sql() -> &'static str {
         "
         shape_id text not null,
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::areas::AreaId;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfscsv::GTFSFile;
+use crate::external::gtfsdb::Table;
+
+/// エリアに属するstop情報 (Fares v2)
+/// https://developers.google.com/transit/gtfs/reference#stop_areastxt
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+pub struct StopArea {
+    /// エリアID
+    pub area_id: AreaId,
+    /// 停留所・標柱ID
+    pub stop_id: StopId,
+}
+
+impl GTFSFile for StopArea {
+    fn file_name() -> &'static str {
+        "stop_areas.txt"
+    }
+}
+
+impl Table for StopArea {
+    fn table_name() -> &'static str {
+        "stop_areas"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["area_id", "stop_id"]
+    }
+
+    fn primary_key_names() -> &'static [&'static str] {
+        &["area_id", "stop_id"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        area_id text not null,
+        stop_id text not null,
+        PRIMARY KEY(area_id, stop_id)
+        "
+    }
+}
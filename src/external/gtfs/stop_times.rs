@@ -1,9 +1,14 @@
+use std::rc::Rc;
+
+use itertools::Itertools;
+use rusqlite::{named_params, types::Value, Connection};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use serde_rusqlite::from_rows;
 
 use crate::external::gtfs::stops::StopId;
 use crate::external::gtfs::trips::TripId;
-use crate::external::gtfs::{Meter, Sequence};
+use crate::external::gtfs::{GtfsTime, Meter, Sequence};
 use crate::external::gtfscsv::GTFSFile;
 use crate::external::gtfsdb::Table;
 
@@ -38,15 +43,15 @@ enum DropOffType {
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
 pub struct StopTime {
     /// 便ID
-    trip_id: TripId,
-    /// 到着時刻 (ex: 7:00:00)
-    arrival_time: String,
-    /// 出発時刻 (ex: 7:00:00)
-    departure_time: String,
+    pub trip_id: TripId,
+    /// 到着時刻 (ex: 7:00:00) タイムポイント以外では空欄を許容
+    pub arrival_time: Option<GtfsTime>,
+    /// 出発時刻 (ex: 7:00:00) タイムポイント以外では空欄を許容
+    pub departure_time: Option<GtfsTime>,
     /// 標柱ID (location_type=0のstopのみ結合可) (ex: 100_10)
-    stop_id: StopId,
+    pub stop_id: StopId,
     /// 通過順位 (ex: 0)
-    stop_sequence: Sequence,
+    pub stop_sequence: Sequence,
     /// 停留所行先 (ex: 東京ビッグサイト（月島駅経由）)
     stop_headsign: Option<String>,
     /// 乗車区分 (ex: 0)
@@ -54,9 +59,12 @@ pub struct StopTime {
     /// 降車区分 (ex: 0)
     drop_off_type: Option<DropOffType>,
     /// 通算距離 (メートル) (ex: 0)
-    shape_dist_traveled: Option<Meter>,
+    pub shape_dist_traveled: Option<Meter>,
     /// 発着時間精度 (日本では使用しない)
     timepoint: Option<i32>,
+    /// 空欄だった時刻を補間したか (GTFSには存在しない独自項目)
+    #[serde(default)]
+    pub interpolated: bool,
 }
 
 impl GTFSFile for StopTime {
@@ -82,22 +90,65 @@ impl Table for StopTime {
             "drop_off_type",
             "shape_dist_traveled",
             "timepoint",
+            "interpolated",
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["trip_id", "stop_sequence"]
+    }
+
     fn create_sql() -> &'static str {
-        "
+        concat!(
+            "
         trip_id text,
-        arrival_time datetime not null,
-        departure_time datetime not null,
+        arrival_time text,
+        departure_time text,
+        arrival_time_seconds int GENERATED ALWAYS AS (",
+            crate::gtfs_time_seconds_sql!("arrival_time"),
+            ") STORED,
+        departure_time_seconds int GENERATED ALWAYS AS (",
+            crate::gtfs_time_seconds_sql!("departure_time"),
+            ") STORED,
         stop_id text not null,
         stop_sequence int,
         stop_headsign text,
         pickup_type int,
         drop_off_type int,
-        shape_dist_traveled int,
+        shape_dist_traveled double,
         timepoint int,
+        interpolated int not null,
         PRIMARY KEY(trip_id, stop_sequence)
         "
+        )
     }
 }
+
+/// tripのいずれかに属するstop_timeを検索する
+/// 返却結果のソートは trip_id, stop_sequence を保証する
+pub fn select_stop_times_by_trip_ids(
+    conn: &mut Connection,
+    trip_ids: Vec<TripId>,
+) -> serde_rusqlite::Result<Vec<StopTime>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT
+  {columns}
+FROM
+  {stop_times}
+WHERE
+  trip_id IN rarray(:trip_ids)
+ORDER BY
+  trip_id, stop_sequence
+",
+            columns = StopTime::column_names().join(", "),
+            stop_times = StopTime::table_name(),
+        )
+        .as_str(),
+    )?;
+
+    let trip_ids = Rc::new(trip_ids.into_iter().map(Value::from).collect_vec());
+    let result = from_rows(stmt.query_named(named_params! {":trip_ids": trip_ids})?).collect();
+    result
+}
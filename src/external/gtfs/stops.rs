@@ -1,9 +1,14 @@
-use rusqlite::{named_params, Connection};
+use itertools::Itertools;
+use rusqlite::{named_params, types::Value, Connection};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use serde_rusqlite::from_rows;
+use std::rc::Rc;
 
-use crate::external::gtfs::{Latitude, Longitude, Timezone, Url};
+use crate::external::gtfs::levels::LevelId;
+use crate::external::gtfs::{
+    great_circle_distance, Latitude, Longitude, Paging, SortColumns, Timezone, Url,
+};
 use crate::external::gtfscsv::GTFSFile;
 use crate::external::gtfsdb::Table;
 
@@ -17,11 +22,17 @@ pub type PlatformCode = String;
 /// 停留所・標柱区分
 #[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
 #[repr(u8)]
-enum LocationType {
-    /// 標柱
+pub enum LocationType {
+    /// 標柱 (駅の場合はのりば)
     Pole = 0,
-    /// 停留所
+    /// 停留所 (駅)
     Stop = 1,
+    /// 出入口
+    Entrance = 2,
+    /// 一般ノード (通路の分岐点など)
+    GenericNode = 3,
+    /// 乗車エリア (のりばの中の乗車位置)
+    BoardingArea = 4,
 }
 
 /// 停留所・標柱情報
@@ -30,7 +41,7 @@ enum LocationType {
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Eq, Hash)]
 pub struct Stop {
     /// 停留所・標柱ID
-    stop_id: StopId,
+    pub stop_id: StopId,
     /// 停留所・標柱番号
     stop_code: Option<String>,
     /// 停留所・標柱名称 (ex: ①東京駅八重洲口 ②東京駅八重洲口)
@@ -46,20 +57,36 @@ pub struct Stop {
     /// 停留所・標柱URL
     stop_url: Option<Url>,
     /// 停留所・標柱区分
-    location_type: Option<LocationType>,
+    pub location_type: Option<LocationType>,
     /// 親駅情報
     /// location_typeが
     ///   - 0だと任意
     ///   - 1だと利用不可
     ///   - 2～4だと必須
-    parent_station: Option<StopId>,
+    pub parent_station: Option<StopId>,
     /// タイムゾーン (日本ではagency_timezoneが優先されるため不要)
     stop_timezone: Option<Timezone>,
     /// 車椅子情報 (日本のバスでは設定しなそうなのでenum定義しない)
     wheelchair_boarding: Option<u32>,
     /// のりば情報 (ex: ①※設定なし ②10)
     platform_code: Option<PlatformCode>,
-    // level_id: Option<LevelId>
+    /// 階層ID
+    pub level_id: Option<LevelId>,
+}
+
+impl Stop {
+    /// 停留所・標柱区分 (未指定の場合は標柱)
+    pub fn location_type(&self) -> LocationType {
+        self.location_type.clone().unwrap_or(LocationType::Pole)
+    }
+
+    /// 他の停留所・標柱までの大円距離 (メートル)
+    pub fn distance(&self, other: &Stop) -> f64 {
+        great_circle_distance(
+            (self.stop_lat, self.stop_lon),
+            (other.stop_lat, other.stop_lon),
+        )
+    }
 }
 
 impl GTFSFile for Stop {
@@ -88,9 +115,14 @@ impl Table for Stop {
             "stop_timezone",
             "wheelchair_boarding",
             "platform_code",
+            "level_id",
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["stop_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         stop_id text primary key,
@@ -105,18 +137,35 @@ impl Table for Stop {
         parent_station text,
         stop_timezone text,
         wheelchair_boarding int,
-        platform_code text
+        platform_code text,
+        level_id text
         "
     }
 }
 
-/// stop_nameの部分一致で検索する
+/// select_stops_by_nameで並び替えられる項目
+pub const STOP_SORT_COLUMNS: SortColumns = &[
+    ("stop_id", "stop_id"),
+    ("stop_code", "stop_code"),
+    ("stop_name", "stop_name"),
+    ("stop_lat", "stop_lat"),
+    ("stop_lon", "stop_lon"),
+    ("zone_id", "zone_id"),
+    ("location_type", "location_type"),
+    ("parent_station", "parent_station"),
+    ("platform_code", "platform_code"),
+    ("level_id", "level_id"),
+];
+
+/// stop_nameの部分一致で検索する. pagingの範囲だけを返す
 pub fn select_stops_by_name(
     conn: &mut Connection,
     word: String,
+    paging: &Paging,
 ) -> serde_rusqlite::Result<Vec<Stop>> {
     let mut stmt = conn.prepare(
-        "
+        format!(
+            "
 SELECT
   stop_id,
   stop_code,
@@ -130,14 +179,102 @@ SELECT
   parent_station,
   stop_timezone,
   wheelchair_boarding,
-  platform_code
+  platform_code,
+  level_id
 FROM stops
 WHERE stop_name like :word
+ORDER BY
+  {}
+LIMIT :limit OFFSET :offset
+",
+            paging.order_by("stop_id")
+        )
+        .as_str(),
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":word": format!("%{}%", word),
+        ":limit": paging.sql_limit(),
+        ":offset": paging.offset,
+    })?)
+    .collect();
+    result
+}
+
+/// select_stops_by_nameでページングしない場合の件数
+pub fn count_stops_by_name(conn: &mut Connection, word: String) -> rusqlite::Result<u32> {
+    let mut stmt = conn.prepare("SELECT count(*) FROM stops WHERE stop_name like :word")?;
+    stmt.query_row_named(named_params! {":word": format!("%{}%", word)}, |row| {
+        row.get(0)
+    })
+}
+
+/// stop_idに一致するstopを取得する
+pub fn select_stop_by_id(
+    conn: &mut Connection,
+    stop_id: StopId,
+) -> serde_rusqlite::Result<Option<Stop>> {
+    let mut stmt = conn.prepare(
+        "
+SELECT
+  stop_id,
+  stop_code,
+  stop_name,
+  stop_desc,
+  stop_lat,
+  stop_lon,
+  zone_id,
+  stop_url,
+  location_type,
+  parent_station,
+  stop_timezone,
+  wheelchair_boarding,
+  platform_code,
+  level_id
+FROM stops
+WHERE stop_id = :stop_id
+",
+    )?;
+
+    let result = from_rows(stmt.query_named(named_params! {
+        ":stop_id": stop_id
+    })?)
+    .next()
+    .transpose();
+    result
+}
+
+/// parent_stationがいずれかのstop_idに一致するstopを検索する
+pub fn select_stops_by_parents(
+    conn: &mut Connection,
+    parent_stations: Vec<StopId>,
+) -> serde_rusqlite::Result<Vec<Stop>> {
+    let mut stmt = conn.prepare(
+        "
+SELECT
+  stop_id,
+  stop_code,
+  stop_name,
+  stop_desc,
+  stop_lat,
+  stop_lon,
+  zone_id,
+  stop_url,
+  location_type,
+  parent_station,
+  stop_timezone,
+  wheelchair_boarding,
+  platform_code,
+  level_id
+FROM stops
+WHERE parent_station in rarray(:parent_stations)
+ORDER BY stop_id
 ",
     )?;
 
+    let ids = Rc::new(parent_stations.into_iter().map(Value::from).collect_vec());
     let result = from_rows(stmt.query_named(named_params! {
-        ":word": format!("%{}%", word)
+        ":parent_stations": ids
     })?)
     .collect();
     result
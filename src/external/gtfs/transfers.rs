@@ -9,7 +9,7 @@ use crate::external::gtfsdb::Table;
 /// 利用タイプ
 #[derive(Debug, Deserialize_repr, Serialize_repr, Eq, PartialEq, Clone, Hash)]
 #[repr(u8)]
-enum TransferType {
+pub enum TransferType {
     /// 2つの経路間の推奨乗換地点
     Recommended = 0,
     /// 2つの経路間で時間に余裕のある乗換地点
@@ -25,13 +25,25 @@ enum TransferType {
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
 pub struct Transfer {
     /// 乗換元標柱ID
-    from_stop_id: StopId,
+    pub from_stop_id: StopId,
     /// 乗換先標柱ID
-    to_stop_id: StopId,
+    pub to_stop_id: StopId,
     /// 乗換タイプ
-    transfer_type: TransferType,
+    pub transfer_type: TransferType,
     /// 乗換時間
-    min_transfer_time: Option<Second>,
+    pub min_transfer_time: Option<Second>,
+}
+
+impl Transfer {
+    /// 徒歩での乗換として生成する
+    pub fn walking(from_stop_id: StopId, to_stop_id: StopId, min_transfer_time: Second) -> Self {
+        Self {
+            from_stop_id,
+            to_stop_id,
+            transfer_type: TransferType::Barely,
+            min_transfer_time: Some(min_transfer_time),
+        }
+    }
 }
 
 impl GTFSFile for Transfer {
@@ -54,6 +66,10 @@ impl Table for Transfer {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["from_stop_id", "to_stop_id"]
+    }
+
     fn create_sql() -> &'static str {
         "
         from_stop_id text not null,
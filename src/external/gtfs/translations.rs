@@ -1,12 +1,15 @@
+use rusqlite::{Connection, NO_PARAMS};
 use serde::{Deserialize, Serialize};
+use strum_macros::Display;
 
 use crate::external::gtfs::legacy_translations::LegacyTranslation;
 use crate::external::gtfs::Lang;
 use crate::external::gtfscsv::GTFSFile;
 use crate::external::gtfsdb::Table;
 
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash, Display)]
 #[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
 pub enum TranslatableTableName {
     Agency,
     Stops,
@@ -61,6 +64,10 @@ impl Table for Translation {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &[]
+    }
+
     fn create_sql() -> &'static str {
         "
         table_name text not null,
@@ -75,63 +82,57 @@ impl Table for Translation {
 }
 
 impl Translation {
-    /// 出現頻度の高いもののみ
-    pub fn from_legacy(regacy: &LegacyTranslation) -> Vec<Translation> {
-        vec![
-            Translation {
-                table_name: TranslatableTableName::Stops,
-                field_name: "stop_name".to_string(),
-                language: regacy.lang.clone(),
-                translation: regacy.translation.clone(),
-                record_id: None,
-                record_sub_id: None,
-                field_value: Some(regacy.trans_id.clone()),
-            },
-            Translation {
-                table_name: TranslatableTableName::Routes,
-                field_name: "route_short_name".to_string(),
-                language: regacy.lang.clone(),
-                translation: regacy.translation.clone(),
-                record_id: None,
-                record_sub_id: None,
-                field_value: Some(regacy.trans_id.clone()),
-            },
-            Translation {
-                table_name: TranslatableTableName::Routes,
-                field_name: "route_long_name".to_string(),
-                language: regacy.lang.clone(),
-                translation: regacy.translation.clone(),
-                record_id: None,
-                record_sub_id: None,
-                field_value: Some(regacy.trans_id.clone()),
-            },
-            Translation {
-                table_name: TranslatableTableName::Trips,
-                field_name: "trip_headsign".to_string(),
-                language: regacy.lang.clone(),
-                translation: regacy.translation.clone(),
-                record_id: None,
-                record_sub_id: None,
-                field_value: Some(regacy.trans_id.clone()),
-            },
-            Translation {
-                table_name: TranslatableTableName::Trips,
-                field_name: "trip_short_name".to_string(),
-                language: regacy.lang.clone(),
-                translation: regacy.translation.clone(),
-                record_id: None,
-                record_sub_id: None,
-                field_value: Some(regacy.trans_id.clone()),
-            },
-            Translation {
-                table_name: TranslatableTableName::StopTimes,
-                field_name: "stop_headsign".to_string(),
-                language: regacy.lang.clone(),
-                translation: regacy.translation.clone(),
-                record_id: None,
-                record_sub_id: None,
-                field_value: Some(regacy.trans_id.clone()),
-            },
-        ]
+    /// 古い仕様の翻訳を、trans_idが実際に使われているテーブル・フィールドの翻訳に変換する
+    pub fn from_legacy(
+        legacy: &LegacyTranslation,
+        table_name: &TranslatableTableName,
+        field_name: &str,
+    ) -> Translation {
+        Translation {
+            table_name: table_name.clone(),
+            field_name: field_name.to_string(),
+            language: legacy.lang.clone(),
+            translation: legacy.translation.clone(),
+            record_id: None,
+            record_sub_id: None,
+            field_value: Some(legacy.trans_id.clone()),
+        }
     }
 }
+
+/// 古い仕様のtrans_idが使われうるテーブルとフィールドの組み合わせ
+pub const LEGACY_TRANSLATABLE_FIELDS: &[(TranslatableTableName, &str)] = &[
+    (TranslatableTableName::Agency, "agency_name"),
+    (TranslatableTableName::Stops, "stop_name"),
+    (TranslatableTableName::Routes, "route_short_name"),
+    (TranslatableTableName::Routes, "route_long_name"),
+    (TranslatableTableName::Trips, "trip_headsign"),
+    (TranslatableTableName::Trips, "trip_short_name"),
+    (TranslatableTableName::StopTimes, "stop_headsign"),
+    (TranslatableTableName::FeedInfo, "feed_publisher_name"),
+];
+
+/// テーブルのフィールドに含まれる値を重複なく取得する
+pub fn select_distinct_values(
+    conn: &mut Connection,
+    table_name: &TranslatableTableName,
+    field_name: &str,
+) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        format!(
+            "
+SELECT DISTINCT
+  {field}
+FROM
+  {table}
+WHERE {field} IS NOT NULL
+",
+            field = field_name,
+            table = table_name,
+        )
+        .as_str(),
+    )?;
+
+    let result = stmt.query_map(NO_PARAMS, |row| row.get(0))?.collect();
+    result
+}
@@ -1,13 +1,19 @@
-use rusqlite::{named_params, Connection};
+use std::rc::Rc;
+
+use itertools::Itertools;
+use rusqlite::{named_params, types::Value, Connection, ToSql, NO_PARAMS};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use serde_rusqlite::from_rows;
 
 use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::extended::service_routes::ServiceRouteId;
+use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
 use crate::external::gtfs::office_jp::JpOfficeId;
-use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::routes::{Route, RouteId, RouteType};
+use crate::external::gtfs::stop_times::StopTime;
 use crate::external::gtfs::stops::StopId;
-use crate::external::gtfs::DirectionId;
+use crate::external::gtfs::{DirectionId, OperatorScope, Paging, Second, SortColumns};
 use crate::external::gtfscsv::GTFSFile;
 use crate::external::gtfsdb::Table;
 
@@ -41,11 +47,11 @@ pub type TripId = String;
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Hash)]
 pub struct Trip {
     /// 経路ID
-    route_id: RouteId,
+    pub route_id: RouteId,
     /// 運行日ID
     service_id: ServiceId,
     /// 便ID
-    trip_id: TripId,
+    pub trip_id: TripId,
     /// 便行き先 (ex: 東京ビッグサイト（月島駅経由）)
     trip_headsign: Option<String>,
     /// 便名称
@@ -65,7 +71,7 @@ pub struct Trip {
     /// 便記号
     jp_trip_desc_symbol: Option<String>,
     /// 営業所ID
-    jp_office_id: Option<JpOfficeId>,
+    pub jp_office_id: Option<JpOfficeId>,
 }
 
 impl GTFSFile for Trip {
@@ -97,6 +103,10 @@ impl Table for Trip {
         ]
     }
 
+    fn primary_key_names() -> &'static [&'static str] {
+        &["trip_id"]
+    }
+
     // 外部キー制約はあえて付けない。季節便などが含まれるとエラーになるため
     fn create_sql() -> &'static str {
         "
@@ -117,34 +127,172 @@ impl Table for Trip {
     }
 }
 
-/// stopを通るtripを検索する
-pub fn select_trips_by_stop(
+/// tripの絞り込み条件 (未指定の項目では絞り込まない)
+#[derive(Debug, Default, Clone)]
+pub struct TripQuery {
+    /// 停車するstop
+    pub stop_id: Option<StopId>,
+    pub route_id: Option<RouteId>,
+    /// いずれかの経路タイプ
+    pub route_types: Option<Vec<RouteType>>,
+    pub scope: OperatorScope,
+    /// いずれかのservice_id
+    pub service_ids: Option<Vec<ServiceId>>,
+    pub direction_id: Option<DirectionId>,
+    /// 属するサービスルート (上下区分は問わない)
+    pub service_route_id: Option<ServiceRouteId>,
+    /// 出発時刻の下限. stop_idを指定した場合はそのstop、しない場合は始発stopの出発時刻
+    pub from_seconds: Option<Second>,
+    /// 出発時刻の上限. 対象のstopはfrom_secondsと同じ
+    pub to_seconds: Option<Second>,
+}
+
+/// select_tripsで並び替えられる項目
+pub const TRIP_SORT_COLUMNS: SortColumns = &[
+    ("route_id", "t.route_id"),
+    ("service_id", "t.service_id"),
+    ("trip_id", "t.trip_id"),
+    ("trip_headsign", "t.trip_headsign"),
+    ("trip_short_name", "t.trip_short_name"),
+    ("direction_id", "t.direction_id"),
+    ("block_id", "t.block_id"),
+    ("shape_id", "t.shape_id"),
+    ("jp_office_id", "t.jp_office_id"),
+];
+
+/// select_tripsとcount_tripsで共通のFROM句とWHERE句
+fn trips_from_sql() -> String {
+    format!(
+        "
+FROM
+  {trips} t
+    INNER JOIN {routes} r
+    ON t.route_id == r.route_id
+WHERE
+  (:route_id IS NULL OR t.route_id == :route_id)
+  AND (:all_route_types OR r.route_type IN rarray(:route_types))
+  AND (:agency_id IS NULL OR r.agency_id == :agency_id)
+  AND (:office_id IS NULL OR t.jp_office_id == :office_id)
+  AND (:all_services OR t.service_id IN rarray(:service_ids))
+  AND (:direction_id IS NULL OR t.direction_id == :direction_id)
+  AND (:service_route_id IS NULL OR EXISTS (
+    SELECT 1 FROM {trips2service_routes} t2sr
+    WHERE t2sr.trip_id == t.trip_id AND t2sr.service_route_id == :service_route_id
+  ))
+  AND (:stop_id IS NULL AND :from_seconds IS NULL AND :to_seconds IS NULL OR EXISTS (
+    SELECT 1 FROM {stop_times} st
+    WHERE st.trip_id == t.trip_id
+      AND (st.stop_id == :stop_id
+        OR :stop_id IS NULL
+          AND st.stop_sequence == (SELECT min(stop_sequence) FROM {stop_times} WHERE trip_id == t.trip_id))
+      AND (:from_seconds IS NULL OR st.departure_time_seconds >= :from_seconds)
+      AND (:to_seconds IS NULL OR st.departure_time_seconds <= :to_seconds)
+  ))
+",
+        trips = Trip::table_name(),
+        routes = Route::table_name(),
+        trips2service_routes = Trip2ServiceRoute::table_name(),
+        stop_times = StopTime::table_name(),
+    )
+}
+
+/// trips_from_sqlに渡すパラメータでfを呼び出す
+fn with_trip_params<F, T>(query: &TripQuery, f: F) -> T
+where
+    F: FnOnce(&[(&str, &dyn ToSql)]) -> T,
+{
+    let all_route_types = query.route_types.is_none();
+    let route_types = Rc::new(
+        query
+            .route_types
+            .iter()
+            .flatten()
+            .map(|x| Value::from(x.code() as i64))
+            .collect_vec(),
+    );
+    let all_services = query.service_ids.is_none();
+    let service_ids = Rc::new(
+        query
+            .service_ids
+            .iter()
+            .flatten()
+            .cloned()
+            .map(Value::from)
+            .collect_vec(),
+    );
+    f(named_params! {
+        ":route_id": query.route_id,
+        ":all_route_types": all_route_types,
+        ":route_types": route_types,
+        ":agency_id": query.scope.agency_id,
+        ":office_id": query.scope.office_id,
+        ":all_services": all_services,
+        ":service_ids": service_ids,
+        ":direction_id": query.direction_id.clone().map(|x| x as u8),
+        ":service_route_id": query.service_route_id,
+        ":stop_id": query.stop_id,
+        ":from_seconds": query.from_seconds,
+        ":to_seconds": query.to_seconds,
+    })
+}
+
+/// 条件に一致するtripを検索する. 同じstopに2度停車するtripも1件のみ返す
+/// 返却結果のソートは paging.sort, 始発の出発時刻, trip_id を保証する
+pub fn select_trips(
     conn: &mut Connection,
-    stop_id: StopId,
+    query: &TripQuery,
+    paging: &Paging,
 ) -> serde_rusqlite::Result<Vec<Trip>> {
     let mut stmt = conn.prepare(
-        "
+        format!(
+            "
 SELECT
-  t.route_id,
-  t.service_id,
-  t.trip_id,
-  t.trip_headsign,
-  t.trip_short_name,
-  t.direction_id,
-  t.block_id,
-  t.shape_id,
-  t.wheelchair_accessible,
-  t.bikes_allowed
-FROM
-  trips t
-    INNER JOIN stop_times st ON t.trip_id = st.trip_id
-WHERE st.stop_id = :stop_id
+  {columns}
+{from}
+ORDER BY
+  {order}
+LIMIT :limit OFFSET :offset
 ",
+            columns = Trip::column_names()
+                .iter()
+                .map(|x| format!("t.{}", x))
+                .join(", "),
+            from = trips_from_sql(),
+            order = paging.order_by(&format!(
+                "(SELECT min(departure_time_seconds) FROM {} WHERE trip_id == t.trip_id), t.trip_id",
+                StopTime::table_name()
+            )),
+        )
+        .as_str(),
     )?;
 
-    let result = from_rows(stmt.query_named(named_params! {
-        ":stop_id": stop_id
-    })?)
-    .collect();
-    result
+    let (limit, offset) = (paging.sql_limit(), paging.offset);
+    with_trip_params(query, |params| {
+        let mut params = params.to_vec();
+        params.push((":limit", &limit));
+        params.push((":offset", &offset));
+        from_rows(stmt.query_named(&params)?).collect()
+    })
+}
+
+/// select_tripsでページングしない場合の件数
+pub fn count_trips(conn: &mut Connection, query: &TripQuery) -> rusqlite::Result<u32> {
+    let mut stmt = conn.prepare(format!("SELECT count(*) {}", trips_from_sql()).as_str())?;
+    with_trip_params(query, |params| {
+        stmt.query_row_named(params, |row| row.get(0))
+    })
+}
+
+/// stop_timesが1つもないtripのIDを検索する
+pub fn select_trip_ids_without_stop_times(conn: &mut Connection) -> rusqlite::Result<Vec<TripId>> {
+    let mut stmt = conn.prepare(
+        "
+SELECT t.trip_id
+FROM trips t
+WHERE NOT EXISTS (SELECT 1 FROM stop_times st WHERE st.trip_id == t.trip_id)
+ORDER BY t.trip_id
+",
+    )?;
+    let rows = stmt.query_map(NO_PARAMS, |row| row.get(0))?;
+    rows.collect()
 }
@@ -1,3 +1,4 @@
+use std::fs;
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
@@ -5,18 +6,31 @@ use serde::de::DeserializeOwned;
 
 use crate::external::gtfs::agency::Agency;
 use crate::external::gtfs::agency_jp::AgencyJp;
+use crate::external::gtfs::areas::Area;
+use crate::external::gtfs::attributions::Attribution;
+use crate::external::gtfs::booking_rules::BookingRule;
 use crate::external::gtfs::calendar::Calendar;
 use crate::external::gtfs::calendar_dates::CalendarDate;
+use crate::external::gtfs::extended::nodes::NodeMapping;
 use crate::external::gtfs::extended::service_route_identity::ServiceRouteIdentity;
 use crate::external::gtfs::fare_attributes::FareAttribute;
+use crate::external::gtfs::fare_leg_rules::FareLegRule;
+use crate::external::gtfs::fare_media::FareMedia;
+use crate::external::gtfs::fare_products::FareProduct;
 use crate::external::gtfs::fare_rules::FareRule;
+use crate::external::gtfs::fare_transfer_rules::FareTransferRule;
 use crate::external::gtfs::feed_info::Feed;
 use crate::external::gtfs::frequencies::Frequency;
 use crate::external::gtfs::legacy_translations::LegacyTranslation;
+use crate::external::gtfs::levels::Level;
+use crate::external::gtfs::networks::Network;
 use crate::external::gtfs::office_jp::OfficeJp;
+use crate::external::gtfs::pathways::Pathway;
+use crate::external::gtfs::route_networks::RouteNetwork;
 use crate::external::gtfs::routes::Route;
 use crate::external::gtfs::routes_jp::RouteJp;
 use crate::external::gtfs::shapes::Shape;
+use crate::external::gtfs::stop_areas::StopArea;
 use crate::external::gtfs::stop_times::StopTime;
 use crate::external::gtfs::stops::Stop;
 use crate::external::gtfs::transfers::Transfer;
@@ -24,7 +38,7 @@ use crate::external::gtfs::translations::Translation;
 use crate::external::gtfs::trips::Trip;
 use crate::external::gtfs::GtfsCsvTrait;
 use crate::io;
-use crate::io::Format;
+use crate::io::{Format, RawRecord};
 
 pub struct GtfsCsv {
     gtfs_dir: PathBuf,
@@ -41,6 +55,13 @@ where
     io::read::<T>(&gtfs_dir.join(T::file_name()), &Format::Csv)
 }
 
+fn load_gtfs_headers<T>(gtfs_dir: &Path) -> Result<Vec<String>>
+where
+    T: GTFSFile,
+{
+    io::read_headers(&gtfs_dir.join(T::file_name()), &Format::Csv)
+}
+
 fn has_gtfs<T>(gtfs_dir: &Path) -> bool
 where
     T: GTFSFile,
@@ -153,6 +174,102 @@ impl GtfsCsvTrait for GtfsCsv {
         has_gtfs::<Transfer>(&self.gtfs_dir)
     }
 
+    fn load_levels(&mut self) -> Result<Vec<Level>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_levels(&mut self) -> bool {
+        has_gtfs::<Level>(&self.gtfs_dir)
+    }
+
+    fn load_pathways(&mut self) -> Result<Vec<Pathway>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_pathways(&mut self) -> bool {
+        has_gtfs::<Pathway>(&self.gtfs_dir)
+    }
+
+    fn load_attributions(&mut self) -> Result<Vec<Attribution>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_attributions(&mut self) -> bool {
+        has_gtfs::<Attribution>(&self.gtfs_dir)
+    }
+
+    fn load_fare_media(&mut self) -> Result<Vec<FareMedia>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_fare_media(&mut self) -> bool {
+        has_gtfs::<FareMedia>(&self.gtfs_dir)
+    }
+
+    fn load_fare_products(&mut self) -> Result<Vec<FareProduct>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_fare_products(&mut self) -> bool {
+        has_gtfs::<FareProduct>(&self.gtfs_dir)
+    }
+
+    fn load_fare_leg_rules(&mut self) -> Result<Vec<FareLegRule>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_fare_leg_rules(&mut self) -> bool {
+        has_gtfs::<FareLegRule>(&self.gtfs_dir)
+    }
+
+    fn load_fare_transfer_rules(&mut self) -> Result<Vec<FareTransferRule>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_fare_transfer_rules(&mut self) -> bool {
+        has_gtfs::<FareTransferRule>(&self.gtfs_dir)
+    }
+
+    fn load_areas(&mut self) -> Result<Vec<Area>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_areas(&mut self) -> bool {
+        has_gtfs::<Area>(&self.gtfs_dir)
+    }
+
+    fn load_stop_areas(&mut self) -> Result<Vec<StopArea>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_stop_areas(&mut self) -> bool {
+        has_gtfs::<StopArea>(&self.gtfs_dir)
+    }
+
+    fn load_networks(&mut self) -> Result<Vec<Network>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_networks(&mut self) -> bool {
+        has_gtfs::<Network>(&self.gtfs_dir)
+    }
+
+    fn load_route_networks(&mut self) -> Result<Vec<RouteNetwork>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_route_networks(&mut self) -> bool {
+        has_gtfs::<RouteNetwork>(&self.gtfs_dir)
+    }
+
+    fn load_booking_rules(&mut self) -> Result<Vec<BookingRule>> {
+        load_gtfs::<_>(&self.gtfs_dir)
+    }
+
+    fn has_booking_rules(&mut self) -> bool {
+        has_gtfs::<BookingRule>(&self.gtfs_dir)
+    }
+
     fn load_feeds(&mut self) -> Result<Vec<Feed>> {
         load_gtfs::<_>(&self.gtfs_dir)
     }
@@ -165,8 +282,45 @@ impl GtfsCsvTrait for GtfsCsv {
         load_gtfs::<_>(&self.gtfs_dir)
     }
 
+    fn is_legacy_translations(&mut self) -> Result<bool> {
+        let headers = load_gtfs_headers::<LegacyTranslation>(&self.gtfs_dir)?;
+        Ok(headers
+            .iter()
+            .any(|x| x == LegacyTranslation::IDENTIFIER_COLUMN))
+    }
+
+    fn has_file(&mut self, file_name: &str) -> bool {
+        self.gtfs_dir.join(file_name).exists()
+    }
+
+    fn load_headers(&mut self, file_name: &str) -> Result<Vec<String>> {
+        io::read_headers(&self.gtfs_dir.join(file_name), &Format::Csv)
+    }
+
+    fn load_raw(&mut self, file_name: &str) -> Result<Vec<RawRecord>> {
+        io::read_raw(&self.gtfs_dir.join(file_name), &Format::Csv)
+    }
+
+    fn list_file_names(&mut self) -> Result<Vec<String>> {
+        let mut file_names = vec![];
+        for entry in fs::read_dir(&self.gtfs_dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().map_or(false, |x| x == "txt") {
+                if let Some(name) = path.file_name().and_then(|x| x.to_str()) {
+                    file_names.push(name.to_string());
+                }
+            }
+        }
+        file_names.sort();
+        Ok(file_names)
+    }
+
     // --- extended ---
-    fn load_service_route_identity(&self, path: &PathBuf) -> Result<Vec<ServiceRouteIdentity>> {
-        io::read::<ServiceRouteIdentity>(path, &Format::Tsv)
+    fn load_service_route_identity(&self, path: &Path) -> Result<Vec<ServiceRouteIdentity>> {
+        io::read::<ServiceRouteIdentity>(path, &Format::from_path(path))
+    }
+
+    fn load_node_mappings(&self, path: &Path) -> Result<Vec<NodeMapping>> {
+        io::read::<NodeMapping>(path, &Format::from_path(path))
     }
 }
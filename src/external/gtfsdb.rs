@@ -1,41 +1,96 @@
-use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::path::Path;
 
 use anyhow::{Context, Result};
+use chrono::NaiveDate;
 use log::{debug, trace};
-use rusqlite::{Connection, NO_PARAMS};
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
 use serde::__private::fmt::Debug;
 use serde_rusqlite::{from_rows, to_params_named};
 
 use crate::external::gtfs::agency::Agency;
 use crate::external::gtfs::agency_jp::AgencyJp;
-use crate::external::gtfs::calendar::Calendar;
+use crate::external::gtfs::areas::Area;
+use crate::external::gtfs::attributions::Attribution;
+use crate::external::gtfs::booking_rules::BookingRule;
+use crate::external::gtfs::calendar::{select_service_ids_by_date, Calendar, ServiceId};
 use crate::external::gtfs::calendar_dates::CalendarDate;
-use crate::external::gtfs::extended::nodes::Node;
+use crate::external::gtfs::extended::agency_details::{
+    count_agency_details, select_agency_details, AgencyDetail,
+};
+use crate::external::gtfs::extended::block_trips::{select_block_trips, BlockTrip};
+use crate::external::gtfs::extended::extras::{
+    add_extras_column, update_extras, ExtraColumns, ExtraRecord,
+};
+use crate::external::gtfs::extended::leg_fares::{count_leg_fares, select_leg_fares, LegFare};
+use crate::external::gtfs::extended::nearby_stops::{
+    count_stops_in_bounds, create_stops_rtree, drop_stops_rtree, insert_stops_rtree,
+    select_stops_in_bounds, NearbyStop, STOPS_RTREE_TABLE_NAME,
+};
+use crate::external::gtfs::extended::node_details::{
+    count_node_departures, count_nodes_by_word, select_node, select_node_departures,
+    select_node_routes, select_node_service_routes, select_node_stops, select_nodes_by_word,
+    NodeDeparture, NodeDepartureQuery,
+};
+use crate::external::gtfs::extended::nodes::{Node, NodeId, Stop2Node};
+use crate::external::gtfs::extended::office_details::{
+    count_office_details, select_office_details, OfficeDetail,
+};
+use crate::external::gtfs::extended::records::{select_records, Record, RecordQuery};
+use crate::external::gtfs::extended::route_details::{
+    count_route_details, select_route_details, select_route_service_routes, select_route_stops,
+    RouteDetail,
+};
+use crate::external::gtfs::extended::service_route_details::{
+    count_service_route_summaries, select_service_route_summaries, select_service_route_trips,
+    ServiceRouteSummary, ServiceRouteTrip,
+};
 use crate::external::gtfs::extended::service_route_identity::{
     select_service_route_identity, ServiceRouteIdentity,
 };
-use crate::external::gtfs::extended::service_routes::ServiceRoute;
+use crate::external::gtfs::extended::service_route_merges::ServiceRouteMerge;
+use crate::external::gtfs::extended::service_route_problems::ServiceRouteProblem;
+use crate::external::gtfs::extended::service_routes::{ServiceRoute, ServiceRouteId};
 use crate::external::gtfs::extended::stop_details::{select_stop_details, StopDetail};
 use crate::external::gtfs::extended::stop_time_details::{
-    select_stop_time_details, select_stop_time_details_by_ids, select_stop_time_details_by_name,
-    StopTimeDetail,
+    count_stop_time_details, select_stop_time_details, StopTimeDetail,
 };
 use crate::external::gtfs::extended::trips2service_routes::Trip2ServiceRoute;
+use crate::external::gtfs::extended::walking_transfers::WalkingTransfer;
 use crate::external::gtfs::fare_attributes::FareAttribute;
+use crate::external::gtfs::fare_leg_rules::FareLegRule;
+use crate::external::gtfs::fare_media::FareMedia;
+use crate::external::gtfs::fare_products::FareProduct;
 use crate::external::gtfs::fare_rules::FareRule;
+use crate::external::gtfs::fare_transfer_rules::FareTransferRule;
 use crate::external::gtfs::feed_info::Feed;
 use crate::external::gtfs::frequencies::Frequency;
 use crate::external::gtfs::legacy_translations::LegacyTranslation;
+use crate::external::gtfs::levels::{select_levels_by_ids, Level, LevelId};
+use crate::external::gtfs::networks::Network;
 use crate::external::gtfs::office_jp::OfficeJp;
-use crate::external::gtfs::routes::Route;
+use crate::external::gtfs::pathways::{select_pathways_by_stops, Pathway};
+use crate::external::gtfs::route_networks::RouteNetwork;
+use crate::external::gtfs::routes::{
+    select_route_types, select_route_types_by_stop, select_routes_by_scope, Route, RouteId,
+    RouteType,
+};
 use crate::external::gtfs::routes_jp::RouteJp;
 use crate::external::gtfs::shapes::Shape;
-use crate::external::gtfs::stop_times::StopTime;
-use crate::external::gtfs::stops::{select_stops_by_name, Stop, StopId};
+use crate::external::gtfs::stop_areas::StopArea;
+use crate::external::gtfs::stop_times::{select_stop_times_by_trip_ids, StopTime};
+use crate::external::gtfs::stops::{
+    count_stops_by_name, select_stop_by_id, select_stops_by_name, select_stops_by_parents, Stop,
+    StopId,
+};
 use crate::external::gtfs::transfers::Transfer;
-use crate::external::gtfs::translations::Translation;
-use crate::external::gtfs::trips::{select_trips_by_stop, Trip, TripId};
-use crate::external::gtfs::GtfsDbTrait;
+use crate::external::gtfs::translations::{
+    select_distinct_values, TranslatableTableName, Translation,
+};
+use crate::external::gtfs::trips::{
+    count_trips, select_trip_ids_without_stop_times, select_trips, Trip, TripId, TripQuery,
+};
+use crate::external::gtfs::{DirectionId, GtfsDbTrait, GtfsTime, OperatorScope, Paging};
 
 pub struct GtfsDb {
     connection: Connection,
@@ -45,6 +100,9 @@ pub trait Table {
     fn table_name() -> &'static str;
     fn column_names() -> &'static [&'static str];
     fn create_sql() -> &'static str;
+
+    /// 主キーのカラム名 (主キーがない場合は空)
+    fn primary_key_names() -> &'static [&'static str];
 }
 
 pub fn init(path: &Path) -> Result<GtfsDb> {
@@ -109,6 +167,34 @@ where
     Ok(())
 }
 
+fn table_exists(conn: &Connection, table_name: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE name == ?",
+        &[table_name],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|x| x > 0)
+}
+
+/// テーブルに存在しないカラムを `table.column` の形式で返す. テーブル自体がない場合はテーブル名のみ返す
+fn missing_columns<T>(conn: &Connection) -> rusqlite::Result<Vec<String>>
+where
+    T: Table,
+{
+    if !table_exists(conn, T::table_name())? {
+        return Ok(vec![T::table_name().to_string()]);
+    }
+    let mut stmt = conn.prepare(format!("PRAGMA table_xinfo({})", T::table_name()).as_str())?;
+    let columns = stmt
+        .query_map(NO_PARAMS, |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<HashSet<_>>>()?;
+    Ok(T::column_names()
+        .iter()
+        .filter(|x| !columns.contains(**x))
+        .map(|x| format!("{}.{}", T::table_name(), x))
+        .collect())
+}
+
 fn select_all<T>(conn: &mut Connection) -> serde_rusqlite::Result<Vec<T>>
 where
     T: serde::de::DeserializeOwned + Table,
@@ -126,8 +212,62 @@ impl GtfsDb {
         Ok(GtfsDb { connection: conn })
     }
 
-    pub fn get_default_path(key: String) -> PathBuf {
-        Path::new("db").join(key).join("gtfs.db")
+    /// 読み取り専用で開く. ファイルが存在しない場合は作らずにエラーとする
+    pub fn open_read_only(db: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            db,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        rusqlite::vtab::array::load_module(&conn)?;
+
+        Ok(GtfsDb { connection: conn })
+    }
+
+    /// create_allで作成するテーブル・カラム (APIが参照しうるすべて) のうち、存在しないものを `table.column` の形式で返す
+    pub fn missing_schema(&mut self) -> Result<Vec<String>> {
+        let mut missing = vec![];
+        missing.extend(missing_columns::<Agency>(&self.connection)?);
+        missing.extend(missing_columns::<AgencyJp>(&self.connection)?);
+        missing.extend(missing_columns::<Stop>(&self.connection)?);
+        missing.extend(missing_columns::<Route>(&self.connection)?);
+        missing.extend(missing_columns::<RouteJp>(&self.connection)?);
+        missing.extend(missing_columns::<Trip>(&self.connection)?);
+        missing.extend(missing_columns::<OfficeJp>(&self.connection)?);
+        missing.extend(missing_columns::<StopTime>(&self.connection)?);
+        missing.extend(missing_columns::<Calendar>(&self.connection)?);
+        missing.extend(missing_columns::<CalendarDate>(&self.connection)?);
+        missing.extend(missing_columns::<FareAttribute>(&self.connection)?);
+        missing.extend(missing_columns::<FareRule>(&self.connection)?);
+        missing.extend(missing_columns::<Shape>(&self.connection)?);
+        missing.extend(missing_columns::<Frequency>(&self.connection)?);
+        missing.extend(missing_columns::<Transfer>(&self.connection)?);
+        missing.extend(missing_columns::<Level>(&self.connection)?);
+        missing.extend(missing_columns::<Pathway>(&self.connection)?);
+        missing.extend(missing_columns::<Attribution>(&self.connection)?);
+        missing.extend(missing_columns::<FareMedia>(&self.connection)?);
+        missing.extend(missing_columns::<FareProduct>(&self.connection)?);
+        missing.extend(missing_columns::<FareLegRule>(&self.connection)?);
+        missing.extend(missing_columns::<FareTransferRule>(&self.connection)?);
+        missing.extend(missing_columns::<Area>(&self.connection)?);
+        missing.extend(missing_columns::<StopArea>(&self.connection)?);
+        missing.extend(missing_columns::<Network>(&self.connection)?);
+        missing.extend(missing_columns::<RouteNetwork>(&self.connection)?);
+        missing.extend(missing_columns::<BookingRule>(&self.connection)?);
+        missing.extend(missing_columns::<Feed>(&self.connection)?);
+        missing.extend(missing_columns::<Translation>(&self.connection)?);
+        // ----------- extended ---------------
+        missing.extend(missing_columns::<Trip2ServiceRoute>(&self.connection)?);
+        missing.extend(missing_columns::<ServiceRoute>(&self.connection)?);
+        missing.extend(missing_columns::<ServiceRouteMerge>(&self.connection)?);
+        missing.extend(missing_columns::<ServiceRouteProblem>(&self.connection)?);
+        missing.extend(missing_columns::<Node>(&self.connection)?);
+        missing.extend(missing_columns::<Stop2Node>(&self.connection)?);
+        missing.extend(missing_columns::<ExtraRecord>(&self.connection)?);
+        missing.extend(missing_columns::<WalkingTransfer>(&self.connection)?);
+        if !table_exists(&self.connection, STOPS_RTREE_TABLE_NAME)? {
+            missing.push(STOPS_RTREE_TABLE_NAME.to_string());
+        }
+        Ok(missing)
     }
 
     /// テストのために使用します. それ以外の用途では適切なインタフェースを作成してください
@@ -158,12 +298,30 @@ impl GtfsDbTrait for GtfsDb {
         create::<Shape>(&self.connection)?;
         create::<Frequency>(&self.connection)?;
         create::<Transfer>(&self.connection)?;
+        create::<Level>(&self.connection)?;
+        create::<Pathway>(&self.connection)?;
+        create::<Attribution>(&self.connection)?;
+        create::<FareMedia>(&self.connection)?;
+        create::<FareProduct>(&self.connection)?;
+        create::<FareLegRule>(&self.connection)?;
+        create::<FareTransferRule>(&self.connection)?;
+        create::<Area>(&self.connection)?;
+        create::<StopArea>(&self.connection)?;
+        create::<Network>(&self.connection)?;
+        create::<RouteNetwork>(&self.connection)?;
+        create::<BookingRule>(&self.connection)?;
         create::<Feed>(&self.connection)?;
         create::<Translation>(&self.connection)?;
         // ----------- extended ---------------
         create::<Trip2ServiceRoute>(&self.connection)?;
         create::<ServiceRoute>(&self.connection)?;
+        create::<ServiceRouteMerge>(&self.connection)?;
+        create::<ServiceRouteProblem>(&self.connection)?;
         create::<Node>(&self.connection)?;
+        create::<Stop2Node>(&self.connection)?;
+        create::<ExtraRecord>(&self.connection)?;
+        create::<WalkingTransfer>(&self.connection)?;
+        create_stops_rtree(&self.connection)?;
         Ok(())
     }
 
@@ -184,12 +342,30 @@ impl GtfsDbTrait for GtfsDb {
         drop::<Shape>(&self.connection)?;
         drop::<Frequency>(&self.connection)?;
         drop::<Transfer>(&self.connection)?;
+        drop::<Level>(&self.connection)?;
+        drop::<Pathway>(&self.connection)?;
+        drop::<Attribution>(&self.connection)?;
+        drop::<FareMedia>(&self.connection)?;
+        drop::<FareProduct>(&self.connection)?;
+        drop::<FareLegRule>(&self.connection)?;
+        drop::<FareTransferRule>(&self.connection)?;
+        drop::<Area>(&self.connection)?;
+        drop::<StopArea>(&self.connection)?;
+        drop::<Network>(&self.connection)?;
+        drop::<RouteNetwork>(&self.connection)?;
+        drop::<BookingRule>(&self.connection)?;
         drop::<Feed>(&self.connection)?;
         drop::<Translation>(&self.connection)?;
         // ----------- extended ---------------
         drop::<Trip2ServiceRoute>(&self.connection)?;
         drop::<ServiceRoute>(&self.connection)?;
+        drop::<ServiceRouteMerge>(&self.connection)?;
+        drop::<ServiceRouteProblem>(&self.connection)?;
         drop::<Node>(&self.connection)?;
+        drop::<Stop2Node>(&self.connection)?;
+        drop::<ExtraRecord>(&self.connection)?;
+        drop::<WalkingTransfer>(&self.connection)?;
+        drop_stops_rtree(&self.connection)?;
         Ok(())
     }
 
@@ -205,16 +381,39 @@ impl GtfsDbTrait for GtfsDb {
         insert(&mut self.connection, stops)
     }
 
-    fn select_stops(&mut self, word: String) -> Result<Vec<Stop>> {
-        select_stops_by_name(&mut self.connection, word).context("Fail to select_stops_by_name")
+    fn select_stops(&mut self, word: String, paging: &Paging) -> Result<Vec<Stop>> {
+        select_stops_by_name(&mut self.connection, word, paging)
+            .context("Fail to select_stops_by_name")
+    }
+
+    fn count_stops(&mut self, word: String) -> Result<u32> {
+        count_stops_by_name(&mut self.connection, word).context("Fail to count_stops_by_name")
+    }
+
+    fn select_stop(&mut self, stop_id: StopId) -> Result<Option<Stop>> {
+        select_stop_by_id(&mut self.connection, stop_id).context("Fail to select_stop_by_id")
+    }
+
+    fn select_stops_by_parents(&mut self, parent_stations: Vec<StopId>) -> Result<Vec<Stop>> {
+        select_stops_by_parents(&mut self.connection, parent_stations)
+            .context("Fail to select_stops_by_parents")
     }
 
     fn insert_routes(&mut self, routes: &[Route]) -> Result<()> {
         insert(&mut self.connection, routes)
     }
 
-    fn select_routes(&mut self) -> Result<Vec<Route>> {
-        select_all::<Route>(&mut self.connection).context("Fail to select_routes")
+    fn select_routes(&mut self, scope: &OperatorScope) -> Result<Vec<Route>> {
+        select_routes_by_scope(&mut self.connection, scope).context("Fail to select_routes")
+    }
+
+    fn select_route_types(&mut self) -> Result<Vec<RouteType>> {
+        select_route_types(&mut self.connection).context("Fail to select_route_types")
+    }
+
+    fn select_route_types_by_stop(&mut self) -> Result<Vec<(StopId, RouteType)>> {
+        select_route_types_by_stop(&mut self.connection)
+            .context("Fail to select_route_types_by_stop")
     }
 
     fn insert_routes_jp(&mut self, routes_jp: &[RouteJp]) -> Result<()> {
@@ -225,8 +424,17 @@ impl GtfsDbTrait for GtfsDb {
         insert(&mut self.connection, trips)
     }
 
-    fn select_trips(&mut self, stop_id: StopId) -> Result<Vec<Trip>> {
-        select_trips_by_stop(&mut self.connection, stop_id).context("Fail to select_trips_by_stop")
+    fn select_trip_ids_without_stop_times(&mut self) -> Result<Vec<TripId>> {
+        select_trip_ids_without_stop_times(&mut self.connection)
+            .context("Fail to select_trip_ids_without_stop_times")
+    }
+
+    fn select_trips(&mut self, query: &TripQuery, paging: &Paging) -> Result<Vec<Trip>> {
+        select_trips(&mut self.connection, query, paging).context("Fail to select_trips")
+    }
+
+    fn count_trips(&mut self, query: &TripQuery) -> Result<u32> {
+        count_trips(&mut self.connection, query).context("Fail to count_trips")
     }
 
     fn insert_offices_jp(&mut self, offices: &[OfficeJp]) -> Result<()> {
@@ -237,6 +445,11 @@ impl GtfsDbTrait for GtfsDb {
         insert(&mut self.connection, stop_times)
     }
 
+    fn select_stop_times(&mut self, trip_ids: Vec<TripId>) -> Result<Vec<StopTime>> {
+        select_stop_times_by_trip_ids(&mut self.connection, trip_ids)
+            .context("Fail to select_stop_times_by_trip_ids")
+    }
+
     fn insert_calendars(&mut self, calendars: &[Calendar]) -> Result<()> {
         insert(&mut self.connection, calendars)
     }
@@ -245,6 +458,11 @@ impl GtfsDbTrait for GtfsDb {
         insert(&mut self.connection, calendar_dates)
     }
 
+    fn select_service_ids(&mut self, date: &NaiveDate) -> Result<Vec<ServiceId>> {
+        select_service_ids_by_date(&mut self.connection, date)
+            .context("Fail to select_service_ids_by_date")
+    }
+
     fn insert_fare_attributes(&mut self, fare_attributes: &[FareAttribute]) -> Result<()> {
         insert(&mut self.connection, fare_attributes)
     }
@@ -265,6 +483,71 @@ impl GtfsDbTrait for GtfsDb {
         insert(&mut self.connection, transfers)
     }
 
+    fn select_transfers(&mut self) -> Result<Vec<Transfer>> {
+        select_all::<Transfer>(&mut self.connection).context("Fail to select_transfers")
+    }
+
+    fn insert_levels(&mut self, levels: &[Level]) -> Result<()> {
+        insert(&mut self.connection, levels)
+    }
+
+    fn select_levels(&mut self, level_ids: Vec<LevelId>) -> Result<Vec<Level>> {
+        select_levels_by_ids(&mut self.connection, level_ids)
+            .context("Fail to select_levels_by_ids")
+    }
+
+    fn insert_pathways(&mut self, pathways: &[Pathway]) -> Result<()> {
+        insert(&mut self.connection, pathways)
+    }
+
+    fn select_pathways(&mut self, stop_ids: Vec<StopId>) -> Result<Vec<Pathway>> {
+        select_pathways_by_stops(&mut self.connection, stop_ids)
+            .context("Fail to select_pathways_by_stops")
+    }
+
+    fn insert_attributions(&mut self, attributions: &[Attribution]) -> Result<()> {
+        insert(&mut self.connection, attributions)
+    }
+
+    fn insert_fare_media(&mut self, fare_media: &[FareMedia]) -> Result<()> {
+        insert(&mut self.connection, fare_media)
+    }
+
+    fn insert_fare_products(&mut self, fare_products: &[FareProduct]) -> Result<()> {
+        insert(&mut self.connection, fare_products)
+    }
+
+    fn insert_fare_leg_rules(&mut self, fare_leg_rules: &[FareLegRule]) -> Result<()> {
+        insert(&mut self.connection, fare_leg_rules)
+    }
+
+    fn insert_fare_transfer_rules(
+        &mut self,
+        fare_transfer_rules: &[FareTransferRule],
+    ) -> Result<()> {
+        insert(&mut self.connection, fare_transfer_rules)
+    }
+
+    fn insert_areas(&mut self, areas: &[Area]) -> Result<()> {
+        insert(&mut self.connection, areas)
+    }
+
+    fn insert_stop_areas(&mut self, stop_areas: &[StopArea]) -> Result<()> {
+        insert(&mut self.connection, stop_areas)
+    }
+
+    fn insert_networks(&mut self, networks: &[Network]) -> Result<()> {
+        insert(&mut self.connection, networks)
+    }
+
+    fn insert_route_networks(&mut self, route_networks: &[RouteNetwork]) -> Result<()> {
+        insert(&mut self.connection, route_networks)
+    }
+
+    fn insert_booking_rules(&mut self, booking_rules: &[BookingRule]) -> Result<()> {
+        insert(&mut self.connection, booking_rules)
+    }
+
     fn insert_feeds(&mut self, feeds: &[Feed]) -> Result<()> {
         insert(&mut self.connection, feeds)
     }
@@ -277,21 +560,46 @@ impl GtfsDbTrait for GtfsDb {
         insert(&mut self.connection, translations)
     }
 
+    fn select_translatable_values(
+        &mut self,
+        table_name: &TranslatableTableName,
+        field_name: &str,
+    ) -> Result<Vec<String>> {
+        select_distinct_values(&mut self.connection, table_name, field_name).with_context(|| {
+            format!(
+                "Fail to select_distinct_values {}.{}",
+                table_name, field_name
+            )
+        })
+    }
+
     /// ---------------------------extended--------------------------
 
     fn select_stop_time_details(
         &mut self,
         trip_ids: Option<Vec<TripId>>,
         stop_name_prefix: Option<String>,
+        scope: &OperatorScope,
+        paging: &Paging,
     ) -> Result<Vec<StopTimeDetail>> {
-        match (trip_ids, stop_name_prefix) {
-            (Some(ids), _) => select_stop_time_details_by_ids(&mut self.connection, ids),
-            (_, Some(stop_name_prefix)) => {
-                select_stop_time_details_by_name(&mut self.connection, stop_name_prefix)
-            }
-            _ => select_stop_time_details(&mut self.connection),
-        }
-        .context("Fail to select_trip_with_stops")
+        select_stop_time_details(
+            &mut self.connection,
+            trip_ids,
+            stop_name_prefix,
+            scope,
+            paging,
+        )
+        .context("Fail to select_stop_time_details")
+    }
+
+    fn count_stop_time_details(
+        &mut self,
+        trip_ids: Option<Vec<TripId>>,
+        stop_name_prefix: Option<String>,
+        scope: &OperatorScope,
+    ) -> Result<u32> {
+        count_stop_time_details(&mut self.connection, trip_ids, stop_name_prefix, scope)
+            .context("Fail to count_stop_time_details")
     }
 
     fn select_service_route_identity(&mut self) -> Result<Vec<ServiceRouteIdentity>> {
@@ -299,6 +607,219 @@ impl GtfsDbTrait for GtfsDb {
             .context("Fail to select_service_route_identity")
     }
 
+    fn select_service_route_summaries(
+        &mut self,
+        service_route_id: Option<ServiceRouteId>,
+        route_id: Option<RouteId>,
+        stop_id: Option<StopId>,
+        direction_id: Option<DirectionId>,
+        paging: &Paging,
+    ) -> Result<Vec<ServiceRouteSummary>> {
+        select_service_route_summaries(
+            &mut self.connection,
+            service_route_id,
+            route_id,
+            stop_id,
+            direction_id,
+            paging,
+        )
+        .context("Fail to select_service_route_summaries")
+    }
+
+    fn count_service_route_summaries(
+        &mut self,
+        route_id: Option<RouteId>,
+        stop_id: Option<StopId>,
+        direction_id: Option<DirectionId>,
+    ) -> Result<u32> {
+        count_service_route_summaries(&mut self.connection, route_id, stop_id, direction_id)
+            .context("Fail to count_service_route_summaries")
+    }
+
+    fn select_service_route_trips(
+        &mut self,
+        service_route_id: ServiceRouteId,
+        direction_id: DirectionId,
+    ) -> Result<Vec<ServiceRouteTrip>> {
+        select_service_route_trips(&mut self.connection, service_route_id, direction_id)
+            .context("Fail to select_service_route_trips")
+    }
+
+    fn select_agency_details(&mut self, paging: &Paging) -> Result<Vec<AgencyDetail>> {
+        select_agency_details(&mut self.connection, paging).context("Fail to select_agency_details")
+    }
+
+    fn count_agency_details(&mut self) -> Result<u32> {
+        count_agency_details(&mut self.connection).context("Fail to count_agency_details")
+    }
+
+    fn select_office_details(&mut self, paging: &Paging) -> Result<Vec<OfficeDetail>> {
+        select_office_details(&mut self.connection, paging).context("Fail to select_office_details")
+    }
+
+    fn count_office_details(&mut self) -> Result<u32> {
+        count_office_details(&mut self.connection).context("Fail to count_office_details")
+    }
+
+    fn insert_stops_rtree(&mut self) -> Result<usize> {
+        insert_stops_rtree(&self.connection).context("Fail to insert_stops_rtree")
+    }
+
+    fn select_stops_in_bounds(
+        &mut self,
+        lat: f64,
+        lon: f64,
+        radius: f64,
+        paging: &Paging,
+    ) -> Result<Vec<NearbyStop>> {
+        select_stops_in_bounds(&mut self.connection, lat, lon, radius, paging)
+            .context("Fail to select_stops_in_bounds")
+    }
+
+    fn count_stops_in_bounds(&mut self, lat: f64, lon: f64, radius: f64) -> Result<u32> {
+        count_stops_in_bounds(&mut self.connection, lat, lon, radius)
+            .context("Fail to count_stops_in_bounds")
+    }
+
+    fn select_records(&mut self, query: &RecordQuery) -> Result<Vec<Record>> {
+        select_records(&mut self.connection, query)
+            .with_context(|| format!("Fail to select_records from {}", query.table))
+    }
+
+    fn select_nodes(&mut self, word: Option<String>, paging: &Paging) -> Result<Vec<Node>> {
+        select_nodes_by_word(&mut self.connection, word, paging)
+            .context("Fail to select_nodes_by_word")
+    }
+
+    fn count_nodes(&mut self, word: Option<String>) -> Result<u32> {
+        count_nodes_by_word(&mut self.connection, word).context("Fail to count_nodes_by_word")
+    }
+
+    fn select_node(&mut self, node_id: NodeId) -> Result<Option<Node>> {
+        select_node(&mut self.connection, node_id).context("Fail to select_node")
+    }
+
+    fn select_node_stops(&mut self, node_id: NodeId) -> Result<Vec<Stop>> {
+        select_node_stops(&mut self.connection, node_id).context("Fail to select_node_stops")
+    }
+
+    fn select_node_routes(&mut self, node_id: NodeId) -> Result<Vec<Route>> {
+        select_node_routes(&mut self.connection, node_id).context("Fail to select_node_routes")
+    }
+
+    fn select_node_service_routes(&mut self, node_id: NodeId) -> Result<Vec<ServiceRoute>> {
+        select_node_service_routes(&mut self.connection, node_id)
+            .context("Fail to select_node_service_routes")
+    }
+
+    fn select_node_departures(
+        &mut self,
+        node_id: NodeId,
+        service_ids: Option<Vec<ServiceId>>,
+        from: Option<&GtfsTime>,
+        scope: &OperatorScope,
+        paging: &Paging,
+    ) -> Result<Vec<NodeDeparture>> {
+        let query = NodeDepartureQuery {
+            node_id,
+            service_ids,
+            from_seconds: from.map(|x| x.seconds()),
+            scope,
+        };
+        select_node_departures(&mut self.connection, &query, paging)
+            .context("Fail to select_node_departures")
+    }
+
+    fn count_node_departures(
+        &mut self,
+        node_id: NodeId,
+        service_ids: Option<Vec<ServiceId>>,
+        from: Option<&GtfsTime>,
+        scope: &OperatorScope,
+    ) -> Result<u32> {
+        let query = NodeDepartureQuery {
+            node_id,
+            service_ids,
+            from_seconds: from.map(|x| x.seconds()),
+            scope,
+        };
+        count_node_departures(&mut self.connection, &query).context("Fail to count_node_departures")
+    }
+
+    fn select_block_trips(
+        &mut self,
+        service_ids: Vec<ServiceId>,
+        block_id: Option<String>,
+    ) -> Result<Vec<BlockTrip>> {
+        select_block_trips(&mut self.connection, service_ids, block_id)
+            .context("Fail to select_block_trips")
+    }
+
+    fn select_route_details(
+        &mut self,
+        route_id: Option<RouteId>,
+        word: Option<String>,
+        route_types: Option<Vec<RouteType>>,
+        scope: &OperatorScope,
+        paging: &Paging,
+    ) -> Result<Vec<RouteDetail>> {
+        select_route_details(
+            &mut self.connection,
+            route_id,
+            word,
+            route_types,
+            scope,
+            paging,
+        )
+        .context("Fail to select_route_details")
+    }
+
+    fn count_route_details(
+        &mut self,
+        word: Option<String>,
+        route_types: Option<Vec<RouteType>>,
+        scope: &OperatorScope,
+    ) -> Result<u32> {
+        count_route_details(&mut self.connection, word, route_types, scope)
+            .context("Fail to count_route_details")
+    }
+
+    fn select_route_stops(&mut self, route_id: RouteId) -> Result<Vec<Stop>> {
+        select_route_stops(&mut self.connection, route_id).context("Fail to select_route_stops")
+    }
+
+    fn select_route_service_routes(&mut self, route_id: RouteId) -> Result<Vec<ServiceRoute>> {
+        select_route_service_routes(&mut self.connection, route_id)
+            .context("Fail to select_route_service_routes")
+    }
+
+    fn select_leg_fares(
+        &mut self,
+        from_stop_id: StopId,
+        to_stop_id: StopId,
+        route_id: Option<RouteId>,
+        paging: &Paging,
+    ) -> Result<Vec<LegFare>> {
+        select_leg_fares(
+            &mut self.connection,
+            from_stop_id,
+            to_stop_id,
+            route_id,
+            paging,
+        )
+        .context("Fail to select_leg_fares")
+    }
+
+    fn count_leg_fares(
+        &mut self,
+        from_stop_id: StopId,
+        to_stop_id: StopId,
+        route_id: Option<RouteId>,
+    ) -> Result<u32> {
+        count_leg_fares(&mut self.connection, from_stop_id, to_stop_id, route_id)
+            .context("Fail to count_leg_fares")
+    }
+
     fn insert_trips2service_routes(
         &mut self,
         trip2service_routes: &[Trip2ServiceRoute],
@@ -310,6 +831,18 @@ impl GtfsDbTrait for GtfsDb {
         insert(&mut self.connection, service_routes)
     }
 
+    fn insert_service_route_merges(&mut self, merges: &[ServiceRouteMerge]) -> Result<()> {
+        insert(&mut self.connection, merges)
+    }
+
+    fn insert_service_route_problems(&mut self, problems: &[ServiceRouteProblem]) -> Result<()> {
+        insert(&mut self.connection, problems)
+    }
+
+    fn insert_walking_transfers(&mut self, walking_transfers: &[WalkingTransfer]) -> Result<()> {
+        insert(&mut self.connection, walking_transfers)
+    }
+
     fn select_stop_details(&mut self) -> Result<Vec<StopDetail>> {
         select_stop_details(&mut self.connection).context("Fail to select_stop_details")
     }
@@ -317,4 +850,23 @@ impl GtfsDbTrait for GtfsDb {
     fn insert_nodes(&mut self, nodes: &[Node]) -> Result<()> {
         insert(&mut self.connection, nodes)
     }
+
+    fn insert_stops2nodes(&mut self, stops2nodes: &[Stop2Node]) -> Result<()> {
+        insert(&mut self.connection, stops2nodes)
+    }
+
+    fn select_stops2nodes(&mut self) -> Result<Vec<Stop2Node>> {
+        self.select_all::<Stop2Node>()
+    }
+
+    fn update_extras(&mut self, table_name: &str, extras: &[ExtraColumns]) -> Result<()> {
+        add_extras_column(&mut self.connection, table_name)
+            .with_context(|| format!("Fail to add_extras_column to {}", table_name))?;
+        update_extras(&mut self.connection, table_name, extras)
+            .with_context(|| format!("Fail to update_extras of {}", table_name))
+    }
+
+    fn insert_extra_records(&mut self, records: &[ExtraRecord]) -> Result<()> {
+        insert(&mut self.connection, records)
+    }
 }
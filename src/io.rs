@@ -1,7 +1,9 @@
+use std::fs::File;
 use std::io;
-use std::path::PathBuf;
+use std::path::Path;
 
 use anyhow::{bail, Context, Result};
+use itertools::Itertools;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use strum_macros::{Display, EnumString, EnumVariantNames};
@@ -16,18 +18,41 @@ pub enum Format {
     Yaml,
 }
 
-pub fn read<T>(path: &PathBuf, format: &Format) -> Result<Vec<T>>
+impl Format {
+    /// 拡張子からフォーマットを推定する (不明な場合はTSV)
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("csv") => Format::Csv,
+            Some("json") => Format::Json,
+            Some("yaml") | Some("yml") => Format::Yaml,
+            _ => Format::Tsv,
+        }
+    }
+}
+
+pub fn read<T>(path: &Path, format: &Format) -> Result<Vec<T>>
 where
     T: DeserializeOwned,
 {
     match format {
         Format::Csv => read_csv(path, b','),
         Format::Tsv => read_csv(path, b'\t'),
-        _ => bail!("{}形式の読みこみには対応していません", format),
+        Format::Json | Format::PJson => {
+            let file = File::open(path)
+                .with_context(|| format!("{:?} が読み込めませんでした", &path.to_str()))?;
+            serde_json::from_reader(file)
+                .with_context(|| format!("{:?} のパースに問題が発生しました", &path.to_str()))
+        }
+        Format::Yaml => {
+            let file = File::open(path)
+                .with_context(|| format!("{:?} が読み込めませんでした", &path.to_str()))?;
+            serde_yaml::from_reader(file)
+                .with_context(|| format!("{:?} のパースに問題が発生しました", &path.to_str()))
+        }
     }
 }
 
-fn read_csv<T>(path: &PathBuf, delimiter: u8) -> Result<Vec<T>>
+fn read_csv<T>(path: &Path, delimiter: u8) -> Result<Vec<T>>
 where
     T: DeserializeOwned,
 {
@@ -40,6 +65,77 @@ where
     r.with_context(|| format!("{:?} のパースに問題が発生しました", &path.to_str()))
 }
 
+/// ファイルのヘッダ(カラム名一覧)のみを読みこむ
+pub fn read_headers(path: &Path, format: &Format) -> Result<Vec<String>> {
+    let delimiter = match format {
+        Format::Csv => b',',
+        Format::Tsv => b'\t',
+        _ => bail!("{}形式のヘッダ読みこみには対応していません", format),
+    };
+    let headers = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_path(path)
+        .with_context(|| format!("{:?} が読み込めませんでした", &path.to_str()))?
+        .headers()
+        .with_context(|| format!("{:?} のヘッダが読み込めませんでした", &path.to_str()))?
+        .iter()
+        .map(header_name)
+        .collect();
+    Ok(headers)
+}
+
+/// 先頭カラム名に残りうるBOMを取り除く (GTFS-JPのファイルはBOM付きのことが多い)
+fn header_name(header: &str) -> String {
+    header.trim_start_matches('\u{feff}').to_string()
+}
+
+/// 型を問わないレコード (カラム名と値の組をカラム順に保持する)
+pub type RawRecord = Vec<(String, String)>;
+
+/// 型を定義せずに文字列のまま読みこむ
+pub fn read_raw(path: &Path, format: &Format) -> Result<Vec<RawRecord>> {
+    let delimiter = match format {
+        Format::Csv => b',',
+        Format::Tsv => b'\t',
+        _ => bail!("{}形式の読みこみには対応していません", format),
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_path(path)
+        .with_context(|| format!("{:?} が読み込めませんでした", &path.to_str()))?;
+    let headers = reader
+        .headers()
+        .with_context(|| format!("{:?} のヘッダが読み込めませんでした", &path.to_str()))?
+        .iter()
+        .map(header_name)
+        .collect_vec();
+    let r: Result<Vec<_>, _> = reader
+        .records()
+        .map(|record| {
+            record.map(|r| {
+                headers
+                    .iter()
+                    .zip(r.iter())
+                    .map(|(h, v)| (h.clone(), v.to_string()))
+                    .collect()
+            })
+        })
+        .collect();
+    r.with_context(|| format!("{:?} のパースに問題が発生しました", &path.to_str()))
+}
+
+/// read_rawで読みこんだレコードを、readと同じ規則で型に変換する
+pub fn deserialize_raw<T>(record: &[(String, String)]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let headers: csv::StringRecord = record.iter().map(|(h, _)| h.as_str()).collect();
+    let values: csv::StringRecord = record.iter().map(|(_, v)| v.as_str()).collect();
+    values
+        .deserialize(Some(&headers))
+        .with_context(|| format!("{:?} のパースに問題が発生しました", record))
+}
+
 pub fn write<T>(records: &[T], format: &Format) -> Result<()>
 where
     T: Serialize,
@@ -1,14 +1,19 @@
-#![feature(proc_macro_hygiene, decl_macro)]
+#![feature(proc_macro_hygiene, decl_macro, once_cell)]
 #[macro_use]
 extern crate rocket;
 
+use std::path::PathBuf;
+
 use anyhow::Result;
 use clap::Clap;
 use env_logger::Env;
 
+use crate::config::Config;
+
 pub mod api;
 pub mod app;
 pub mod cmd;
+pub mod config;
 pub mod external;
 pub mod io;
 pub mod serde_chrono_custom;
@@ -16,9 +21,12 @@ pub mod serde_chrono_custom;
 #[derive(Clap, Debug)]
 #[clap(version = clap::crate_version!(), author = "tadashi-aikawa")]
 struct Opts {
-    /// The path of the config file to load
-    // #[clap(short, long, default_value = ".diamant.yaml")]
-    // config: String,
+    /// 読みこむ設定ファイルのパス (省略時は.diamant.yamlがあれば読みこむ)
+    #[clap(long, parse(from_os_str), global = true)]
+    config: Option<PathBuf>,
+    /// 設定ファイルから使うfeed名
+    #[clap(long, global = true)]
+    feed: Option<String>,
     #[clap(subcommand)]
     subcmd: SubCommand,
     // #[clap(short, long, parse(from_occurrences), global = true)]
@@ -33,16 +41,21 @@ enum SubCommand {
     Get(cmd::get::Opts),
     /// APIサーバーとして立ち上げる(データベースと連携)
     Serve(cmd::serve::Opts),
+    /// APIのOpenAPIドキュメントを出力する
+    Openapi(cmd::openapi::Opts),
 }
 
 pub fn run() -> Result<()> {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
     let opts: Opts = Opts::parse();
+    let config = Config::load(opts.config.as_deref())?;
+    let feed = config.feed(opts.feed.as_deref())?;
     match opts.subcmd {
-        SubCommand::Db(op) => cmd::db::run(&op)?,
-        SubCommand::Get(op) => cmd::get::run(&op)?,
-        SubCommand::Serve(op) => cmd::serve::run(&op),
+        SubCommand::Db(op) => cmd::db::run(&op, &feed)?,
+        SubCommand::Get(op) => cmd::get::run(&op, &feed)?,
+        SubCommand::Serve(op) => cmd::serve::run(&op, &config)?,
+        SubCommand::Openapi(op) => cmd::openapi::run(&op)?,
     }
 
     Ok(())
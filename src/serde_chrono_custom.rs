@@ -4,12 +4,21 @@ pub mod yyyymmdd {
 
     const FORMAT: &str = "%Y%m%d";
 
+    /// YYYYMMDD形式の文字列を日付に変換する
+    pub fn parse(s: &str) -> Result<NaiveDate, chrono::ParseError> {
+        NaiveDate::parse_from_str(s, FORMAT)
+    }
+
+    /// 日付をYYYYMMDD形式の文字列に変換する
+    pub fn format(date: &NaiveDate) -> String {
+        date.format(FORMAT).to_string()
+    }
+
     pub fn serialize<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let s = format!("{}", date.format(FORMAT));
-        serializer.serialize_str(&s)
+        serializer.serialize_str(&format(date))
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
@@ -17,6 +26,6 @@ pub mod yyyymmdd {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        NaiveDate::parse_from_str(&s, FORMAT).map_err(serde::de::Error::custom)
+        parse(&s).map_err(serde::de::Error::custom)
     }
 }
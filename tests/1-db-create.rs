@@ -1,20 +1,77 @@
 use anyhow::Result;
+use chrono::NaiveDate;
+use diamant::api::utils::feeds::Feeds;
+use diamant::app::block::BlockServiceDb;
+use diamant::app::graphql::{GraphqlLimits, GraphqlRequest, GraphqlResponse, GraphqlServiceDb};
+use diamant::app::node::NodeServiceDb;
+use diamant::app::route::{RouteService, RouteServiceDb};
+use diamant::app::service_route::{IdentityIssueKind, ServiceRouteServiceDb};
+use diamant::app::stops::StopServiceDb;
+use diamant::app::trip::TripServiceDb;
 use diamant::cmd;
+use diamant::config::FeedConfig;
 use diamant::external::gtfs::agency::Agency;
-use diamant::external::gtfs::extended::nodes::Node;
+use diamant::external::gtfs::extended::nodes::{Node, NodeStrategy, Stop2Node};
+use diamant::external::gtfs::extended::service_route_identity::ServiceRouteIdentity;
 use diamant::external::gtfs::extended::service_routes::IdentifyStrategy;
-use diamant::external::gtfs::{Lang, Timezone};
-use std::path::PathBuf;
+use diamant::external::gtfs::extended::walking_transfers::WalkingTransfer;
+use diamant::external::gtfs::routes::{RouteType, TransitMode};
+use diamant::external::gtfs::transfers::TransferType;
+use diamant::external::gtfs::trips::TripQuery;
+use diamant::external::gtfs::{
+    GtfsDbTrait, GtfsTime, Lang, OperatorScope, Paging, SortKey, Timezone,
+};
+use diamant::io::Format;
+use itertools::Itertools;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// tests/dataにtests/fixtures/<fixture>のファイルを上書きしたGTFSから、一時ディレクトリにデータベースを作成する
+fn create_fixture_db(fixture: &str, opts: cmd::db::create::Opts) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join("diamant-tests").join(fixture);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    for src in &[
+        Path::new("tests/data"),
+        &Path::new("tests/fixtures").join(fixture),
+    ] {
+        if !src.exists() {
+            continue;
+        }
+        for entry in fs::read_dir(src)? {
+            let path = entry?.path();
+            fs::copy(&path, dir.join(path.file_name().unwrap()))?;
+        }
+    }
+
+    let database = dir.with_extension("db");
+    if database.exists() {
+        fs::remove_file(&database)?;
+    }
+    cmd::db::create::run(
+        &cmd::db::create::Opts {
+            gtfs_dir: Some(dir),
+            database: Some(database.clone()),
+            ..opts
+        },
+        &FeedConfig::default(),
+    )?;
+    Ok(database)
+}
 
 #[test]
 fn no1_db_create() -> Result<()> {
-    cmd::db::create::run(&cmd::db::create::Opts {
-        gtfs_dir: PathBuf::from("tests/data"),
-        database: PathBuf::from("gtfs.db"),
-        legacy_translations: false,
-        service_route_identify_strategy: IdentifyStrategy::StopNames,
-        service_route_identify: None,
-    })
+    cmd::db::create::run(
+        &cmd::db::create::Opts {
+            gtfs_dir: Some(PathBuf::from("tests/data")),
+            database: Some(PathBuf::from("gtfs.db")),
+            service_route_identify_strategy: Some(IdentifyStrategy::StopNames),
+            ..Default::default()
+        },
+        &FeedConfig::default(),
+    )
 }
 
 #[test]
@@ -46,45 +103,1484 @@ fn no3_nodes_is_valid() -> Result<()> {
     let nodes = db.select_all::<Node>()?;
     assert_eq!(7, nodes.len());
 
+    // ノードIDは代表stopのstop_idのハッシュから求める
     assert_eq!(
         vec![
             Node {
-                node_id: 1,
-                node_name: "日本橋".to_string(),
-                node_ruby: "にほんばし".to_string(),
-            },
-            Node {
-                node_id: 2,
+                node_id: 249400729,
                 node_name: "茅場町".to_string(),
                 node_ruby: "かやばちょう".to_string(),
             },
             Node {
-                node_id: 3,
+                node_id: 534620252,
                 node_name: "茅場町".to_string(),
                 node_ruby: "かやばちょう".to_string(),
             },
             Node {
-                node_id: 4,
-                node_name: "清澄白河".to_string(),
-                node_ruby: "きよすみしらかわ".to_string(),
-            },
-            Node {
-                node_id: 5,
+                node_id: 562761019,
                 node_name: "門前仲町".to_string(),
                 node_ruby: "もんぜんなかちょう".to_string(),
             },
             Node {
-                node_id: 6,
+                node_id: 814425304,
                 node_name: "門前仲町".to_string(),
                 node_ruby: "もんぜんなかちょう".to_string(),
             },
             Node {
-                node_id: 7,
+                node_id: 878013093,
+                node_name: "清澄白河".to_string(),
+                node_ruby: "きよすみしらかわ".to_string(),
+            },
+            Node {
+                node_id: 948646256,
                 node_name: "門前仲町".to_string(),
                 node_ruby: "もんぜんなかちょう".to_string(),
             },
+            Node {
+                node_id: 2093481802,
+                node_name: "日本橋".to_string(),
+                node_ruby: "にほんばし".to_string(),
+            },
         ],
         nodes
     );
+
+    let stops2nodes = db.select_all::<Stop2Node>()?;
+    assert_eq!(9, stops2nodes.len());
+    assert!(stops2nodes
+        .iter()
+        .filter(|x| x.stop_id.starts_with("1_"))
+        .all(|x| x.node_id == 2093481802));
+    Ok(())
+}
+
+#[test]
+fn no4_routes_filtered_by_mode() -> Result<()> {
+    let db = diamant::external::gtfsdb::GtfsDb::new("gtfs.db".as_ref())?;
+    let mut service = RouteServiceDb::new(db);
+    assert_eq!(3, service.fetch(None, &OperatorScope::default())?.len());
+    assert_eq!(
+        3,
+        service
+            .fetch(Some(&TransitMode::Bus), &OperatorScope::default())?
+            .len()
+    );
+    assert_eq!(
+        0,
+        service
+            .fetch(Some(&TransitMode::Rail), &OperatorScope::default())?
+            .len()
+    );
+    let details = service.fetch_route_details(
+        Some("清澄白河".into()),
+        None,
+        &OperatorScope::default(),
+        &Paging::default(),
+    )?;
+    assert_eq!(1, details.len());
+    assert_eq!("系統1", details[0].route_id);
+    assert_eq!(3, details[0].trip_count);
+    Ok(())
+}
+
+#[test]
+fn no5_gtfs_time_is_valid() -> Result<()> {
+    let late: GtfsTime = "25:10:00".parse().unwrap();
+    let early: GtfsTime = "7:05:30".parse().unwrap();
+    assert_eq!(90600, late.seconds());
+    assert_eq!("07:05:30", early.to_string());
+    assert!(early < late);
+    assert_eq!(late, early + 65070);
+    assert!("7:5:00".parse::<GtfsTime>().is_err());
+    Ok(())
+}
+
+#[test]
+fn no6_legacy_translations_with_bom() -> Result<()> {
+    let database = create_fixture_db("legacy_translations", Default::default())?;
+    let mut db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let stops = db.select_stop_details()?;
+    let nihonbashi = stops.iter().find(|x| x.stop_id == "1_d").unwrap();
+    assert_eq!("にほんばし", nihonbashi.stop_ruby);
+    Ok(())
+}
+
+#[test]
+fn no7_unknown_route_types_and_mode_transfer_times() -> Result<()> {
+    let database = create_fixture_db(
+        "unknown_route_types",
+        cmd::db::create::Opts {
+            walking_transfer_distance: Some(100.0),
+            ..Default::default()
+        },
+    )?;
+    let mut db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    assert_eq!(
+        vec![
+            RouteType::Bus,
+            RouteType::Unknown(118),
+            RouteType::Unknown(1701)
+        ],
+        db.select_route_types()?
+    );
+    assert_eq!(TransitMode::Rail, RouteType::Unknown(118).mode());
+
+    let rail_trips = TripServiceDb::new(db).fetch_trips(
+        Some(&TransitMode::Rail),
+        None,
+        None,
+        TripQuery::default(),
+        false,
+        &Paging::default(),
+    )?;
+    assert_eq!(2, rail_trips.len());
+    assert!(rail_trips.iter().all(|x| x.trip.route_id == "系統3"));
+
+    // 4_uは鉄道(118)のみが通るため、徒歩時間が短くても鉄道の既定乗換時間になる
+    let mut db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let transfers = db.select_transfers()?;
+    let to_rail = transfers
+        .iter()
+        .find(|x| x.from_stop_id == "4_d" && x.to_stop_id == "4_u")
+        .unwrap();
+    assert_eq!(
+        Some(TransitMode::Rail.default_min_transfer_time()),
+        to_rail.min_transfer_time
+    );
+    let to_bus = transfers
+        .iter()
+        .find(|x| x.from_stop_id == "4_u" && x.to_stop_id == "4_d")
+        .unwrap();
+    assert!(to_bus.min_transfer_time.unwrap() < TransitMode::Rail.default_min_transfer_time());
+    Ok(())
+}
+
+#[test]
+fn no8_station_structure() -> Result<()> {
+    let database = create_fixture_db("station_structure", Default::default())?;
+    let db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let mut service = StopServiceDb::new(db);
+
+    let structure = service.fetch_station_structure("1_d".to_string())?.unwrap();
+    assert_eq!("1_p", structure.station.stop_id);
+    assert_eq!(
+        vec!["1_d", "1_u"],
+        structure
+            .platforms
+            .iter()
+            .map(|x| x.stop_id.as_str())
+            .sorted()
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, structure.entrances.len());
+    assert_eq!(1, structure.pathways.len());
+
+    // 親駅のないのりばは駅に属さない
+    assert!(service
+        .fetch_station_structure("2_u".to_string())?
+        .is_none());
+    // 親駅が循環していても打ち切られる
+    assert!(service
+        .fetch_station_structure("9_a".to_string())?
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn no9_leg_fares_fallback_and_priority() -> Result<()> {
+    let database = create_fixture_db("fares_v2", Default::default())?;
+    let mut db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let mut leg_fares = |from: &str, to: &str, route: Option<&str>| -> Result<Vec<String>> {
+        Ok(db
+            .select_leg_fares(
+                from.to_string(),
+                to.to_string(),
+                route.map(|x| x.to_string()),
+                &Default::default(),
+            )?
+            .into_iter()
+            .map(|x| x.fare_product_id)
+            .collect())
+    };
+
+    // ネットワーク・エリアが指定されたルールがあれば、未指定のルールは該当しない
+    assert_eq!(vec!["specific"], leg_fares("1_d", "4_d", Some("系統1"))?);
+    // ネットワークに属さない経路は、ネットワーク未指定のルールに該当する
+    assert_eq!(vec!["generic"], leg_fares("1_d", "4_d", Some("系統2"))?);
+    // routesのnetwork_idでもネットワークに属する
+    assert_eq!(vec!["specific"], leg_fares("1_d", "4_d", Some("系統3"))?);
+    // エリアに属さないstopは、エリア未指定のルールに該当する
+    assert_eq!(vec!["network"], leg_fares("3_d", "4_l", Some("系統1"))?);
+    // rule_priorityが最も高いルールのみ該当する
+    assert_eq!(vec!["priority"], leg_fares("2_u", "4_d", Some("系統1"))?);
+    Ok(())
+}
+
+#[test]
+fn no10_extra_columns_are_preserved() -> Result<()> {
+    let database = create_fixture_db(
+        "extras",
+        cmd::db::create::Opts {
+            preserve_extras: true,
+            ..Default::default()
+        },
+    )?;
+    let conn = rusqlite::Connection::open(&database)?;
+    let extras_of = |sql: &str| -> Result<Option<String>> {
+        Ok(conn.query_row(sql, rusqlite::NO_PARAMS, |row| row.get(0))?)
+    };
+
+    // 主キーの値はテーブルと同じ形式 (07:05:30) に変換して照合する
+    assert_eq!(
+        Some(r#"{"vendor_note":"朝"}"#.to_string()),
+        extras_of("SELECT extras FROM frequencies WHERE trip_id == '系統1_平日_11'")?
+    );
+    // 主キーのないテーブルは重複を除いた行順で照合する
+    assert_eq!(
+        Some(r#"{"vendor_memo":"memo1"}"#.to_string()),
+        extras_of(
+            "SELECT extras FROM translations WHERE field_value == '日本橋' AND language == 'ja-Hrkt'"
+        )?
+    );
+    assert_eq!(
+        Some(r#"{"vendor_memo":"memo2"}"#.to_string()),
+        extras_of(
+            "SELECT extras FROM translations WHERE field_value == '日本橋' AND language == 'en'"
+        )?
+    );
+    Ok(())
+}
+
+#[test]
+fn no11_interpolate_stop_times() -> Result<()> {
+    // 終点に時刻がない便があると、lenientでなければ失敗する
+    let error = create_fixture_db("interpolation", Default::default()).unwrap_err();
+    assert!(error.to_string().contains("系統1_平日_12"));
+
+    let database = create_fixture_db(
+        "interpolation",
+        cmd::db::create::Opts {
+            lenient: true,
+            ..Default::default()
+        },
+    )?;
+    let mut db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let stop_times = db.select_stop_times(vec![
+        "系統1_平日_11".to_string(),
+        "系統1_平日_12".to_string(),
+    ])?;
+
+    // shape_dist_traveled (小数を含む) で按分する
+    let times = stop_times
+        .iter()
+        .filter(|x| x.trip_id == "系統1_平日_11")
+        .map(|x| {
+            (
+                x.arrival_time.unwrap().to_string(),
+                x.interpolated,
+                x.shape_dist_traveled.unwrap().into_inner(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            ("10:00:00".to_string(), false, 0.0),
+            ("10:15:00".to_string(), true, 250.5),
+            ("10:45:00".to_string(), true, 751.5),
+            ("11:00:00".to_string(), false, 1002.0),
+        ],
+        times
+    );
+
+    // 補間できない便は除外して隔離する
+    assert!(stop_times.iter().all(|x| x.trip_id != "系統1_平日_12"));
+    let conn = rusqlite::Connection::open(&database)?;
+    let kind: String = conn.query_row(
+        "SELECT group_concat(kind) FROM service_route_problems WHERE trip_id == '系統1_平日_12'",
+        rusqlite::NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    assert_eq!("uninterpolatable", kind);
+    Ok(())
+}
+
+#[test]
+fn no12_blocks() -> Result<()> {
+    let database = create_fixture_db("blocks", Default::default())?;
+    let db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let mut service = BlockServiceDb::new(db);
+    let wednesday = NaiveDate::from_ymd(2021, 5, 12);
+
+    // 門前仲町で終わった便の次に日本橋から出発する運用は成立しない
+    let b1 = service.fetch_block(wednesday, "B1".to_string())?.unwrap();
+    assert!(b1.impossible);
+    assert_eq!(None, b1.trips[0].layover);
+    assert_eq!(Some(3 * 60 * 60), b1.trips[1].layover);
+    assert!(b1.trips[1].location_jumped);
+
+    // 同じ親駅や近くの標柱で折り返す運用は成立する
+    let b2 = service.fetch_block(wednesday, "B2".to_string())?.unwrap();
+    assert!(!b2.impossible);
+    assert_eq!(
+        vec!["系統1_平日_12", "系統3_全日_31", "系統1_平日_13"],
+        b2.trips
+            .iter()
+            .map(|x| x.trip_id.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(Some(0), b2.trips[1].layover);
+
+    let summaries = service.fetch_summaries(wednesday, 2)?;
+    assert_eq!(
+        vec![(2, 5, 0, 2, 1), (3, 7, 0, 3, 1)],
+        summaries
+            .iter()
+            .map(|x| (
+                x.block_count,
+                x.blocked_trip_count,
+                x.unblocked_trip_count,
+                x.vehicle_count,
+                x.impossible_block_count
+            ))
+            .collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[test]
+fn no13_departures_filtered_by_operator() -> Result<()> {
+    let database = create_fixture_db("operator_scope", Default::default())?;
+    let db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let mut service = NodeServiceDb::new(db);
+    let node_id = service.fetch_nodes(Some("日本橋".to_string()), &Paging::default())?[0].node_id;
+
+    let mut departures =
+        |agency_id: Option<&str>, office_id: Option<&str>| -> Result<Vec<String>> {
+            let scope = OperatorScope {
+                agency_id: agency_id.map(|x| x.to_string()),
+                office_id: office_id.map(|x| x.to_string()),
+            };
+            Ok(service
+                .fetch_departures(node_id, None, None, &scope, &Paging::default())?
+                .into_iter()
+                .map(|x| x.trip_id)
+                .collect())
+        };
+
+    assert_eq!(5, departures(None, None)?.len());
+    assert_eq!(5, departures(Some("33"), None)?.len());
+    assert!(departures(Some("99"), None)?.is_empty());
+    assert_eq!(
+        vec!["系統1_平日_11", "系統1_平日_12", "系統1_平日_13"],
+        departures(None, Some("S"))?
+    );
+    Ok(())
+}
+
+#[test]
+fn no14_service_route_uses_canonical_pattern() -> Result<()> {
+    let database = create_fixture_db(
+        "subsequence",
+        cmd::db::create::Opts {
+            service_route_identify_strategy: Some(IdentifyStrategy::Subsequence),
+            ..Default::default()
+        },
+    )?;
+    let db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let mut service = ServiceRouteServiceDb::new(db);
+
+    // 系統1_平日_10 (日本橋→茅場町→門前仲町) は正規パターンの部分列としてまとめられる
+    let patterns =
+        service.fetch_service_routes(Some("系統1".to_string()), None, None, &Paging::default())?;
+    assert_eq!(1, patterns.len());
+    let pattern = &patterns[0];
+    assert_eq!(4, pattern.trip_count);
+
+    // trip_idが最小の短いtripではなく、正規パターンの停車順になる
+    let detail = service
+        .fetch_service_route(pattern.service_route_id, pattern.direction_id.clone())?
+        .unwrap();
+    assert_eq!(
+        vec!["1_d", "2_d", "3_d", "4_d"],
+        detail
+            .pattern
+            .stops
+            .iter()
+            .map(|x| x.stop_id.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(4, detail.trips.len());
+    assert!(service
+        .fetch_service_route(pattern.service_route_id + 100, pattern.direction_id.clone())?
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn no15_service_route_identify_by_nodes() -> Result<()> {
+    // 系統1_平日_14 は日本橋の別の標柱 (1_u) から出発するが、同じノード (1_p) に属する
+    let database = create_fixture_db(
+        "nodes_strategy",
+        cmd::db::create::Opts {
+            service_route_identify_strategy: Some(IdentifyStrategy::Nodes),
+            ..Default::default()
+        },
+    )?;
+    let conn = rusqlite::Connection::open(&database)?;
+    let service_route_of = |trip_id: &str| -> rusqlite::Result<(u32, u32)> {
+        conn.query_row(
+            "SELECT service_route_id, service_route_direction_id FROM trips2service_routes WHERE trip_id == ?",
+            &[trip_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    };
+    assert_eq!(
+        service_route_of("系統1_平日_11")?,
+        service_route_of("系統1_平日_14")?
+    );
+    let reason: String = conn.query_row(
+        "SELECT reason FROM service_route_merges WHERE trip_id == '系統1_平日_14'",
+        rusqlite::NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    assert_eq!("same_nodes", reason);
+
+    // 識別ファイルに載っている識別子でまとめた場合は、識別ファイルを理由として記録する
+    let database = create_fixture_db(
+        "nodes_strategy",
+        cmd::db::create::Opts {
+            service_route_identify_strategy: Some(IdentifyStrategy::StopNames),
+            service_route_identify: Some(PathBuf::from(
+                "tests/fixtures/nodes_strategy/service_route_identity.csv",
+            )),
+            ..Default::default()
+        },
+    )?;
+    let conn = rusqlite::Connection::open(&database)?;
+    let reason: String = conn.query_row(
+        "SELECT reason FROM service_route_merges WHERE trip_id == '系統1_平日_14'",
+        rusqlite::NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    assert_eq!("identity_file", reason);
+    Ok(())
+}
+
+#[test]
+fn no16_missing_route_name_is_reported_or_quarantined() -> Result<()> {
+    // 系統3 には route_short_name と route_long_name のどちらもない
+    let err = create_fixture_db("missing_route_name", cmd::db::create::Opts::default())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("missing_route_name"), "{}", err);
+    assert!(err.contains("系統3_全日_31"), "{}", err);
+
+    let database = create_fixture_db(
+        "missing_route_name",
+        cmd::db::create::Opts {
+            lenient: true,
+            ..Default::default()
+        },
+    )?;
+    let conn = rusqlite::Connection::open(&database)?;
+    let problems: String = conn.query_row(
+        "SELECT group_concat(trip_id || ':' || kind) FROM (SELECT * FROM service_route_problems ORDER BY trip_id)",
+        rusqlite::NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    assert_eq!(
+        "系統3_全日_31:missing_route_name,系統3_水曜以外_32:missing_route_name",
+        problems
+    );
+    // 隔離したtripはサービスルートに割り当てない
+    let assigned: u32 = conn.query_row(
+        "SELECT count(*) FROM trips2service_routes WHERE trip_id LIKE '系統3_%'",
+        rusqlite::NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    assert_eq!(0, assigned);
+    let assigned: u32 = conn.query_row(
+        "SELECT count(*) FROM trips2service_routes",
+        rusqlite::NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    assert!(assigned > 0);
+    Ok(())
+}
+
+#[test]
+fn no17_check_service_route_identity() -> Result<()> {
+    let database = create_fixture_db("identity_check", cmd::db::create::Opts::default())?;
+    let db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let mut service = ServiceRouteServiceDb::new(db);
+    let identities = service.fetch_service_route_identity()?;
+    assert!(identities.len() > 1);
+
+    // 出力した識別ファイルはそのまま適用しても割当が変わらない (JSON・YAMLでも読み込める)
+    let dir = database.with_extension("identity");
+    fs::create_dir_all(&dir)?;
+    let json = dir.join("identity.json");
+    fs::write(&json, serde_json::to_string(&identities)?)?;
+    let yaml = dir.join("identity.yaml");
+    fs::write(&yaml, serde_yaml::to_string(&identities)?)?;
+    for path in &[json, yaml] {
+        let read: Vec<ServiceRouteIdentity> = diamant::io::read(path, &Format::from_path(path))?;
+        assert_eq!(identities, read);
+        let report = service.check_service_route_identity(&read, &IdentifyStrategy::StopNames)?;
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+        assert!(report.diffs.is_empty(), "{:?}", report.diffs);
+    }
+
+    // 編集した識別ファイルの問題と割当の変化
+    let mut edited = identities;
+    let moved = edited[0].trip_ids.split(',').next().unwrap().to_string();
+    edited[0].service_route_id = 99;
+    edited[0].trip_ids = format!("{},存在しない便", edited[0].trip_ids);
+    edited[1].trip_ids = format!("{},{}", edited[1].trip_ids, moved);
+    let report = service.check_service_route_identity(&edited, &IdentifyStrategy::StopNames)?;
+    let issues = report
+        .issues
+        .iter()
+        .map(|x| (x.kind.clone(), x.trip_id.clone().unwrap_or_default()))
+        .collect_vec();
+    assert!(issues.contains(&(IdentityIssueKind::UnknownTripId, "存在しない便".to_string())));
+    assert!(issues.contains(&(IdentityIssueKind::DuplicateTripId, moved.clone())));
+    let diff = report.diffs.iter().find(|x| x.trip_id == moved).unwrap();
+    assert!(
+        diff.after.as_ref().unwrap().starts_with("99^"),
+        "{:?}",
+        diff
+    );
+    Ok(())
+}
+
+#[test]
+fn no18_same_name_nodes_are_transitive() -> Result<()> {
+    // 5_a と 5_b は約200m離れているが、中間の 5_c を介して同じノードにまとまる
+    let database = create_fixture_db(
+        "same_name_chain",
+        cmd::db::create::Opts {
+            node_strategy: Some(NodeStrategy::SameName),
+            node_distance: Some(150.0),
+            ..Default::default()
+        },
+    )?;
+    let mut db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let stops2nodes = db.select_all::<Stop2Node>()?;
+    let node_ids = stops2nodes
+        .iter()
+        .filter(|x| x.stop_id.starts_with("5_"))
+        .map(|x| x.node_id)
+        .unique()
+        .collect_vec();
+    assert_eq!(1, node_ids.len());
+
+    // ノードIDは他のstopの有無に依存せず、no3と同じになる
+    let node_id_of = |stop_id: &str| {
+        stops2nodes
+            .iter()
+            .find(|x| x.stop_id == stop_id)
+            .map(|x| x.node_id)
+    };
+    assert_eq!(Some(2093481802), node_id_of("1_u"));
+    assert_eq!(Some(878013093), node_id_of("3_d"));
+    Ok(())
+}
+
+#[test]
+fn no19_node_search_detail_and_departures() -> Result<()> {
+    let database = create_fixture_db("node_api", cmd::db::create::Opts::default())?;
+    let db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let mut service = NodeServiceDb::new(db);
+
+    // 読み仮名でも検索できる
+    let nodes = service.fetch_nodes(Some("にほんばし".to_string()), &Paging::default())?;
+    assert_eq!(1, nodes.len());
+    let node = service.fetch_node(nodes[0].node_id)?.unwrap();
+    assert_eq!(
+        vec!["1_d", "1_p", "1_u"],
+        node.stops.iter().map(|x| x.stop_id.as_str()).collect_vec()
+    );
+    assert!(!node.routes.is_empty());
+    assert!(!node.service_routes.is_empty());
+    assert!(service.fetch_node(0)?.is_none());
+
+    // 先頭からlimit件はすべての出発の先頭と一致し、件数は別に数える
+    let date = NaiveDate::from_ymd(2021, 5, 12);
+    let scope = OperatorScope::default();
+    let all = service.fetch_departures(
+        node.node.node_id,
+        Some(date),
+        None,
+        &scope,
+        &Paging::default(),
+    )?;
+    assert!(all.len() > 2);
+    assert!(all.iter().all(|x| x.stop_id.starts_with("1_")));
+    let limited = service.fetch_departures(
+        node.node.node_id,
+        Some(date),
+        None,
+        &scope,
+        &Paging {
+            limit: Some(2),
+            offset: 0,
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(all[..2].to_vec(), limited);
+    assert_eq!(
+        all.len(),
+        service.count_departures(node.node.node_id, Some(date), None, &scope)?
+    );
+    Ok(())
+}
+
+#[test]
+fn no20_nearby_stops() -> Result<()> {
+    let database = create_fixture_db("nearby_stops", cmd::db::create::Opts::default())?;
+    let db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let mut service = StopServiceDb::new(db);
+
+    // 1_u の位置から100m以内は日本橋のstopのみ (1_p と 1_u はほぼ同じ位置なのでstop_id順)
+    let (lat, lon) = (35.68227523342356, 139.77405340600478);
+    let all = service.fetch_nearby_stops(lat, lon, 100.0, &Paging::default())?;
+    assert_eq!(
+        vec!["1_p", "1_u", "1_d"],
+        all.iter().map(|x| x.stop_id.as_str()).collect_vec()
+    );
+    assert!(all.windows(2).all(|x| x[0].distance <= x[1].distance));
+    assert!(all.iter().all(|x| x.distance <= 100.0));
+    assert_eq!(all.len(), service.count_nearby_stops(lat, lon, 100.0)?);
+
+    let paging = Paging {
+        limit: Some(2),
+        offset: 1,
+        ..Default::default()
+    };
+    let limited = service.fetch_nearby_stops(lat, lon, 100.0, &paging)?;
+    assert_eq!(all[1..3].to_vec(), limited);
+
+    // 半径を広げると件数が増える
+    assert!(service.count_nearby_stops(lat, lon, 5000.0)? > all.len());
+    Ok(())
+}
+
+#[test]
+fn no21_walking_transfers() -> Result<()> {
+    let database = create_fixture_db(
+        "walking_transfers",
+        cmd::db::create::Opts {
+            walking_transfer_distance: Some(100.0),
+            walking_speed: Some(0.1),
+            ..Default::default()
+        },
+    )?;
+    let mut db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+
+    // 事業者が提供した乗換はそのまま残し、同じ組は生成しない
+    let transfers = db.select_transfers()?;
+    let provided = transfers
+        .iter()
+        .find(|x| x.from_stop_id == "2_u" && x.to_stop_id == "2_d")
+        .unwrap();
+    assert_eq!(TransferType::Recommended, provided.transfer_type);
+    assert_eq!(None, provided.min_transfer_time);
+
+    // 生成した組は別のテーブルに距離とともに記録する
+    let walking_transfers = db.select_all::<WalkingTransfer>()?;
+    assert_eq!(
+        vec![
+            ("1_d", "1_u"),
+            ("1_u", "1_d"),
+            ("2_d", "2_u"),
+            ("4_d", "4_u"),
+            ("4_u", "4_d")
+        ],
+        walking_transfers
+            .iter()
+            .map(|x| (x.from_stop_id.as_str(), x.to_stop_id.as_str()))
+            .collect_vec()
+    );
+    for walking in &walking_transfers {
+        assert!(walking.distance.0 <= 100.0);
+        let transfer = transfers
+            .iter()
+            .find(|x| x.from_stop_id == walking.from_stop_id && x.to_stop_id == walking.to_stop_id)
+            .unwrap();
+        // 歩行速度が遅いため、バスの既定乗換時間より徒歩時間が長くなる
+        assert_eq!(
+            Some((walking.distance.0 / 0.1).ceil() as u32),
+            transfer.min_transfer_time
+        );
+    }
+
+    // transfersには独自の項目を持たない
+    let conn = rusqlite::Connection::open(&database)?;
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('transfers')")?;
+    let columns = stmt
+        .query_map(rusqlite::NO_PARAMS, |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    assert_eq!(
+        vec![
+            "from_stop_id",
+            "to_stop_id",
+            "transfer_type",
+            "min_transfer_time"
+        ],
+        columns
+    );
+    Ok(())
+}
+
+#[test]
+fn no22_config_paths_and_flag_overrides() -> Result<()> {
+    // 系統3 にroute名がないため、lenientでなければ作成に失敗する
+    let dir = std::env::temp_dir()
+        .join("diamant-tests")
+        .join("config_paths");
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(dir.join("gtfs"))?;
+    for src in &[
+        Path::new("tests/data"),
+        Path::new("tests/fixtures/missing_route_name"),
+    ] {
+        for entry in fs::read_dir(src)? {
+            let path = entry?.path();
+            fs::copy(&path, dir.join("gtfs").join(path.file_name().unwrap()))?;
+        }
+    }
+    let config_path = dir.join("diamant.yaml");
+    fs::write(
+        &config_path,
+        "
+feeds:
+  company1:
+    source: gtfs
+    database: db/gtfs.db
+    create:
+      lenient: true
+",
+    )?;
+    fs::create_dir_all(dir.join("db"))?;
+
+    // 相対パスは設定ファイルのディレクトリから解決する
+    let config = diamant::config::Config::load(Some(&config_path))?;
+    let feed = config.feed(Some("company1"))?;
+    assert_eq!(Some(dir.join("gtfs")), feed.source);
+    assert_eq!(dir.join("db/gtfs.db"), feed.database_or(None));
+
+    // 設定ファイルのlenientを--no-lenientで無効にできる
+    assert!(cmd::db::create::run(
+        &cmd::db::create::Opts {
+            no_lenient: true,
+            ..Default::default()
+        },
+        &feed,
+    )
+    .is_err());
+    cmd::db::create::run(&cmd::db::create::Opts::default(), &feed)?;
+    assert!(dir.join("db/gtfs.db").exists());
+
+    assert!(cmd::flag(true, false, Some(false)));
+    assert!(!cmd::flag(false, true, Some(true)));
+    assert!(cmd::flag(false, false, Some(true)));
+    assert!(!cmd::flag(false, false, None));
+    Ok(())
+}
+
+#[test]
+fn no23_unknown_feed_key_and_missing_schema() -> Result<()> {
+    let database = create_fixture_db("feeds_api", cmd::db::create::Opts::default())?;
+    let db_root = database.with_extension("root");
+    fs::create_dir_all(&db_root)?;
+    let feeds = Feeds::new(
+        db_root,
+        vec![("company1".to_string(), database.clone())]
+            .into_iter()
+            .collect(),
+        vec![("latest".to_string(), "company1".to_string())]
+            .into_iter()
+            .collect(),
+    );
+    feeds.load()?;
+    assert!(feeds.open("unknown")?.is_none());
+    assert!(feeds.open("latest")?.is_some());
+
+    // 有効なデータベースがないkeyは404にする
+    let rocket = rocket::custom(
+        rocket::config::Config::build(rocket::config::Environment::Development).finalize()?,
+    )
+    .manage(feeds)
+    .mount("/", cmd::serve::api_routes());
+    let client = rocket::local::Client::new(rocket)?;
+    assert_eq!(
+        rocket::http::Status::NotFound,
+        client.get("/unknown/agencies").dispatch().status()
+    );
+    assert_eq!(
+        rocket::http::Status::Ok,
+        client.get("/company1/agencies").dispatch().status()
+    );
+
+    // APIが参照するテーブルがなければ検証に失敗する
+    let copied = database.with_extension("missing.db");
+    fs::copy(&database, &copied)?;
+    for table in &[
+        "routes_jp",
+        "translations",
+        "pathways",
+        "fare_leg_rules",
+        "attributions",
+        "booking_rules",
+        "service_route_problems",
+        "walking_transfers",
+    ] {
+        rusqlite::Connection::open(&copied)?.execute(
+            format!("DROP TABLE {}", table).as_str(),
+            rusqlite::NO_PARAMS,
+        )?;
+    }
+    let missing = diamant::external::gtfsdb::GtfsDb::new(&copied)?.missing_schema()?;
+    assert_eq!(
+        vec![
+            "routes_jp",
+            "pathways",
+            "attributions",
+            "fare_leg_rules",
+            "booking_rules",
+            "translations",
+            "service_route_problems",
+            "walking_transfers",
+        ],
+        missing
+    );
+    Ok(())
+}
+
+#[test]
+fn no24_hot_reload() -> Result<()> {
+    let base = create_fixture_db("hot_reload", cmd::db::create::Opts::default())?;
+    let dir = base.with_extension("root");
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    let live = dir.join("gtfs.db");
+    fs::copy(&base, &live)?;
+    let feeds = Feeds::new(
+        dir.clone(),
+        vec![("company1".to_string(), live.clone())]
+            .into_iter()
+            .collect(),
+        Default::default(),
+    );
+    feeds.load()?;
+    let agency_name = |feeds: &Feeds| -> Result<String> {
+        let mut db = feeds.open("company1")?.unwrap();
+        Ok(db.select_all::<Agency>()?.remove(0).agency_name)
+    };
+    let before = agency_name(&feeds)?;
+    let mut opened = feeds.open("company1")?.unwrap();
+
+    // 別のファイルに書いてからrenameで差し替える
+    let replace = |sql: &str| -> Result<()> {
+        let next = dir.join("next.db");
+        fs::copy(&base, &next)?;
+        rusqlite::Connection::open(&next)?.execute(sql, rusqlite::NO_PARAMS)?;
+        fs::rename(&next, &live)?;
+        Ok(())
+    };
+    replace("UPDATE agency SET agency_name = '新しい事業者'")?;
+
+    // 前回の確認から変わっていないことを確かめてから有効にする
+    feeds.reload()?;
+    assert_eq!(before, agency_name(&feeds)?);
+    feeds.reload()?;
+    assert_eq!("新しい事業者", agency_name(&feeds)?);
+    // 差し替え前に開いたものは以前のデータベースを読み続ける
+    assert_eq!(before, opened.select_all::<Agency>()?.remove(0).agency_name);
+
+    // 検証に失敗したデータベースには差し替えない
+    replace("DROP TABLE translations")?;
+    feeds.reload()?;
+    feeds.reload()?;
+    assert_eq!("新しい事業者", agency_name(&feeds)?);
+
+    // 有効なスナップショットだけが残る
+    let snapshots = fs::read_dir(dir.join(".diamant-active"))?.count();
+    assert_eq!(1, snapshots);
+    Ok(())
+}
+
+#[test]
+fn no25_pagination_in_sql() -> Result<()> {
+    let database = create_fixture_db("pagination", cmd::db::create::Opts::default())?;
+    let db_root = database.with_extension("root");
+    fs::create_dir_all(&db_root)?;
+    let feeds = Feeds::new(
+        db_root,
+        vec![("company1".to_string(), database)]
+            .into_iter()
+            .collect(),
+        Default::default(),
+    );
+    feeds.load()?;
+    let rocket = rocket::custom(
+        rocket::config::Config::build(rocket::config::Environment::Development).finalize()?,
+    )
+    .manage(feeds)
+    .mount("/", cmd::serve::api_routes());
+    let client = rocket::local::Client::new(rocket)?;
+    let get = |url: &str| -> Result<serde_json::Value> {
+        let body = client.get(url).dispatch().body_string().unwrap_or_default();
+        Ok(serde_json::from_str(&body)?)
+    };
+    let trip_ids = |page: &serde_json::Value| -> Vec<String> {
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| x["trip_id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // ページングしてもtotalはページング前の件数で、itemsは全件の一部と一致する
+    for url in &[
+        "/company1/trips?date=20210512",
+        // 日本橋
+        "/company1/stop_time_details?stop_name_prefix=%E6%97%A5%E6%9C%AC%E6%A9%8B",
+        "/company1/stops?word=",
+        "/company1/nodes?word=",
+    ] {
+        let all = get(url)?;
+        let total = all["items"].as_array().unwrap().len();
+        assert!(total > 3);
+        assert_eq!(total as u64, all["total"].as_u64().unwrap());
+        let page = get(&format!("{}&limit=2&offset=1", url))?;
+        assert_eq!(all["total"], page["total"]);
+        assert_eq!(
+            all["items"].as_array().unwrap()[1..3],
+            page["items"].as_array().unwrap()[..]
+        );
+        let tail = get(&format!("{}&offset={}", url, total - 1))?;
+        assert_eq!(1, tail["items"].as_array().unwrap().len());
+    }
+
+    // 並び替える場合は全件を並び替えてからページングする
+    let all = trip_ids(&get("/company1/trips?date=20210512")?);
+    let sorted =
+        get("/company1/trips?date=20210512&sort=-trip_id&limit=2&offset=1&fields=trip_id")?;
+    let expected = all
+        .iter()
+        .sorted()
+        .rev()
+        .skip(1)
+        .take(2)
+        .cloned()
+        .collect_vec();
+    assert_eq!(expected, trip_ids(&sorted));
+    assert_eq!(all.len() as u64, sorted["total"].as_u64().unwrap());
+    assert_eq!(1, sorted["items"][0].as_object().unwrap().len());
+
+    // 並び替えもSQLで行うので、大きな一覧でも並び替えた先頭だけを取得できる
+    let departure_times = |page: &serde_json::Value| -> Vec<String> {
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| x["departure_time"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let all = departure_times(&get("/company1/stop_time_details")?);
+    let latest = get("/company1/stop_time_details?sort=-departure_time&limit=3")?;
+    let expected = all.iter().sorted().rev().take(3).cloned().collect_vec();
+    assert_eq!(expected, departure_times(&latest));
+    assert_eq!(all.len() as u64, latest["total"].as_u64().unwrap());
+
+    // 並び替えられない項目や解釈できない交通モードは400で、全件を返さない
+    for url in &[
+        "/company1/stop_time_details?sort=unknown&limit=3",
+        "/company1/routes?mode=tram_typo",
+    ] {
+        assert_eq!(
+            rocket::http::Status::BadRequest,
+            client.get(*url).dispatch().status()
+        );
+    }
+    Ok(())
+}
+
+/// スキーマの$refとallOfを展開して、項目名とnullableでない項目名を集める
+fn schema_fields(
+    spec: &serde_json::Value,
+    schema: &serde_json::Value,
+) -> (Vec<String>, Vec<String>) {
+    if let Some(name) = schema["$ref"].as_str() {
+        let name = name.trim_start_matches("#/components/schemas/");
+        return schema_fields(spec, &spec["components"]["schemas"][name]);
+    }
+    if let Some(all) = schema["allOf"].as_array() {
+        let (mut properties, mut required) = (vec![], vec![]);
+        for x in all {
+            let (p, r) = schema_fields(spec, x);
+            properties.extend(p);
+            required.extend(r);
+        }
+        return (properties, required);
+    }
+    let properties = schema["properties"]
+        .as_object()
+        .map(|x| x.keys().cloned().collect())
+        .unwrap_or_default();
+    let required = schema["required"]
+        .as_array()
+        .map(|x| x.iter().map(|x| x.as_str().unwrap().to_string()).collect())
+        .unwrap_or_default();
+    (properties, required)
+}
+
+#[test]
+fn no26_openapi_matches_routes_and_responses() -> Result<()> {
+    let spec = diamant::api::openapi::spec();
+
+    // mountするすべてのルートがドキュメントにある (パラメータは{}で囲む)
+    let rocket = cmd::serve::mount(
+        rocket::custom(
+            rocket::config::Config::build(rocket::config::Environment::Development).finalize()?,
+        ),
+        true,
+    );
+    let routes = rocket
+        .routes()
+        .map(|x| {
+            let path = x
+                .uri
+                .path()
+                .replace('<', "{")
+                .replace('>', "}")
+                .replace("..}", "}");
+            (x.method.as_str().to_lowercase(), path)
+        })
+        .sorted()
+        .collect_vec();
+    let documented = spec["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, x)| {
+            x.as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| (method.clone(), path.clone()))
+        })
+        .sorted()
+        .collect_vec();
+    assert_eq!(routes, documented);
+
+    // 整数で表す列挙型は値を列挙する
+    let schemas = &spec["components"]["schemas"];
+    assert_eq!(
+        serde_json::json!([0, 1, 2, 3, 4]),
+        schemas["Stop"]["properties"]["location_type"]["enum"]
+    );
+    assert_eq!(
+        serde_json::json!([0, 1]),
+        schemas["Trip"]["properties"]["direction_id"]["enum"]
+    );
+    let route_type = &schemas["Route"]["properties"]["route_type"];
+    assert!(route_type["enum"].is_null());
+    assert!(route_type["description"].as_str().unwrap().contains("1700"));
+
+    // 実際のレスポンスの項目がスキーマと一致する
+    let database = create_fixture_db("openapi", cmd::db::create::Opts::default())?;
+    let db_root = database.with_extension("root");
+    fs::create_dir_all(&db_root)?;
+    let feeds = Feeds::new(
+        db_root,
+        vec![("company1".to_string(), database)]
+            .into_iter()
+            .collect(),
+        Default::default(),
+    );
+    feeds.load()?;
+    let client = rocket::local::Client::new(
+        rocket::custom(
+            rocket::config::Config::build(rocket::config::Environment::Development).finalize()?,
+        )
+        .manage(feeds)
+        .mount("/", cmd::serve::api_routes()),
+    )?;
+    for (url, path) in &[
+        ("/company1/stops?word=", "/{key}/stops"),
+        ("/company1/nodes", "/{key}/nodes"),
+        ("/company1/routes", "/{key}/routes"),
+        ("/company1/agencies", "/{key}/agencies"),
+        ("/company1/service_routes", "/{key}/service_routes"),
+        ("/company1/trips?with_stop_times=true", "/{key}/trips"),
+        ("/company1/stop_time_details", "/{key}/stop_time_details"),
+    ] {
+        let body = client.get(*url).dispatch().body_string().unwrap();
+        let page: serde_json::Value = serde_json::from_str(&body)?;
+        let item = &spec["paths"][*path]["get"]["responses"]["200"]["content"]["application/json"]
+            ["schema"]["properties"]["items"]["items"];
+        let (properties, required) = schema_fields(&spec, item);
+        let items = page["items"].as_array().unwrap();
+        assert!(!items.is_empty(), "{}", url);
+        for x in items {
+            let x = x.as_object().unwrap();
+            for key in x.keys() {
+                assert!(properties.contains(key), "{} has undocumented {}", url, key);
+            }
+            for key in &required {
+                assert!(x.contains_key(key), "{} lacks required {}", url, key);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn no27_graphql_depth_and_list_limits() -> Result<()> {
+    let database = create_fixture_db("graphql", cmd::db::create::Opts::default())?;
+    let execute = |query: &str, limits: &GraphqlLimits| -> Result<GraphqlResponse> {
+        let db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+        let request = GraphqlRequest {
+            query: query.to_string(),
+            variables: None,
+            operation_name: None,
+        };
+        Ok(GraphqlServiceDb::new(db).execute(&request, limits))
+    };
+    let limits = GraphqlLimits {
+        max_depth: 6,
+        max_complexity: 200,
+        default_limit: 2,
+        max_limit: 3,
+    };
+    let error = |response: GraphqlResponse| -> String {
+        assert!(response.data.is_none());
+        response.errors[0].message.clone()
+    };
+
+    // 深い入れ子はスタックを使い切る前に解釈をやめる
+    let deep = format!("{}{}", "{ stops ".repeat(200_000), "}".repeat(200_000));
+    assert!(error(execute(&deep, &limits)?).contains("深さ"));
+    let nested = format!(
+        "{{ stops(stop_id: {}{}) {{ stop_id }} }}",
+        "[".repeat(200_000),
+        "]".repeat(200_000)
+    );
+    assert!(error(execute(&nested, &limits)?).contains("入れ子"));
+    let typed = format!(
+        "query Q($a: {}ID{}) {{ stops {{ stop_id }} }}",
+        "[".repeat(200_000),
+        "]".repeat(200_000)
+    );
+    assert!(error(execute(&typed, &limits)?).contains("入れ子"));
+    let seven =
+        "{ stops { parent { parent { parent { parent { parent { parent { stop_id } } } } } } } }";
+    assert!(error(execute(seven, &limits)?).contains("深さ"));
+
+    // limitを省略したリストは既定の件数まで、上限を超えるlimitはエラー
+    let trips = |query: &str, limits: &GraphqlLimits| -> Result<Vec<serde_json::Value>> {
+        let response = execute(query, limits)?;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        Ok(response.data.unwrap()["trips"].as_array().unwrap().clone())
+    };
+    let limited = trips(
+        "{ trips { trip_id stop_times { stop_sequence } } }",
+        &limits,
+    )?;
+    assert_eq!(2, limited.len());
+    assert!(limited
+        .iter()
+        .all(|x| x["stop_times"].as_array().unwrap().len() == 2));
+    assert!(error(execute("{ trips(limit: 4) { trip_id } }", &limits)?).contains("limit"));
+    // u32に収まらない値は切り捨てずにエラー (4294967298は切り捨てると2)
+    for query in &[
+        "{ trips(limit: 4294967298) { trip_id } }",
+        "{ trips(offset: 4294967296) { trip_id } }",
+    ] {
+        assert!(error(execute(query, &limits)?).contains("0以上の整数"));
+    }
+
+    // 関連のlimit・offsetは親ごとに適用する
+    let unlimited = GraphqlLimits {
+        default_limit: 1000,
+        max_limit: 1000,
+        ..limits
+    };
+    let all = trips(
+        "{ trips(limit: 3) { trip_id stop_times { stop_sequence } } }",
+        &unlimited,
+    )?;
+    let paged = trips(
+        "{ trips(limit: 3) { trip_id stop_times(offset: 1, limit: 2) { stop_sequence } } }",
+        &unlimited,
+    )?;
+    assert_eq!(3, paged.len());
+    for (a, p) in all.iter().zip(&paged) {
+        let a = a["stop_times"].as_array().unwrap();
+        assert!(a.len() > 3);
+        assert_eq!(a[1..3], p["stop_times"].as_array().unwrap()[..]);
+    }
+    Ok(())
+}
+
+#[test]
+fn no28_trips_with_stop_times_are_paged_first() -> Result<()> {
+    let database = create_fixture_db("trip_stop_times", cmd::db::create::Opts::default())?;
+    let db_root = database.with_extension("root");
+    fs::create_dir_all(&db_root)?;
+    let feeds = Feeds::new(
+        db_root,
+        vec![("company1".to_string(), database.clone())]
+            .into_iter()
+            .collect(),
+        Default::default(),
+    );
+    feeds.load()?;
+    let client = rocket::local::Client::new(
+        rocket::custom(
+            rocket::config::Config::build(rocket::config::Environment::Development).finalize()?,
+        )
+        .manage(feeds)
+        .mount("/", cmd::serve::api_routes()),
+    )?;
+    let items = |url: &str| -> Result<Vec<serde_json::Value>> {
+        let body = client.get(url).dispatch().body_string().unwrap_or_default();
+        let page: serde_json::Value = serde_json::from_str(&body)?;
+        Ok(page["items"].as_array().unwrap().clone())
+    };
+
+    let url = "/company1/trips?date=20210512&with_stop_times=true";
+    let all = items(url)?;
+    assert!(all.len() > 3);
+    assert!(all
+        .iter()
+        .all(|x| !x["stop_times"].as_array().unwrap().is_empty()));
+    assert_eq!(all[1..3], items(&format!("{}&limit=2&offset=1", url))?[..]);
+
+    // 並び替えてからページに含めるtripのstop_timeを取得する
+    let last = all
+        .iter()
+        .max_by_key(|x| x["trip_id"].as_str().unwrap().to_string())
+        .unwrap();
+    assert_eq!(
+        vec![last.clone()],
+        items(&format!("{}&sort=-trip_id&limit=1", url))?
+    );
+
+    // 解釈できない絞り込みは無視せずに400
+    for query in &[
+        "date=2021-05-10",
+        "direction_id=2",
+        "mode=tram_typo",
+        "from=25:00",
+        "with_stop_times=yes",
+    ] {
+        assert_eq!(
+            rocket::http::Status::BadRequest,
+            client
+                .get(format!("/company1/trips?{}", query))
+                .dispatch()
+                .status()
+        );
+    }
+
+    // ページングしたtripのstop_timeのみ取得する
+    let db = diamant::external::gtfsdb::GtfsDb::new(&database)?;
+    let trips = TripServiceDb::new(db).fetch_trips(
+        None,
+        None,
+        Some(NaiveDate::from_ymd(2021, 5, 12)),
+        TripQuery::default(),
+        true,
+        &Paging {
+            limit: Some(1),
+            offset: 0,
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(1, trips.len());
+    let stop_times = trips[0].stop_times.as_ref().unwrap();
+    assert!(stop_times
+        .iter()
+        .all(|x| x.trip_id == trips[0].trip.trip_id));
+    assert_eq!(
+        all[0]["stop_times"].as_array().unwrap().len(),
+        stop_times.len()
+    );
+    Ok(())
+}
+
+#[test]
+fn no29_route_details_are_counted_per_filtered_route() -> Result<()> {
+    let database = create_fixture_db("route_details", cmd::db::create::Opts::default())?;
+    let mut service = RouteServiceDb::new(diamant::external::gtfsdb::GtfsDb::new(&database)?);
+    let all =
+        service.fetch_route_details(None, None, &OperatorScope::default(), &Paging::default())?;
+    assert!(!all.is_empty());
+
+    // route_idで絞り込んでも各数は変わらない
+    for detail in &all {
+        let route = service.fetch_route(detail.route_id.clone())?.unwrap();
+        assert_eq!(detail, &route.route);
+        assert_eq!(detail.stop_count as usize, route.stops.len());
+        assert_eq!(
+            detail.service_route_count as usize,
+            route.service_routes.len()
+        );
+    }
+
+    // 交通モードはSQLで絞り込む
+    for mode in &[TransitMode::Bus, TransitMode::Rail] {
+        let expected = all
+            .iter()
+            .filter(|x| &x.route_type.mode() == mode)
+            .cloned()
+            .collect_vec();
+        assert_eq!(
+            expected,
+            service.fetch_route_details(
+                None,
+                Some(mode),
+                &OperatorScope::default(),
+                &Paging::default()
+            )?
+        );
+    }
+    let rails = service.fetch_route_details(
+        None,
+        Some(&TransitMode::Rail),
+        &OperatorScope::default(),
+        &Paging::default(),
+    )?;
+    assert_eq!(
+        vec!["系統3"],
+        rails.iter().map(|x| x.route_id.as_str()).collect_vec()
+    );
+
+    // 並び替えとページングもSQLで行い、件数は別に数える
+    let paged = service.fetch_route_details(
+        None,
+        None,
+        &OperatorScope::default(),
+        &Paging {
+            limit: Some(1),
+            offset: 1,
+            sort: vec![SortKey {
+                column: "trip_count",
+                descending: true,
+            }],
+        },
+    )?;
+    let expected = all
+        .iter()
+        .sorted_by(|a, b| b.trip_count.cmp(&a.trip_count))
+        .skip(1)
+        .take(1)
+        .cloned()
+        .collect_vec();
+    assert_eq!(expected, paged);
+    assert_eq!(
+        all.len(),
+        service.count_route_details(None, None, &OperatorScope::default())?
+    );
+    Ok(())
+}
+
+#[test]
+fn no30_primary_key_names_match_tables() -> Result<()> {
+    use diamant::external::gtfs::extended::{
+        extras::ExtraRecord, nodes::Node, nodes::Stop2Node,
+        service_route_merges::ServiceRouteMerge, service_route_problems::ServiceRouteProblem,
+        service_routes::ServiceRoute, trips2service_routes::Trip2ServiceRoute,
+        walking_transfers::WalkingTransfer,
+    };
+    use diamant::external::gtfs::{
+        agency_jp::AgencyJp, areas::Area, attributions::Attribution, booking_rules::BookingRule,
+        calendar::Calendar, calendar_dates::CalendarDate, fare_attributes::FareAttribute,
+        fare_leg_rules::FareLegRule, fare_media::FareMedia, fare_products::FareProduct,
+        fare_rules::FareRule, fare_transfer_rules::FareTransferRule, feed_info::Feed,
+        frequencies::Frequency, levels::Level, networks::Network, office_jp::OfficeJp,
+        pathways::Pathway, route_networks::RouteNetwork, routes::Route, routes_jp::RouteJp,
+        shapes::Shape, stop_areas::StopArea, stop_times::StopTime, stops::Stop,
+        transfers::Transfer, translations::Translation, trips::Trip,
+    };
+    use diamant::external::gtfsdb::Table;
+
+    let database = create_fixture_db("primary_keys", Default::default())?;
+    let conn = rusqlite::Connection::open(&database)?;
+    // PRAGMA table_infoのpkは主キー内の順番 (主キーでなければ0)
+    let primary_keys_of = |table: &str| -> Result<Vec<String>> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let columns = stmt
+            .query_map(rusqlite::NO_PARAMS, |row| {
+                Ok((row.get::<_, i64>("pk")?, row.get::<_, String>("name")?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        assert!(!columns.is_empty(), "{} does not exist", table);
+        Ok(columns
+            .into_iter()
+            .filter(|(pk, _)| *pk > 0)
+            .sorted()
+            .map(|(_, name)| name)
+            .collect())
+    };
+    macro_rules! assert_primary_keys {
+        ($($table:ty),*) => {
+            $(
+                assert_eq!(
+                    primary_keys_of(<$table>::table_name())?,
+                    <$table>::primary_key_names(),
+                    "{}",
+                    <$table>::table_name()
+                );
+            )*
+        };
+    }
+    assert_primary_keys!(
+        Agency,
+        AgencyJp,
+        Stop,
+        Route,
+        RouteJp,
+        Trip,
+        OfficeJp,
+        StopTime,
+        Calendar,
+        CalendarDate,
+        FareAttribute,
+        FareRule,
+        Shape,
+        Frequency,
+        Transfer,
+        Level,
+        Pathway,
+        Attribution,
+        FareMedia,
+        FareProduct,
+        FareLegRule,
+        FareTransferRule,
+        Area,
+        StopArea,
+        Network,
+        RouteNetwork,
+        BookingRule,
+        Feed,
+        Translation,
+        Trip2ServiceRoute,
+        ServiceRoute,
+        ServiceRouteMerge,
+        ServiceRouteProblem,
+        Node,
+        Stop2Node,
+        ExtraRecord,
+        WalkingTransfer
+    );
     Ok(())
 }